@@ -9,6 +9,31 @@
 //! integration-test-commons = { git = "https://github.com/stackabletech/integration-test-commons.git", tag = "0.1.0" }
 //! ```
 //!
+//! The following features are enabled by default and can be disabled
+//! to reduce the dependency tree:
+//!
+//! * `native-tls` - TLS backend of the Kubernetes client; alternatively
+//!   `rustls-tls` can be enabled, one of both is required
+//! * `copy` - copying files to and from pods
+//! * `port-forward` - port forwarding to pods and WebSocket checks
+//!
+//! The following features must be enabled explicitly:
+//!
+//! * `grpc-health` - checks of the gRPC health checking protocol
+//! * `http-probe` - authenticated HTTP probes of product endpoints
+//! * `tracing-progress` - progress reporting as `tracing` events via
+//!   `KUBE_TEST_PROGRESS=tracing`
+//!
+//! ```toml
+//! [dependencies]
+//! integration-test-commons = { git = "https://github.com/stackabletech/integration-test-commons.git", tag = "0.1.0", default-features = false, features = ["rustls-tls"] }
+//! ```
+//!
+//! The Helm installer, the balloon pods and preemption scenarios, and
+//! the metrics are not gated because they only use the Kubernetes client
+//! and add no dependencies, so gating them would not reduce the build
+//! time.
+//!
 //! Then `use` the prelude module in your test files:
 //!
 //! ```rust
@@ -44,5 +69,10 @@
 //! }
 //! ```
 
+#![allow(clippy::test_attr_in_doctest)]
+
+#[cfg(not(any(feature = "native-tls", feature = "rustls-tls")))]
+compile_error!("One of the features `native-tls` or `rustls-tls` must be enabled.");
+
 pub mod operator;
 pub mod test;
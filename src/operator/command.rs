@@ -0,0 +1,197 @@
+//! Typed commands for operators which do not define their own types yet
+//!
+//! Stackable operators accept the commands `Restart`, `Start`, and
+//! `Stop` which share a common schema but are defined in the API group
+//! of each operator. The group and version are provided by an
+//! implementation of [`CommandGroup`], so command tests can be written
+//! with the generic types of this module.
+//!
+//! ```rust,no_run
+//! use integration_test_commons::operator::command::{CommandGroup, Restart};
+//! use integration_test_commons::test::prelude::*;
+//!
+//! #[derive(Clone, Debug)]
+//! struct ZooKeeperCommands;
+//!
+//! impl CommandGroup for ZooKeeperCommands {
+//!     const GROUP: &'static str = "command.zookeeper.stackable.tech";
+//!     const VERSION: &'static str = "v1alpha1";
+//! }
+//!
+//! let client = TestKubeClient::new();
+//! let restart = Restart::<ZooKeeperCommands>::new("restart-simple", "simple");
+//! client.apply::<Restart<ZooKeeperCommands>>(&serde_yaml::to_string(&restart).unwrap());
+//! ```
+
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, Time};
+use kube::Resource;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::marker::PhantomData;
+
+/// API group and version of the commands of an operator
+pub trait CommandGroup {
+    /// API group, e.g. `command.zookeeper.stackable.tech`
+    const GROUP: &'static str;
+    /// API version, e.g. `v1alpha1`
+    const VERSION: &'static str;
+}
+
+/// Specification which is common to all commands
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandSpec {
+    /// Name of the cluster the command is applied to
+    pub name: String,
+    /// Whether the pods are processed one after another
+    #[serde(default)]
+    pub rolling: bool,
+    /// Roles the command is restricted to; all roles if not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roles: Option<Vec<String>>,
+}
+
+/// Status which is common to all commands
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandStatus {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<Time>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<Time>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl CommandStatus {
+    /// Returns true if the operator finished the command.
+    pub fn is_finished(&self) -> bool {
+        self.finished_at.is_some()
+    }
+}
+
+/// Defines a command type with the given kind and plural name.
+macro_rules! command {
+    ($(#[$attr:meta])* $kind:ident, $plural:literal) => {
+        $(#[$attr])*
+        ///
+        /// The type parameter determines the API group and version.
+        #[derive(Clone, Debug, Deserialize, Serialize)]
+        #[serde(rename_all = "camelCase")]
+        pub struct $kind<G> {
+            pub api_version: String,
+            pub kind: String,
+            pub metadata: ObjectMeta,
+            pub spec: CommandSpec,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub status: Option<CommandStatus>,
+            #[serde(skip)]
+            group: PhantomData<fn() -> G>,
+        }
+
+        impl<G: CommandGroup> $kind<G> {
+            /// Creates a command with the given name which is applied to
+            /// all roles of the given cluster.
+            pub fn new(name: &str, cluster_name: &str) -> Self {
+                $kind {
+                    api_version: Self::api_version(&()).into_owned(),
+                    kind: Self::kind(&()).into_owned(),
+                    metadata: ObjectMeta {
+                        name: Some(name.to_string()),
+                        ..Default::default()
+                    },
+                    spec: CommandSpec {
+                        name: cluster_name.to_string(),
+                        ..Default::default()
+                    },
+                    status: None,
+                    group: PhantomData,
+                }
+            }
+        }
+
+        impl<G: CommandGroup> Resource for $kind<G> {
+            type DynamicType = ();
+
+            fn kind(_: &()) -> Cow<'_, str> {
+                stringify!($kind).into()
+            }
+
+            fn group(_: &()) -> Cow<'_, str> {
+                G::GROUP.into()
+            }
+
+            fn version(_: &()) -> Cow<'_, str> {
+                G::VERSION.into()
+            }
+
+            fn api_version(_: &()) -> Cow<'_, str> {
+                format!("{}/{}", G::GROUP, G::VERSION).into()
+            }
+
+            fn plural(_: &()) -> Cow<'_, str> {
+                $plural.into()
+            }
+
+            fn meta(&self) -> &ObjectMeta {
+                &self.metadata
+            }
+
+            fn meta_mut(&mut self) -> &mut ObjectMeta {
+                &mut self.metadata
+            }
+        }
+    };
+}
+
+command!(
+    /// Command to restart the pods of a cluster
+    Restart,
+    "restarts"
+);
+command!(
+    /// Command to start a stopped cluster
+    Start,
+    "starts"
+);
+command!(
+    /// Command to stop the pods of a cluster
+    Stop,
+    "stops"
+);
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use indoc::indoc;
+    use spectral::prelude::*;
+
+    #[derive(Clone, Debug)]
+    struct TestCommands;
+
+    impl CommandGroup for TestCommands {
+        const GROUP: &'static str = "command.test.stackable.tech";
+        const VERSION: &'static str = "v1alpha1";
+    }
+
+    #[test]
+    fn command_should_be_serialized_with_the_group_and_version() {
+        let mut restart = Restart::<TestCommands>::new("restart-simple", "simple");
+        restart.spec.rolling = true;
+
+        assert_that(&serde_yaml::to_string(&restart).unwrap()).is_equal_to(String::from(indoc! {"
+            ---
+            apiVersion: command.test.stackable.tech/v1alpha1
+            kind: Restart
+            metadata:
+              name: restart-simple
+            spec:
+              name: simple
+              rolling: true
+        "}));
+        assert_that(&Stop::<TestCommands>::url_path(&(), Some("default"))).is_equal_to(
+            String::from("/apis/command.test.stackable.tech/v1alpha1/namespaces/default/stops"),
+        );
+    }
+}
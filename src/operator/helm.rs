@@ -0,0 +1,206 @@
+//! Installation of operators via Helm
+//!
+//! The operators are installed with the `helm` binary which must be
+//! available on the `PATH`.
+
+use anyhow::{anyhow, Result};
+use serde_yaml::{Mapping, Value};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Values for a Helm chart of an operator
+///
+/// The keys follow the conventions of the Stackable operator charts.
+/// Further values can be set with [`HelmValues::set`].
+///
+/// ```rust
+/// use integration_test_commons::operator::helm::HelmValues;
+///
+/// let values = HelmValues::new()
+///     .image("docker.stackable.tech/stackable/zookeeper-operator", "0.5.0")
+///     .watch_namespace("test")
+///     .log_level("debug");
+///
+/// assert!(values.to_yaml().contains("watchNamespace: test"));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HelmValues {
+    values: Mapping,
+}
+
+impl HelmValues {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the image repository and tag of the operator.
+    pub fn image(self, repository: &str, tag: &str) -> Self {
+        self.set(&["image", "repository"], repository)
+            .set(&["image", "tag"], tag)
+    }
+
+    /// Sets the resource requests and limits of the operator container.
+    pub fn resources(self, cpu: &str, memory: &str) -> Self {
+        self.set(&["resources", "requests", "cpu"], cpu)
+            .set(&["resources", "requests", "memory"], memory)
+            .set(&["resources", "limits", "cpu"], cpu)
+            .set(&["resources", "limits", "memory"], memory)
+    }
+
+    /// Restricts the operator to watch only the given namespace.
+    pub fn watch_namespace(self, namespace: &str) -> Self {
+        self.set(&["watchNamespace"], namespace)
+    }
+
+    /// Sets the log level of the operator.
+    pub fn log_level(self, log_level: &str) -> Self {
+        self.set(&["logLevel"], log_level)
+    }
+
+    /// Sets the value at the given path.
+    ///
+    /// Intermediate mappings are created if they do not exist.
+    pub fn set<V: Into<Value>>(mut self, path: &[&str], value: V) -> Self {
+        let (last_key, parent_keys) = path.split_last().expect("Path must not be empty");
+
+        let mut mapping = &mut self.values;
+        for key in parent_keys {
+            let entry = mapping
+                .entry(Value::from(*key))
+                .or_insert_with(|| Value::Mapping(Mapping::new()));
+            if !entry.is_mapping() {
+                *entry = Value::Mapping(Mapping::new());
+            }
+            mapping = entry.as_mapping_mut().unwrap();
+        }
+        mapping.insert(Value::from(*last_key), value.into());
+
+        self
+    }
+
+    /// Renders the values as YAML.
+    pub fn to_yaml(&self) -> String {
+        serde_yaml::to_string(&self.values).expect("Helm values could not be serialized")
+    }
+}
+
+/// A Helm release of an operator
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HelmRelease {
+    pub name: String,
+    pub chart: String,
+    pub namespace: String,
+    pub timeout: Duration,
+}
+
+impl HelmRelease {
+    pub fn new(name: &str, chart: &str, namespace: &str) -> Self {
+        HelmRelease {
+            name: name.to_string(),
+            chart: chart.to_string(),
+            namespace: namespace.to_string(),
+            timeout: Duration::from_secs(300),
+        }
+    }
+
+    /// Installs the release with the given values or upgrades it if it
+    /// is already installed, and waits until the rollout is finished.
+    ///
+    /// Calling this method again with changed values re-installs the
+    /// operator with the new configuration.
+    pub fn install(&self, values: &HelmValues) -> Result<()> {
+        let timeout = format!("{}s", self.timeout.as_secs());
+        self.helm(
+            &[
+                "upgrade",
+                "--install",
+                &self.name,
+                &self.chart,
+                "--namespace",
+                &self.namespace,
+                "--values",
+                "-",
+                "--wait",
+                "--timeout",
+                &timeout,
+            ],
+            Some(&values.to_yaml()),
+        )
+    }
+
+    /// Uninstalls the release.
+    pub fn uninstall(&self) -> Result<()> {
+        self.helm(
+            &["uninstall", &self.name, "--namespace", &self.namespace],
+            None,
+        )
+    }
+
+    /// Runs `helm` with the given arguments and passes the input via
+    /// stdin.
+    fn helm(&self, args: &[&str], input: Option<&str>) -> Result<()> {
+        let mut child = Command::new("helm")
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        if let Some(input) = input {
+            child
+                .stdin
+                .take()
+                .expect("stdin is piped")
+                .write_all(input.as_bytes())?;
+        }
+
+        let output = child.wait_with_output()?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "helm {} failed for release [{}]: {}",
+                args[0],
+                self.name,
+                String::from_utf8_lossy(&output.stderr)
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn helm_values_should_be_rendered_as_nested_yaml() {
+        let values = HelmValues::new()
+            .image("operator", "1.0.0")
+            .resources("100m", "128Mi")
+            .watch_namespace("test")
+            .log_level("debug");
+
+        let rendered: Value = serde_yaml::from_str(&values.to_yaml()).unwrap();
+
+        assert_that(&rendered["image"]["repository"].as_str()).contains_value("operator");
+        assert_that(&rendered["image"]["tag"].as_str()).contains_value("1.0.0");
+        assert_that(&rendered["resources"]["limits"]["memory"].as_str()).contains_value("128Mi");
+        assert_that(&rendered["watchNamespace"].as_str()).contains_value("test");
+        assert_that(&rendered["logLevel"].as_str()).contains_value("debug");
+    }
+
+    #[test]
+    fn helm_values_should_replace_scalars_with_mappings() {
+        let values = HelmValues::new()
+            .set(&["image"], "operator")
+            .set(&["image", "tag"], "1.0.0");
+
+        let rendered: Value = serde_yaml::from_str(&values.to_yaml()).unwrap();
+
+        assert_that(&rendered["image"]["tag"].as_str()).contains_value("1.0.0");
+    }
+}
@@ -0,0 +1,157 @@
+//! Callbacks for lifecycle events of the resources of a test cluster
+//!
+//! The resources are watched in the background and the callbacks are
+//! called on a dedicated thread, so they may block, e.g. to inject a
+//! failure with their own [`TestKubeClient`].
+
+use crate::test::connectivity::is_connectivity_error;
+use crate::test::prelude::{KubeClient, TestKubeClient};
+
+use anyhow::Result;
+use futures::{StreamExt, TryStreamExt};
+use kube::api::{Api, ListParams, WatchEvent};
+use kube::{Resource, ResourceExt};
+use serde::de::DeserializeOwned;
+use std::fmt::Debug;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use tokio::task::JoinHandle;
+
+/// A subscription to the events of resources
+///
+/// The callback of the subscription is not called anymore when this
+/// value is dropped.
+pub struct Subscription {
+    watch: JoinHandle<()>,
+    dispatcher: Option<thread::JoinHandle<()>>,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.watch.abort();
+        if let Some(dispatcher) = self.dispatcher.take() {
+            let _ = dispatcher.join();
+        }
+    }
+}
+
+/// Watches the resources selected by the list parameters and passes
+/// the events which occur after the subscription to the handler.
+///
+/// The handler is called on a dedicated thread in the order of the
+/// events.
+pub(crate) fn subscribe<K, H>(
+    client: &TestKubeClient,
+    api: Api<K>,
+    list_params: ListParams,
+    mut handle: H,
+) -> Subscription
+where
+    K: Clone + Debug + DeserializeOwned + Resource + Send + 'static,
+    H: FnMut(WatchEvent<K>) + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    let kube_client = client
+        .kube_client()
+        .in_namespace(client.kube_client().namespace());
+
+    let watch = client.spawn(async move {
+        if let Err(error) = forward_events(&kube_client, &api, &list_params, sender).await {
+            eprintln!("Watch of the subscription failed: {}", error);
+        }
+    });
+
+    let dispatcher = thread::spawn(move || {
+        for event in receiver {
+            handle(event);
+        }
+    });
+
+    Subscription {
+        watch,
+        dispatcher: Some(dispatcher),
+    }
+}
+
+/// Sends the events of the watched resources to the given sender until
+/// the receiver is dropped.
+///
+/// The watch is restarted from the last seen resource version whenever
+/// the API server closes it or the connection is interrupted, e.g. by a
+/// timeout. The reconnects are retried according to the reconnect policy
+/// of the client. If the API server reports an error, e.g. `410 Gone`
+/// because the resource version is too old, then the watch is restarted
+/// from the current resource version.
+async fn forward_events<K>(
+    client: &KubeClient,
+    api: &Api<K>,
+    list_params: &ListParams,
+    sender: Sender<WatchEvent<K>>,
+) -> Result<()>
+where
+    K: Clone + Debug + DeserializeOwned + Resource,
+{
+    let mut resource_version = client
+        .retry_on_disconnect(|| current_resource_version(api, list_params))
+        .await?;
+
+    loop {
+        let version = resource_version.clone();
+        let mut stream = client
+            .retry_on_disconnect(|| async { Ok(api.watch(list_params, &version).await?.boxed()) })
+            .await?;
+
+        loop {
+            let event = match stream.try_next().await {
+                Ok(Some(event)) => event,
+                Ok(None) => break,
+                Err(error) => {
+                    let error = anyhow::Error::from(error);
+                    if is_connectivity_error(&error) {
+                        eprintln!("Watch of the subscription is restarted: {}", error);
+                        break;
+                    }
+                    return Err(error);
+                }
+            };
+
+            match &event {
+                WatchEvent::Added(resource)
+                | WatchEvent::Modified(resource)
+                | WatchEvent::Deleted(resource) => {
+                    if let Some(version) = resource.resource_version() {
+                        resource_version = version;
+                    }
+                }
+                WatchEvent::Bookmark(bookmark) => {
+                    resource_version = bookmark.metadata.resource_version.clone();
+                }
+                WatchEvent::Error(error) => {
+                    eprintln!("Watch of the subscription is restarted: {}", error.message);
+                    resource_version = client
+                        .retry_on_disconnect(|| current_resource_version(api, list_params))
+                        .await?;
+                    break;
+                }
+            }
+
+            if sender.send(event).is_err() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Lists the watched resources and returns the resource version of the
+/// list.
+async fn current_resource_version<K>(api: &Api<K>, list_params: &ListParams) -> Result<String>
+where
+    K: Clone + Debug + DeserializeOwned + Resource,
+{
+    Ok(api
+        .list(list_params)
+        .await?
+        .metadata
+        .resource_version
+        .unwrap_or_default())
+}
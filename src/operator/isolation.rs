@@ -0,0 +1,212 @@
+//! Assertions for the isolation of cluster instances
+
+use super::setup::TestCluster;
+use crate::test::prelude::{ConfigMap, Pod, Service};
+
+use anyhow::{anyhow, Result};
+use kube::{Resource, ResourceExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fmt::Debug;
+
+/// Verifies that the resources generated for two instances of the same
+/// product do not reference each other.
+///
+/// Both clusters must be up and running, e.g. created with
+/// [`TestCluster::create_or_update`], and should reside in the same
+/// namespace. The following references are detected in both
+/// directions:
+///
+/// * selectors of services which match pods of the other instance
+/// * config maps, e.g. discovery data, which contain the name of the
+///   other instance
+/// * persistent volume claims which are mounted by pods of both
+///   instances
+///
+/// This catches bugs in label selectors which only surface if several
+/// instances are deployed.
+pub fn verify_no_cross_instance_interference<T>(
+    instance_a: &TestCluster<T>,
+    instance_b: &TestCluster<T>,
+) -> Result<()>
+where
+    T: Clone + Debug + DeserializeOwned + Resource + Serialize,
+    <T as Resource>::DynamicType: Clone,
+{
+    let a = InstanceResources::of(instance_a);
+    let b = InstanceResources::of(instance_b);
+
+    let violations = [a.find_references_to(&b), b.find_references_to(&a)].concat();
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Instances [{}] and [{}] interfere with each other:\n{}",
+            a.name,
+            b.name,
+            violations.join("\n")
+        ))
+    }
+}
+
+/// The resources generated for an instance
+struct InstanceResources {
+    name: String,
+    services: Vec<Service>,
+    config_maps: Vec<ConfigMap>,
+    pods: Vec<Pod>,
+}
+
+impl InstanceResources {
+    fn of<T>(cluster: &TestCluster<T>) -> Self
+    where
+        T: Clone + Debug + DeserializeOwned + Resource + Serialize,
+        <T as Resource>::DynamicType: Clone,
+    {
+        InstanceResources {
+            name: cluster.name().to_string(),
+            services: cluster.list(None),
+            config_maps: cluster.list(None),
+            pods: cluster.list(None),
+        }
+    }
+
+    /// Returns descriptions of all references from the resources of
+    /// this instance to the other instance.
+    fn find_references_to(&self, other: &InstanceResources) -> Vec<String> {
+        let mut violations = Vec::new();
+
+        for service in &self.services {
+            let selector = service
+                .spec
+                .as_ref()
+                .and_then(|spec| spec.selector.as_ref())
+                .filter(|selector| !selector.is_empty());
+            if let Some(selector) = selector {
+                for pod in &other.pods {
+                    let labels = pod.labels();
+                    if selector
+                        .iter()
+                        .all(|(key, value)| labels.get(key) == Some(value))
+                    {
+                        violations.push(format!(
+                            "Service [{}] selects pod [{}] of instance [{}].",
+                            service.name(),
+                            pod.name(),
+                            other.name
+                        ));
+                    }
+                }
+            }
+        }
+
+        for config_map in &self.config_maps {
+            let references_other = config_map
+                .data
+                .iter()
+                .flatten()
+                .any(|(_, value)| value.contains(&other.name));
+            if references_other {
+                violations.push(format!(
+                    "Config map [{}] references instance [{}].",
+                    config_map.name(),
+                    other.name
+                ));
+            }
+        }
+
+        let other_claims = claim_names(&other.pods);
+        for claim in claim_names(&self.pods).intersection(&other_claims) {
+            violations.push(format!(
+                "Persistent volume claim [{}] is mounted by instance [{}] and [{}].",
+                claim, self.name, other.name
+            ));
+        }
+
+        violations
+    }
+}
+
+/// Returns the names of all persistent volume claims mounted by the
+/// given pods.
+fn claim_names(pods: &[Pod]) -> BTreeSet<String> {
+    pods.iter()
+        .filter_map(|pod| pod.spec.as_ref())
+        .flat_map(|spec| spec.volumes.iter().flatten())
+        .filter_map(|volume| volume.persistent_volume_claim.as_ref())
+        .map(|claim| claim.claim_name.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test::prelude::from_yaml;
+    use spectral::prelude::*;
+
+    fn instance(
+        name: &str,
+        service_selector_instance: &str,
+        claim_name: &str,
+    ) -> InstanceResources {
+        InstanceResources {
+            name: name.to_string(),
+            services: vec![from_yaml(&format!(
+                "
+                metadata:
+                  name: {name}
+                spec:
+                  selector:
+                    app.kubernetes.io/instance: {selector}
+                ",
+                name = name,
+                selector = service_selector_instance
+            ))],
+            config_maps: vec![from_yaml(&format!(
+                "
+                metadata:
+                  name: {name}-discovery
+                data:
+                  ZOOKEEPER: {name}-server-0:2181
+                ",
+                name = name
+            ))],
+            pods: vec![from_yaml(&format!(
+                "
+                metadata:
+                  name: {name}-server-0
+                  labels:
+                    app.kubernetes.io/instance: {name}
+                spec:
+                  containers: []
+                  volumes:
+                    - name: data
+                      persistentVolumeClaim:
+                        claimName: {claim}
+                ",
+                name = name,
+                claim = claim_name
+            ))],
+        }
+    }
+
+    #[test]
+    fn isolated_instances_should_not_reference_each_other() {
+        let a = instance("simple-a", "simple-a", "data-simple-a");
+        let b = instance("simple-b", "simple-b", "data-simple-b");
+
+        assert_that(&a.find_references_to(&b)).is_empty();
+        assert_that(&b.find_references_to(&a)).is_empty();
+    }
+
+    #[test]
+    fn references_via_selectors_and_claims_should_be_detected() {
+        let a = instance("simple-a", "simple-b", "data");
+        let b = instance("simple-b", "simple-b", "data");
+
+        assert_that(&a.find_references_to(&b)).has_length(2);
+    }
+}
@@ -1 +1,7 @@
+pub mod command;
+pub mod helm;
+pub mod hooks;
+pub mod isolation;
+pub mod scenario;
 pub mod setup;
+pub mod watch_namespace;
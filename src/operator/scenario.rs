@@ -0,0 +1,260 @@
+//! Declarative scenarios for multi-step operator tests
+//!
+//! A [`Scenario`] describes the steps of a test case, e.g. applying a
+//! custom resource, waiting for the pods, and changing the custom
+//! resource. The steps are executed with [`Scenario::run`] which reports
+//! the progress and the duration of every step and names the failed
+//! step in the error.
+//!
+//! ```rust,no_run
+//! use integration_test_commons::operator::scenario::Scenario;
+//! use integration_test_commons::test::prelude::*;
+//! # fn test(mut cluster: TestCluster<StatefulSet>, stateful_set: StatefulSet) {
+//!
+//! Scenario::new()
+//!     .apply(&stateful_set)
+//!     .wait_ready(3)
+//!     .patch(|stateful_set| {
+//!         stateful_set
+//!             .metadata
+//!             .labels
+//!             .get_or_insert_with(Default::default)
+//!             .insert("restart".into(), "1".into());
+//!     })
+//!     .expect_rolling_restart()
+//!     .run(&mut cluster)
+//!     .unwrap();
+//! # }
+//! ```
+
+use super::setup::TestCluster;
+use crate::test::prelude::Pod;
+use crate::test::progress::Progress;
+
+use anyhow::{anyhow, Result};
+use kube::Resource;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fmt::Debug;
+use std::thread;
+use std::time::{Duration, Instant};
+
+type Action<T> = Box<dyn FnOnce(&mut TestCluster<T>, &mut State) -> Result<()>>;
+
+/// A step of a scenario
+struct Step<T>
+where
+    T: Clone + Debug + DeserializeOwned + Resource + Serialize,
+    <T as Resource>::DynamicType: Clone,
+{
+    description: String,
+    action: Action<T>,
+}
+
+/// State which is passed from one step to the next
+#[derive(Debug, Default)]
+struct State {
+    /// UIDs of the pods which existed before the custom resource was
+    /// changed the last time
+    pods_before_change: BTreeSet<String>,
+}
+
+/// Sequence of steps which are executed on a [`TestCluster`]
+pub struct Scenario<T>
+where
+    T: Clone + Debug + DeserializeOwned + Resource + Serialize,
+    <T as Resource>::DynamicType: Clone,
+{
+    steps: Vec<Step<T>>,
+}
+
+impl<T> Default for Scenario<T>
+where
+    T: Clone + Debug + DeserializeOwned + Resource + Serialize,
+    <T as Resource>::DynamicType: Clone,
+{
+    fn default() -> Self {
+        Scenario { steps: Vec::new() }
+    }
+}
+
+impl<T> Scenario<T>
+where
+    T: Clone + Debug + DeserializeOwned + Resource + Serialize + 'static,
+    <T as Resource>::DynamicType: Clone,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies the given custom resource.
+    pub fn apply(self, cluster: &T) -> Self {
+        let cluster = cluster.clone();
+        self.push_step("apply the custom resource", move |test_cluster, state| {
+            state.pods_before_change = pod_uids(test_cluster);
+            test_cluster.apply(&cluster)
+        })
+    }
+
+    /// Waits until the given number of pods of the cluster is ready.
+    pub fn wait_ready(self, expected_pod_count: usize) -> Self {
+        self.push_step(
+            &format!("wait until {} pod(s) are ready", expected_pod_count),
+            move |test_cluster, _| test_cluster.wait_ready(expected_pod_count),
+        )
+    }
+
+    /// Changes the last applied custom resource with the given function
+    /// and applies it.
+    pub fn patch<F>(self, change: F) -> Self
+    where
+        F: FnOnce(&mut T) + 'static,
+    {
+        self.push_step("patch the custom resource", move |test_cluster, state| {
+            let mut cluster = test_cluster.applied_cluster()?.clone();
+            change(&mut cluster);
+            state.pods_before_change = pod_uids(test_cluster);
+            test_cluster.apply(&cluster)
+        })
+    }
+
+    /// Waits until all pods which existed before the last change of the
+    /// custom resource are replaced by new pods which are ready.
+    pub fn expect_rolling_restart(self) -> Self {
+        self.push_step("expect a rolling restart", |test_cluster, state| {
+            let old_pods = &state.pods_before_change;
+            let timeout = test_cluster.timeouts.cluster_ready;
+            let start = Instant::now();
+
+            while !pod_uids(test_cluster).is_disjoint(old_pods) {
+                if start.elapsed() > timeout {
+                    return Err(anyhow!(
+                        "Not all pods were restarted within {} second(s)",
+                        timeout.as_secs()
+                    ));
+                }
+                thread::sleep(Duration::from_secs(2));
+            }
+
+            test_cluster.wait_ready(old_pods.len())
+        })
+    }
+
+    /// Waits until the operator reports a configuration error which
+    /// contains the given message substring in a status condition or an
+    /// event of the custom resource.
+    ///
+    /// This step follows [`Scenario::apply`] or [`Scenario::patch`] with
+    /// a custom resource which is valid according to the schema but
+    /// rejected by the operator, e.g. because of contradicting settings.
+    pub fn expect_config_error(self, message_substring: &str) -> Self {
+        let message_substring = message_substring.to_string();
+        self.push_step(
+            &format!("expect the configuration error [{}]", message_substring),
+            move |test_cluster, _| {
+                test_cluster
+                    .wait_for_config_error(&message_substring)
+                    .map(|_| ())
+            },
+        )
+    }
+
+    /// Adds a custom step with the given description.
+    pub fn step<F>(self, description: &str, action: F) -> Self
+    where
+        F: FnOnce(&mut TestCluster<T>) -> Result<()> + 'static,
+    {
+        self.push_step(description, move |test_cluster, _| action(test_cluster))
+    }
+
+    fn push_step<F>(mut self, description: &str, action: F) -> Self
+    where
+        F: FnOnce(&mut TestCluster<T>, &mut State) -> Result<()> + 'static,
+    {
+        self.steps.push(Step {
+            description: description.to_string(),
+            action: Box::new(action),
+        });
+        self
+    }
+
+    /// Returns the descriptions of the steps.
+    pub fn descriptions(&self) -> Vec<&str> {
+        self.steps
+            .iter()
+            .map(|step| step.description.as_str())
+            .collect()
+    }
+
+    /// Executes the steps on the given cluster in order and stops at the
+    /// first failed step.
+    pub fn run(self, test_cluster: &mut TestCluster<T>) -> Result<()> {
+        let mut state = State::default();
+        let step_count = self.steps.len();
+
+        for (index, step) in self.steps.into_iter().enumerate() {
+            let prefix = format!("Step {}/{} [{}]", index + 1, step_count, step.description);
+            let progress = Progress::start(&test_cluster.log(&format!("{} started", prefix)));
+
+            let start = Instant::now();
+            let result = (step.action)(test_cluster, &mut state);
+            let elapsed = start.elapsed().as_secs_f32();
+
+            match result {
+                Ok(()) => progress.finish(
+                    &test_cluster.log(&format!("{} succeeded after {:.1}s", prefix, elapsed)),
+                ),
+                Err(error) => {
+                    return Err(anyhow!(test_cluster.log(&format!(
+                        "{} failed after {:.1}s: {}",
+                        prefix, elapsed, error
+                    ))))
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the UIDs of the pods belonging to the cluster.
+fn pod_uids<T>(test_cluster: &TestCluster<T>) -> BTreeSet<String>
+where
+    T: Clone + Debug + DeserializeOwned + Resource + Serialize,
+    <T as Resource>::DynamicType: Clone,
+{
+    test_cluster
+        .list::<Pod>(None)
+        .into_iter()
+        .filter_map(|pod| pod.metadata.uid)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test::prelude::ConfigMap;
+    use spectral::prelude::*;
+
+    #[test]
+    fn descriptions_should_list_the_steps_in_order() {
+        let scenario = Scenario::<ConfigMap>::new()
+            .apply(&ConfigMap::default())
+            .wait_ready(3)
+            .patch(|_| {})
+            .expect_rolling_restart()
+            .expect_config_error("replicas must be odd")
+            .step("check the logs", |_| Ok(()));
+
+        assert_that(&scenario.descriptions()).is_equal_to(vec![
+            "apply the custom resource",
+            "wait until 3 pod(s) are ready",
+            "patch the custom resource",
+            "expect a rolling restart",
+            "expect the configuration error [replicas must be odd]",
+            "check the logs",
+        ]);
+    }
+}
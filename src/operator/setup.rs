@@ -1,26 +1,45 @@
-use crate::test::prelude::{Node, Pod, TestKubeClient};
+use super::hooks::{subscribe, Subscription};
+use crate::test::kube::{
+    get_container_arguments, get_pod_conditions, parse_env, parse_properties, split_arguments,
+};
+use crate::test::prelude::{ClusterSnapshot, ConfigMap, Event, Node, Pod, TestKubeClient};
+use crate::test::progress::Progress;
+use crate::test::redaction::redact_value;
+use crate::test::table::Table;
+use uuid::Uuid;
 
 use anyhow::{anyhow, Result};
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
-use kube::Resource;
+use kube::api::{Api, ListParams, WatchEvent};
+use kube::{Resource, ResourceExt};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use serde_json::{Map, Value};
 use std::collections::BTreeMap;
 use std::fmt::Debug;
+use std::ops::Deref;
 use std::thread;
 use std::time::{Duration, Instant};
-use uuid::Uuid;
 
 const MAX_INSTANCE_NAME_LEN: usize = 63;
 
 /// A wrapper to avoid passing in client or cluster everywhere.
-pub struct TestCluster<T: Clone + Debug + DeserializeOwned + Resource<DynamicType = ()> + Serialize>
+///
+/// The custom resource can either be a Rust type or a dynamically typed resource like a
+/// [`kube::core::DynamicObject`] which is created with [`TestCluster::new_with`].
+pub struct TestCluster<T>
+where
+    T: Clone + Debug + DeserializeOwned + Resource + Serialize,
+    <T as Resource>::DynamicType: Clone,
 {
     pub client: TestKubeClient,
     pub cluster: Option<T>,
     pub options: TestClusterOptions,
     pub labels: TestClusterLabels,
     pub timeouts: TestClusterTimeouts,
+    dynamic_type: T::DynamicType,
+    /// The custom resource as it was last applied, without the fields set by the API server
+    applied_cluster: Option<T>,
 }
 
 /// Some reoccurring common test cluster options.
@@ -63,27 +82,48 @@ pub struct TestClusterLabels {
     pub app: String,
     pub instance: String,
     pub version: String,
+    pub role_group: String,
 }
 
 impl TestClusterLabels {
+    /// The role group label defaults to `app.kubernetes.io/role-group`.
     pub fn new(app: &str, instance: &str, version: &str) -> Self {
         TestClusterLabels {
             app: app.to_string(),
             instance: instance.to_string(),
             version: version.to_string(),
+            role_group: String::from("app.kubernetes.io/role-group"),
         }
     }
 }
 
 impl<T> TestCluster<T>
 where
-    T: Clone + Debug + DeserializeOwned + Resource<DynamicType = ()> + Serialize,
+    T: Clone + Debug + DeserializeOwned + Resource + Serialize,
+    <T as Resource>::DynamicType: Clone + Default,
 {
     /// This creates a kube client and should be executed at the start of every test.
     pub fn new(
         options: &TestClusterOptions,
         labels: &TestClusterLabels,
         timeouts: &TestClusterTimeouts,
+    ) -> Self {
+        TestCluster::new_with(options, labels, timeouts, Default::default())
+    }
+}
+
+impl<T> TestCluster<T>
+where
+    T: Clone + Debug + DeserializeOwned + Resource + Serialize,
+    <T as Resource>::DynamicType: Clone,
+{
+    /// This creates a kube client for a custom resource with the given dynamic type, e.g. the
+    /// [`kube::core::ApiResource`] of a [`kube::core::DynamicObject`].
+    pub fn new_with(
+        options: &TestClusterOptions,
+        labels: &TestClusterLabels,
+        timeouts: &TestClusterTimeouts,
+        dynamic_type: T::DynamicType,
     ) -> Self {
         TestCluster {
             client: TestKubeClient::new(),
@@ -91,24 +131,42 @@ where
             options: options.clone(),
             labels: labels.clone(),
             timeouts: timeouts.clone(),
+            dynamic_type,
+            applied_cluster: None,
         }
     }
 
     /// Applies a custom resource, stores the returned cluster object and sleeps for
     /// two seconds to give the operator time to react on the custom resource.
     /// Without the sleep it can happen that tests run without any pods being created.
-    fn apply(&mut self, cluster: &T) -> Result<()> {
-        self.cluster = Some(self.client.apply(&serde_yaml::to_string(cluster)?));
+    pub(crate) fn apply(&mut self, cluster: &T) -> Result<()> {
+        self.cluster = Some(
+            self.client
+                .apply_with(&serde_yaml::to_string(cluster)?, &self.dynamic_type),
+        );
+        self.applied_cluster = Some(cluster.clone());
 
         // we wait here to give the operator time to react to the custom resource
         thread::sleep(Duration::from_secs(2));
         Ok(())
     }
 
+    /// Returns the custom resource as it was last applied.
+    ///
+    /// Changes must be based on this specification and not on the returned cluster object
+    /// because server-side apply rejects objects containing `managedFields` and outdated
+    /// resource versions.
+    pub(crate) fn applied_cluster(&self) -> Result<&T> {
+        self.applied_cluster
+            .as_ref()
+            .ok_or_else(|| anyhow!(self.log("The cluster was not created yet")))
+    }
+
     /// Applies a command and waits 2 seconds to let the operator react on in.
     pub fn apply_command<C>(&self, command: &C) -> Result<C>
     where
-        C: Clone + Debug + DeserializeOwned + Resource<DynamicType = ()> + Serialize,
+        C: Clone + Debug + DeserializeOwned + Resource + Serialize,
+        <C as Resource>::DynamicType: Default,
     {
         let cmd: C = self.client.apply(&serde_yaml::to_string(command)?);
 
@@ -150,7 +208,7 @@ where
                         "Pod [{}] has version [{}] but should have version [{}]. This should not happen!",
                         pod.metadata.name.as_ref().unwrap(),
                         pod_version,
-                        version.to_string()
+                        version
                     ))));
                 }
             } else {
@@ -158,7 +216,7 @@ where
                     "Pod [{}] has no version label [{}]. Expected version [{}]. This should not happen!",
                     pod.metadata.name.as_ref().unwrap(),
                     &self.labels.version,
-                    version.to_string(),
+                    version,
                 ));
             }
         }
@@ -175,12 +233,545 @@ where
         Ok(())
     }
 
+    /// Removes the role group with the given name from the given role of the custom resource
+    /// and waits for the pods of the role group to be terminated.
+    pub fn remove_role_group(&mut self, role: &str, name: &str) -> Result<()> {
+        self.update_role_group(role, name, Value::Null)?;
+        self.wait_for_pods_terminated_with(Some(self.role_group_labels(name)))
+    }
+
+    /// Adds the role group with the given name and YAML specification to the given role of the
+    /// custom resource and waits for `expected_pod_count` pods of the role group to be ready.
+    ///
+    /// An existing role group with the same name is replaced.
+    pub fn add_role_group(
+        &mut self,
+        role: &str,
+        name: &str,
+        spec_fragment: &str,
+        expected_pod_count: usize,
+    ) -> Result<()> {
+        self.update_role_group(role, name, serde_yaml::from_str(spec_fragment)?)?;
+        self.wait_ready_with(Some(self.role_group_labels(name)), expected_pod_count)
+    }
+
+    /// Sets the config override `key=value` for the given file of a role group in the custom
+    /// resource and verifies that the override lands in the generated ConfigMap and in the file
+    /// `{config_dir}/{file}` inside every pod of the role group.
+    ///
+    /// The file is expected in the format of Java properties files. The verification is
+    /// retried until the `cluster_ready` timeout expires because the operator reconciles the
+    /// ConfigMap and restarts the pods asynchronously.
+    pub fn verify_config_override(
+        &mut self,
+        role: &str,
+        role_group: &str,
+        file: &str,
+        key: &str,
+        value: &str,
+        config_dir: &str,
+    ) -> Result<()> {
+        self.modify_role_group(role, role_group, |spec| {
+            let config_overrides = object_entry(spec, "configOverrides")?;
+            object_entry(config_overrides, file)?
+                .insert(key.to_string(), Value::String(value.to_string()));
+            Ok(())
+        })?;
+
+        self.wait_for_override(&format!("config override [{}={}]", key, value), || {
+            self.find_missing_config_override(role_group, file, key, value, config_dir)
+        })
+    }
+
+    /// Sets the given environment variables as `envOverrides` of a role group in the custom
+    /// resource and verifies that they land in the specification of the given container and
+    /// in its effective environment inside every pod of the role group.
+    ///
+    /// Overrides of the role group take precedence over the ones of the role, so the given
+    /// values are expected even if the role overrides the same variables. Mismatches are
+    /// reported per variable with the expected and the actual value.
+    pub fn verify_env_overrides(
+        &mut self,
+        role: &str,
+        role_group: &str,
+        container: &str,
+        env_overrides: &BTreeMap<String, String>,
+    ) -> Result<()> {
+        self.modify_role_group(role, role_group, |spec| {
+            let overrides = object_entry(spec, "envOverrides")?;
+            for (name, value) in env_overrides {
+                overrides.insert(name.clone(), Value::String(value.clone()));
+            }
+            Ok(())
+        })?;
+
+        let redacted_overrides = env_overrides
+            .iter()
+            .map(|(name, value)| (name.as_str(), redact_value(name, value)))
+            .collect::<BTreeMap<_, _>>();
+        self.wait_for_override(&format!("env overrides {:?}", redacted_overrides), || {
+            self.find_missing_env_overrides(role_group, container, env_overrides)
+        })
+    }
+
+    /// Verifies that the given JVM arguments, e.g. the heap settings derived from the resources
+    /// or the ones set with `jvmArgumentOverrides`, are passed to the given container in every
+    /// pod of the role group.
+    ///
+    /// The arguments are searched in the command, the arguments, and the environment variables
+    /// of the container specification. If `check_processes` is set, it is additionally verified
+    /// that a process in the running container was started with all of the arguments, which
+    /// requires `ps` in the container image. The verification is retried until the
+    /// `cluster_ready` timeout expires because the pods may still be restarted.
+    pub fn verify_jvm_arguments(
+        &self,
+        role_group: &str,
+        container: &str,
+        expected_arguments: &[&str],
+        check_processes: bool,
+    ) -> Result<()> {
+        self.wait_for_override(&format!("JVM arguments {:?}", expected_arguments), || {
+            self.find_missing_jvm_arguments(
+                role_group,
+                container,
+                expected_arguments,
+                check_processes,
+            )
+        })
+    }
+
+    /// Waits until `find_missing` does not report a location where the described settings are
+    /// missing anymore or returns an error after the `cluster_ready` timeout.
+    fn wait_for_override<F>(&self, description: &str, find_missing: F) -> Result<()>
+    where
+        F: Fn() -> Option<String>,
+    {
+        let progress = Progress::start(&self.log(&format!("Waiting for {}", description)));
+        let now = Instant::now();
+        loop {
+            match find_missing() {
+                None => {
+                    progress.finish(&self.log(&format!("Verified {}", description)));
+                    return Ok(());
+                }
+                Some(location) if now.elapsed() < self.timeouts.cluster_ready => {
+                    progress.update(
+                        &self.log(&format!("Waiting for {} in {}...", description, location)),
+                    );
+                    thread::sleep(Duration::from_secs(2));
+                }
+                Some(location) => {
+                    return Err(anyhow!(self.log(&format!(
+                        "{} did not land in {} within the specified timeout of {} second(s)",
+                        description,
+                        location,
+                        self.timeouts.cluster_ready.as_secs()
+                    ))));
+                }
+            }
+        }
+    }
+
+    /// Modifies the specification of a role group in the custom resource with the given
+    /// function and applies the custom resource.
+    fn modify_role_group<F>(&mut self, role: &str, role_group: &str, modify: F) -> Result<()>
+    where
+        F: FnOnce(&mut Map<String, Value>) -> Result<()>,
+    {
+        let mut cluster = serde_json::to_value(self.applied_cluster()?)?;
+        let spec = cluster
+            .pointer_mut(&format!("/spec/{}/roleGroups/{}", role, role_group))
+            .and_then(Value::as_object_mut)
+            .ok_or_else(|| {
+                anyhow!(self.log(&format!(
+                    "Role [{}] has no role group [{}]",
+                    role, role_group
+                )))
+            })?;
+        modify(spec).map_err(|error| anyhow!(self.log(&error.to_string())))?;
+
+        self.apply(&serde_json::from_value(cluster)?)
+    }
+
+    /// Returns the ready pods of the role group or a description of the reason why the pods
+    /// cannot be checked yet.
+    fn ready_role_group_pods(&self, role_group: &str) -> std::result::Result<Vec<Pod>, String> {
+        let pods = self.list::<Pod>(Some(self.role_group_labels(role_group)));
+        if pods.is_empty() {
+            return Err(format!("the pods of role group [{}]", role_group));
+        }
+
+        for pod in &pods {
+            let is_ready = get_pod_conditions(pod)
+                .iter()
+                .any(|condition| condition.type_ == "Ready" && condition.status == "True");
+            if !is_ready {
+                return Err(format!("pod [{}] which is not ready", pod.name()));
+            }
+        }
+
+        Ok(pods)
+    }
+
+    /// Returns a description of the first location of the role group where the config
+    /// override is missing, or `None` if it is present in the ConfigMaps and in all pods.
+    fn find_missing_config_override(
+        &self,
+        role_group: &str,
+        file: &str,
+        key: &str,
+        value: &str,
+        config_dir: &str,
+    ) -> Option<String> {
+        let has_override =
+            |content: &str| parse_properties(content).get(key).map(String::as_str) == Some(value);
+
+        let in_config_map = self
+            .list::<ConfigMap>(Some(self.role_group_labels(role_group)))
+            .iter()
+            .any(|config_map| {
+                config_map
+                    .data
+                    .as_ref()
+                    .and_then(|data| data.get(file))
+                    .is_some_and(|content| has_override(content))
+            });
+        if !in_config_map {
+            return Some(format!("the ConfigMaps of role group [{}]", role_group));
+        }
+
+        let pods = match self.ready_role_group_pods(role_group) {
+            Ok(pods) => pods,
+            Err(location) => return Some(location),
+        };
+
+        let path = format!("{}/{}", config_dir.trim_end_matches('/'), file);
+        for pod in &pods {
+            let output = self.client.exec(pod, &["cat", &path]);
+            if !output.success() || !has_override(&output.stdout) {
+                return Some(format!("file [{}] of pod [{}]", path, pod.name()));
+            }
+        }
+
+        None
+    }
+
+    /// Returns a description of the first location of the role group where the environment
+    /// variables do not have the expected values, or `None` if all containers have them in
+    /// their specification and in their effective environment.
+    fn find_missing_env_overrides(
+        &self,
+        role_group: &str,
+        container: &str,
+        expected: &BTreeMap<String, String>,
+    ) -> Option<String> {
+        let pods = match self.ready_role_group_pods(role_group) {
+            Ok(pods) => pods,
+            Err(location) => return Some(location),
+        };
+
+        for pod in &pods {
+            let specified = pod
+                .spec
+                .iter()
+                .flat_map(|spec| &spec.containers)
+                .find(|candidate| candidate.name == container)
+                .into_iter()
+                .flat_map(|container| container.env.iter().flatten())
+                .map(|env_var| {
+                    (
+                        env_var.name.clone(),
+                        env_var.value.clone().unwrap_or_default(),
+                    )
+                })
+                .collect();
+            let mismatches = env_mismatches(expected, &specified);
+            if !mismatches.is_empty() {
+                return Some(format!(
+                    "the specification of container [{}] of pod [{}]: {}",
+                    container,
+                    pod.name(),
+                    mismatches.join(", ")
+                ));
+            }
+
+            let output = self.client.exec_in_container(pod, container, &["env"]);
+            let mismatches = env_mismatches(expected, &parse_env(&output.stdout));
+            if !output.success() || !mismatches.is_empty() {
+                return Some(format!(
+                    "the environment of container [{}] of pod [{}]: {}",
+                    container,
+                    pod.name(),
+                    mismatches.join(", ")
+                ));
+            }
+        }
+
+        None
+    }
+
+    /// Returns a description of the first location of the role group where JVM arguments are
+    /// missing, or `None` if all containers and, if requested, their processes have them.
+    fn find_missing_jvm_arguments(
+        &self,
+        role_group: &str,
+        container: &str,
+        expected: &[&str],
+        check_processes: bool,
+    ) -> Option<String> {
+        let pods = match self.ready_role_group_pods(role_group) {
+            Ok(pods) => pods,
+            Err(location) => return Some(location),
+        };
+
+        for pod in &pods {
+            let missing = missing_arguments(&get_container_arguments(pod, container), expected);
+            if !missing.is_empty() {
+                return Some(format!(
+                    "the specification of container [{}] of pod [{}] which misses {:?}",
+                    container,
+                    pod.name(),
+                    missing
+                ));
+            }
+
+            if check_processes {
+                let output = self.client.exec_in_container(
+                    pod,
+                    container,
+                    &["ps", "-e", "-ww", "-o", "args="],
+                );
+                let is_started = output.success()
+                    && output.stdout.lines().any(|command_line| {
+                        missing_arguments(&split_arguments(command_line), expected).is_empty()
+                    });
+                if !is_started {
+                    return Some(format!(
+                        "the processes of container [{}] of pod [{}]",
+                        container,
+                        pod.name()
+                    ));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Sets the role group with the given name in the custom resource to the given value, or
+    /// removes it if the value is null, and applies the custom resource.
+    fn update_role_group(&mut self, role: &str, name: &str, role_group: Value) -> Result<()> {
+        let mut cluster = serde_json::to_value(self.applied_cluster()?)?;
+        let role_groups = cluster
+            .pointer_mut(&format!("/spec/{}/roleGroups", role))
+            .and_then(Value::as_object_mut)
+            .ok_or_else(|| anyhow!(self.log(&format!("Role [{}] has no role groups", role))))?;
+
+        if role_group.is_null() {
+            role_groups.remove(name);
+        } else {
+            role_groups.insert(name.to_string(), role_group);
+        }
+
+        self.apply(&serde_json::from_value(cluster)?)
+    }
+
+    /// Returns the labels which select the pods of the given role group.
+    fn role_group_labels(&self, role_group: &str) -> BTreeMap<String, String> {
+        let mut labels = BTreeMap::new();
+        labels.insert(self.labels.role_group.clone(), role_group.to_string());
+        labels
+    }
+
+    /// Re-applies the last applied custom resource, waits for the given reconcile period, and
+    /// verifies that the operator neither created, deleted, nor modified any resource belonging
+    /// to the cluster.
+    ///
+    /// This catches operators which rewrite their objects in every reconcile loop and thereby
+    /// bump the resource versions endlessly. Status updates, e.g. of pod conditions, also modify
+    /// a resource, so the cluster should be ready before this check is performed.
+    pub fn verify_idempotent_reconciliation(&mut self, reconcile_period: Duration) -> Result<()> {
+        let cluster = self.applied_cluster()?.clone();
+
+        let label_selector = self.label_selector(None);
+        let before = ClusterSnapshot::take(&self.client, &label_selector);
+
+        self.apply(&cluster)?;
+        thread::sleep(reconcile_period);
+
+        let diff = before.diff(&ClusterSnapshot::take(&self.client, &label_selector));
+        if diff.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(self.log(&format!(
+                "Reconciliation is not idempotent; re-applying the custom resource changed the following resources:\n{}",
+                diff
+            ))))
+        }
+    }
+
     /// List resources belonging to the cluster. Additional labels to filter or limit the
     /// selector may be passed via `additional_labels`.
     pub fn list<R>(&self, additional_labels: Option<BTreeMap<String, String>>) -> Vec<R>
     where
-        R: Clone + Debug + DeserializeOwned + Resource<DynamicType = ()> + Serialize,
+        R: Clone + Debug + DeserializeOwned + Resource + Serialize,
+        <R as Resource>::DynamicType: Default,
+    {
+        self.client
+            .list_labeled::<R>(&self.label_selector(additional_labels))
+            .items
+    }
+
+    /// Returns the resources belonging to the cluster as table like it is printed by `kubectl get`.
+    /// Additional labels to filter or limit the selector may be passed via `additional_labels`.
+    pub fn get_table<R>(&self, additional_labels: Option<BTreeMap<String, String>>) -> Table
+    where
+        R: Resource,
+        <R as Resource>::DynamicType: Default,
+    {
+        self.client
+            .get_table::<R>(&self.label_selector(additional_labels))
+    }
+
+    /// Calls the callback for every pod of the cluster which is created after the subscription.
+    ///
+    /// The callback is called on a dedicated thread until the returned [`Subscription`] is
+    /// dropped. This allows to inject failures at a specific point in time, e.g. when the third
+    /// pod appears.
+    pub fn on_pod_created<F>(&self, mut callback: F) -> Subscription
+    where
+        F: FnMut(&Pod) + Send + 'static,
+    {
+        self.subscribe_pods(move |event| {
+            if let WatchEvent::Added(pod) = event {
+                callback(&pod);
+            }
+        })
+    }
+
+    /// Calls the callback for every pod of the cluster which is deleted after the subscription.
+    ///
+    /// The callback is called on a dedicated thread until the returned [`Subscription`] is
+    /// dropped.
+    pub fn on_pod_deleted<F>(&self, mut callback: F) -> Subscription
+    where
+        F: FnMut(&Pod) + Send + 'static,
+    {
+        self.subscribe_pods(move |event| {
+            if let WatchEvent::Deleted(pod) = event {
+                callback(&pod);
+            }
+        })
+    }
+
+    /// Watches the pods belonging to the cluster and passes the events to the handler.
+    fn subscribe_pods<H>(&self, handle: H) -> Subscription
+    where
+        H: FnMut(WatchEvent<Pod>) + Send + 'static,
     {
+        let api = Api::namespaced(self.client.kube_client().client(), self.client.namespace());
+        let list_params = ListParams::default().labels(&self.label_selector(None));
+        subscribe(&self.client, api, list_params, handle)
+    }
+
+    /// Calls the callback with the custom resource whenever its status changes after the
+    /// subscription.
+    ///
+    /// The callback is called on a dedicated thread until the returned [`Subscription`] is
+    /// dropped.
+    pub fn on_status_changed<F>(&self, mut callback: F) -> Result<Subscription>
+    where
+        F: FnMut(&T) + Send + 'static,
+        T: Send + 'static,
+    {
+        let cluster = self
+            .cluster
+            .as_ref()
+            .ok_or_else(|| anyhow!(self.log("The cluster was not created yet")))?;
+
+        let api = Api::namespaced_with(
+            self.client.kube_client().client(),
+            self.client.namespace(),
+            &self.dynamic_type,
+        );
+        let list_params =
+            ListParams::default().fields(&format!("metadata.name={}", cluster.name()));
+
+        let get_status = |cluster: &T| {
+            serde_json::to_value(cluster)
+                .ok()
+                .and_then(|cluster| cluster.get("status").cloned())
+        };
+        let mut last_status = get_status(cluster);
+
+        Ok(subscribe(&self.client, api, list_params, move |event| {
+            if let WatchEvent::Modified(cluster) = event {
+                let status = get_status(&cluster);
+                if status != last_status {
+                    last_status = status;
+                    callback(&cluster);
+                }
+            }
+        }))
+    }
+
+    /// Waits until the operator reports a configuration error which contains the given message
+    /// substring and returns the full message, or returns an error after the `wait_for_event`
+    /// timeout of the client.
+    ///
+    /// This is meant for custom resources which pass the schema validation but are rejected by
+    /// the operator. The error is expected in the message of a status condition or of an event
+    /// which involves the custom resource.
+    pub fn wait_for_config_error(&self, message_substring: &str) -> Result<String> {
+        let cluster = self
+            .cluster
+            .as_ref()
+            .ok_or_else(|| anyhow!(self.log("The cluster was not created yet")))?;
+        let api: Api<T> = Api::namespaced_with(
+            self.client.kube_client().client(),
+            self.client.namespace(),
+            &self.dynamic_type,
+        );
+        let event_fields = format!("involvedObject.uid={}", cluster.uid().unwrap_or_default());
+
+        let timeout = self.client.kube_client().timeouts.wait_for_event;
+        let progress = Progress::start(&self.log(&format!(
+            "Waiting for the configuration error [{}]",
+            message_substring
+        )));
+        let now = Instant::now();
+        loop {
+            let current_cluster = self.client.block_on(api.get(&cluster.name()))?;
+            let events = self.client.list_with_fields::<Event>(&event_fields).items;
+            let messages = config_error_messages(&serde_json::to_value(current_cluster)?, &events);
+
+            if let Some(message) = messages
+                .iter()
+                .find(|message| message.contains(message_substring))
+            {
+                progress.finish(&self.log(&format!("Verified configuration error: {}", message)));
+                return Ok(message.clone());
+            } else if now.elapsed() < timeout {
+                progress.update(&self.log(&format!(
+                    "Waiting for the configuration error [{}]...",
+                    message_substring
+                )));
+                thread::sleep(Duration::from_secs(2));
+            } else {
+                return Err(anyhow!(self.log(&format!(
+                    "The configuration error [{}] was not reported within the specified timeout \
+                    of {} second(s); the reported messages are {:?}",
+                    message_substring,
+                    timeout.as_secs(),
+                    messages
+                ))));
+            }
+        }
+    }
+
+    /// Returns the label selector for the resources belonging to the cluster extended by the
+    /// additional labels.
+    fn label_selector(&self, additional_labels: Option<BTreeMap<String, String>>) -> String {
         let mut labels = additional_labels.unwrap_or_default();
 
         labels.insert(self.labels.app.clone(), self.options.app_name.clone());
@@ -189,14 +780,11 @@ where
             self.options.instance_name.clone(),
         );
 
-        let transformed_labels = labels
+        labels
             .iter()
             .map(|(key, value)| format!("{}={}", key, value))
-            .collect::<Vec<String>>();
-
-        self.client
-            .list_labeled::<R>(&transformed_labels.join(","))
-            .items
+            .collect::<Vec<String>>()
+            .join(",")
     }
 
     /// List all nodes registered in the api server that have an agent running (or default to
@@ -204,13 +792,20 @@ where
     /// May be used to determine the expected pods for tests (depending on the custom resource).
     pub fn list_nodes(&self, selector: Option<&str>) -> Vec<Node> {
         self.client
-            .list_labeled::<Node>(selector.unwrap_or("kubernetes.io/arch=stackable-linux"))
+            .list_without_label_defaults::<Node>(
+                selector.unwrap_or("kubernetes.io/arch=stackable-linux"),
+            )
             .items
     }
 
     /// Write a formatted message with cluster kind and cluster name in the beginning to the console.
-    fn log(&self, message: &str) -> String {
-        format!("[{}/{}] {}", T::kind(&()), self.name(), message)
+    pub(crate) fn log(&self, message: &str) -> String {
+        format!(
+            "[{}/{}] {}",
+            T::kind(&self.dynamic_type),
+            self.name(),
+            message
+        )
     }
 
     /// Return the cluster / instance name
@@ -220,19 +815,26 @@ where
 
     /// A "busy" wait for all pods to be terminated and cleaned up.
     pub fn wait_for_pods_terminated(&self) -> Result<()> {
+        self.wait_for_pods_terminated_with(None)
+    }
+
+    /// A "busy" wait for all pods with the additional labels to be terminated and cleaned up.
+    fn wait_for_pods_terminated_with(
+        &self,
+        additional_labels: Option<BTreeMap<String, String>>,
+    ) -> Result<()> {
+        let progress = Progress::start(&self.log("Waiting for the pods to terminate"));
         let now = Instant::now();
 
         while now.elapsed().as_secs() < self.timeouts.pods_terminated.as_secs() {
-            let pods = &self.list::<Pod>(None);
+            let pods = &self.list::<Pod>(additional_labels.clone());
 
             if pods.is_empty() {
+                progress.finish(&self.log("All pods terminated"));
                 return Ok(());
             }
 
-            println!(
-                "{}",
-                self.log(&format!("Waiting for {} Pod(s) to terminate", pods.len()))
-            );
+            progress.update(&self.log(&format!("Waiting for {} Pod(s) to terminate", pods.len())));
             thread::sleep(Duration::from_secs(1));
         }
 
@@ -251,35 +853,136 @@ where
     /// * `expected_pod_count` - Number of pods to wait for until they become ready.
     ///
     pub fn wait_ready(&self, expected_pod_count: usize) -> Result<()> {
+        self.wait_ready_with(None, expected_pod_count)
+    }
+
+    /// Wait for the `expected_pod_count` pods with the additional labels to become ready.
+    fn wait_ready_with(
+        &self,
+        additional_labels: Option<BTreeMap<String, String>>,
+        expected_pod_count: usize,
+    ) -> Result<()> {
+        let progress = Progress::start(&self.log(&format!(
+            "Waiting for {} pod(s) to be ready",
+            expected_pod_count
+        )));
         let now = Instant::now();
+        let mut scheduling_failures = Vec::new();
 
         while now.elapsed().as_secs() < self.timeouts.cluster_ready.as_secs() {
-            let created_pods = &self.list::<Pod>(None);
-            println!(
-                "{}",
-                self.log(&format!(
-                    "Waiting for [{}/{}] pod(s) to be ready...",
-                    created_pods.len(),
-                    expected_pod_count
-                )),
-            );
-
-            if created_pods.len() != expected_pod_count {
+            let created_pods = &self.list::<Pod>(additional_labels.clone());
+            scheduling_failures = created_pods
+                .iter()
+                .filter_map(|pod| {
+                    self.client.get_scheduling_failure(pod).map(|reason| {
+                        format!("Pod [{}] cannot be scheduled: {}", pod.name(), reason)
+                    })
+                })
+                .collect::<Vec<_>>();
+            progress.update(&self.log(&format!(
+                "Waiting for [{}/{}] pod(s) to be ready...",
+                created_pods.len(),
+                expected_pod_count
+            )));
+            for scheduling_failure in &scheduling_failures {
+                progress.update(&self.log(scheduling_failure));
+            }
+
+            if created_pods.len() != expected_pod_count || !scheduling_failures.is_empty() {
                 thread::sleep(Duration::from_secs(2));
                 continue;
             } else {
                 for pod in created_pods {
                     self.client.verify_pod_condition(pod, "Ready");
                 }
-                println!("{}", self.log("Installation finished"));
+                progress.finish(&self.log("Installation finished"));
                 return Ok(());
             }
         }
 
-        Err(anyhow!(self.log(&format!(
+        let mut message = format!(
             "Cluster did not startup within the specified timeout of {} second(s)",
             self.timeouts.cluster_ready.as_secs()
-        ))))
+        );
+        for scheduling_failure in &scheduling_failures {
+            message.push_str(&format!("\n{}", scheduling_failure));
+        }
+        Err(anyhow!(self.log(&message)))
+    }
+}
+
+/// Returns the object with the given key in the given object and inserts an empty one if the
+/// key is missing.
+fn object_entry<'a>(
+    object: &'a mut Map<String, Value>,
+    key: &str,
+) -> Result<&'a mut Map<String, Value>> {
+    object
+        .entry(key)
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("[{}] is not an object", key))
+}
+
+/// Returns the messages of the status conditions of the given custom resource and of the given
+/// events.
+fn config_error_messages(cluster: &Value, events: &[Event]) -> Vec<String> {
+    let condition_messages = cluster["status"]["conditions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|condition| condition["message"].as_str())
+        .map(String::from);
+    let event_messages = events.iter().filter_map(|event| event.message.clone());
+    condition_messages.chain(event_messages).collect()
+}
+
+/// Returns the expected arguments which are not contained in the actual ones.
+fn missing_arguments<'a>(actual: &[String], expected: &[&'a str]) -> Vec<&'a str> {
+    expected
+        .iter()
+        .filter(|argument| !actual.iter().any(|actual| actual == *argument))
+        .copied()
+        .collect()
+}
+
+/// Describes the environment variables which do not have the expected values.
+///
+/// The values of variables with sensitive names are redacted.
+fn env_mismatches(
+    expected: &BTreeMap<String, String>,
+    actual: &BTreeMap<String, String>,
+) -> Vec<String> {
+    expected
+        .iter()
+        .filter_map(|(name, value)| match actual.get(name) {
+            Some(actual_value) if actual_value == value => None,
+            Some(actual_value) => Some(format!(
+                "[{}] is [{}] instead of [{}]",
+                name,
+                redact_value(name, actual_value),
+                redact_value(name, value)
+            )),
+            None => Some(format!(
+                "[{}] is not set instead of [{}]",
+                name,
+                redact_value(name, value)
+            )),
+        })
+        .collect()
+}
+
+/// Gives direct access to the methods of the [`TestKubeClient`], e.g. `cluster.find_namespaced`
+/// instead of `cluster.client.find_namespaced`.
+impl<T> Deref for TestCluster<T>
+where
+    T: Clone + Debug + DeserializeOwned + Resource + Serialize,
+    <T as Resource>::DynamicType: Clone,
+{
+    type Target = TestKubeClient;
+
+    fn deref(&self) -> &Self::Target {
+        &self.client
     }
 }
 
@@ -287,14 +990,87 @@ where
 /// to the cluster each time a single test is finished.
 impl<T> Drop for TestCluster<T>
 where
-    T: Clone + Debug + DeserializeOwned + Resource<DynamicType = ()> + Serialize,
+    T: Clone + Debug + DeserializeOwned + Resource + Serialize,
+    <T as Resource>::DynamicType: Clone,
 {
     fn drop(&mut self) {
         if let Some(cluster) = self.cluster.take() {
-            self.client.delete(cluster);
+            self.client.delete_with(cluster, &self.dynamic_type);
             if let Err(err) = self.wait_for_pods_terminated() {
                 self.log(&err.to_string());
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test::kube::from_yaml;
+    use indoc::indoc;
+    use spectral::prelude::*;
+
+    #[test]
+    fn config_error_messages_should_contain_conditions_and_events() {
+        let cluster = serde_json::json!({
+            "status": {
+                "conditions": [
+                    { "type": "Available", "status": "False" },
+                    {
+                        "type": "ReconciliationPaused",
+                        "status": "True",
+                        "message": "The number of replicas must be odd"
+                    }
+                ]
+            }
+        });
+        let event: Event = from_yaml(indoc! {"
+            metadata:
+              name: simple.event
+            involvedObject:
+              name: simple
+            reason: InvalidConfig
+            message: The role group [default] has no nodes
+        "});
+
+        assert_that(&config_error_messages(&cluster, &[event])).is_equal_to(vec![
+            String::from("The number of replicas must be odd"),
+            String::from("The role group [default] has no nodes"),
+        ]);
+    }
+
+    #[test]
+    fn missing_arguments_should_return_the_arguments_which_are_not_passed() {
+        let actual = vec![String::from("-Xmx2g"), String::from("-Xms2g")];
+
+        assert_that(&missing_arguments(
+            &actual,
+            &["-Xms2g", "-Xmx4g", "-XX:+UseG1GC"],
+        ))
+        .is_equal_to(vec!["-Xmx4g", "-XX:+UseG1GC"]);
+    }
+
+    #[test]
+    fn env_mismatches_should_describe_wrong_and_missing_variables() {
+        let env = |entries: &[(&str, &str)]| {
+            entries
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let expected = env(&[
+            ("HEAP", "2g"),
+            ("MODE", "test"),
+            ("PASSWORD", "s3cr3t"),
+            ("ZONE", "a"),
+        ]);
+        let actual = env(&[("HEAP", "1g"), ("MODE", "test"), ("PASSWORD", "admin")]);
+
+        assert_that(&env_mismatches(&expected, &actual)).is_equal_to(vec![
+            String::from("[HEAP] is [1g] instead of [2g]"),
+            String::from("[PASSWORD] is [<redacted>] instead of [<redacted>]"),
+            String::from("[ZONE] is not set instead of [a]"),
+        ]);
+    }
+}
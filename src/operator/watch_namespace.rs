@@ -0,0 +1,231 @@
+//! Test scenario for operators which watch only a single namespace
+
+use super::helm::{HelmRelease, HelmValues};
+use crate::test::prelude::{KubeClient, Namespace, TestKubeClient};
+
+use anyhow::Result;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::api::{Api, PostParams};
+use kube::Resource;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+use std::time::Duration;
+
+/// A scenario which verifies that an operator only reconciles custom
+/// resources in the namespace it watches.
+///
+/// The operator is installed with the watched namespace configured.
+/// Then the custom resource is created in every namespace which is
+/// expected to be reconciled or ignored and the reconciliation is
+/// verified with a predicate on the custom resource.
+///
+/// ```rust,no_run
+/// use integration_test_commons::operator::helm::{HelmRelease, HelmValues};
+/// use integration_test_commons::operator::watch_namespace::WatchNamespaceScenario;
+/// use integration_test_commons::test::prelude::*;
+/// # use integration_test_commons::test::repository::Repository;
+///
+/// let client = TestKubeClient::new();
+/// let release = HelmRelease::new("zookeeper-operator", "stackable/zookeeper-operator", "default");
+///
+/// WatchNamespaceScenario::new(release, HelmValues::new(), "namespace-a")
+///     .expect_ignored("namespace-b")
+///     .run::<Repository, _>(&client, "...", |repository| repository.metadata.finalizers.is_some())
+///     .unwrap();
+/// ```
+pub struct WatchNamespaceScenario {
+    pub release: HelmRelease,
+    pub values: HelmValues,
+    pub watched_namespace: String,
+    pub reconciled_namespaces: Vec<String>,
+    pub ignored_namespaces: Vec<String>,
+    pub observation_window: Duration,
+}
+
+impl WatchNamespaceScenario {
+    /// Creates a scenario which expects that custom resources in the
+    /// watched namespace are reconciled.
+    pub fn new(release: HelmRelease, values: HelmValues, watched_namespace: &str) -> Self {
+        WatchNamespaceScenario {
+            release,
+            values,
+            watched_namespace: watched_namespace.to_string(),
+            reconciled_namespaces: vec![watched_namespace.to_string()],
+            ignored_namespaces: Vec::new(),
+            observation_window: Duration::from_secs(30),
+        }
+    }
+
+    /// Expects that custom resources in the given namespace are
+    /// reconciled.
+    pub fn expect_reconciled(mut self, namespace: &str) -> Self {
+        self.reconciled_namespaces.push(namespace.to_string());
+        self
+    }
+
+    /// Expects that custom resources in the given namespace are ignored.
+    pub fn expect_ignored(mut self, namespace: &str) -> Self {
+        self.ignored_namespaces.push(namespace.to_string());
+        self
+    }
+
+    /// Installs the operator, creates the custom resource with the given
+    /// YAML specification in all namespaces and verifies that only the
+    /// expected ones are reconciled.
+    ///
+    /// A custom resource is considered reconciled if `is_reconciled`
+    /// returns true. The custom resources are deleted afterwards.
+    ///
+    /// Finally, the operator is uninstalled and the namespaces which were
+    /// created by the scenario are deleted, also if the scenario fails or
+    /// panics. A failed verification is reported before a failed cleanup.
+    pub fn run<T, P>(&self, client: &TestKubeClient, spec: &str, is_reconciled: P) -> Result<()>
+    where
+        T: Clone + Debug + DeserializeOwned + Resource<DynamicType = ()> + Serialize,
+        P: Fn(&T) -> bool,
+    {
+        self.release
+            .install(&self.values.clone().watch_namespace(&self.watched_namespace))?;
+
+        let mut cleanup = ScenarioCleanup {
+            client,
+            release: &self.release,
+            created_namespaces: Vec::new(),
+            done: false,
+        };
+        let created_namespaces = &mut cleanup.created_namespaces;
+
+        let result = client.block_on(async {
+            for namespace in &self.reconciled_namespaces {
+                self.verify_reconciliation(
+                    client.kube_client(),
+                    namespace,
+                    spec,
+                    &is_reconciled,
+                    true,
+                    created_namespaces,
+                )
+                .await?;
+            }
+            for namespace in &self.ignored_namespaces {
+                self.verify_reconciliation(
+                    client.kube_client(),
+                    namespace,
+                    spec,
+                    &is_reconciled,
+                    false,
+                    created_namespaces,
+                )
+                .await?;
+            }
+            Ok(())
+        });
+
+        let cleanup_result = cleanup.run();
+        result.and(cleanup_result)
+    }
+
+    /// Creates the custom resource in the given namespace, verifies if
+    /// it is reconciled as expected and deletes it again.
+    async fn verify_reconciliation<T, P>(
+        &self,
+        kube_client: &KubeClient,
+        namespace: &str,
+        spec: &str,
+        is_reconciled: P,
+        expect_reconciled: bool,
+        created_namespaces: &mut Vec<String>,
+    ) -> Result<()>
+    where
+        T: Clone + Debug + DeserializeOwned + Resource<DynamicType = ()> + Serialize,
+        P: Fn(&T) -> bool,
+    {
+        if create_namespace_if_absent(kube_client, namespace).await? {
+            created_namespaces.push(namespace.to_string());
+        }
+
+        let kube_client = kube_client.in_namespace(namespace);
+        let resource: T = kube_client.create(spec).await?;
+
+        let result = if expect_reconciled {
+            kube_client
+                .verify_status(&resource, is_reconciled)
+                .await
+                .map(|_| ())
+        } else {
+            kube_client
+                .verify_status_not_reached(&resource, is_reconciled, self.observation_window)
+                .await
+        };
+
+        let deleted = kube_client.delete(resource).await;
+
+        result.and(deleted)
+    }
+}
+
+/// Cleanup of a scenario which uninstalls the operator and deletes the
+/// namespaces which were created by the scenario
+///
+/// If the cleanup is not run explicitly, e.g. because the scenario
+/// panicked, then it is run when it is dropped.
+struct ScenarioCleanup<'a> {
+    client: &'a TestKubeClient,
+    release: &'a HelmRelease,
+    created_namespaces: Vec<String>,
+    done: bool,
+}
+
+impl<'a> ScenarioCleanup<'a> {
+    fn run(&mut self) -> Result<()> {
+        self.done = true;
+
+        let mut result = self.release.uninstall();
+        for name in &self.created_namespaces {
+            let namespace = namespace(name);
+            let deleted = self
+                .client
+                .block_on(self.client.kube_client().delete(namespace));
+            result = result.and(deleted);
+        }
+        result
+    }
+}
+
+impl<'a> Drop for ScenarioCleanup<'a> {
+    fn drop(&mut self) {
+        if !self.done {
+            if let Err(error) = self.run() {
+                eprintln!("The scenario could not be cleaned up: {}", error);
+            }
+        }
+    }
+}
+
+/// Creates the namespace with the given name if it does not exist and
+/// returns true if it was created.
+async fn create_namespace_if_absent(kube_client: &KubeClient, name: &str) -> Result<bool> {
+    let namespaces: Api<Namespace> = Api::all(kube_client.client());
+
+    match namespaces.get(name).await {
+        Ok(_) => Ok(false),
+        Err(kube::Error::Api(error)) if error.code == 404 => {
+            namespaces
+                .create(&PostParams::default(), &namespace(name))
+                .await?;
+            Ok(true)
+        }
+        Err(error) => Err(error.into()),
+    }
+}
+
+fn namespace(name: &str) -> Namespace {
+    Namespace {
+        metadata: ObjectMeta {
+            name: Some(name.to_string()),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
@@ -0,0 +1,171 @@
+//! Resource kinds and versions served by the API server
+//!
+//! Tests of custom resource definitions with multiple versions assert
+//! which versions are served, which ones are deprecated, and in which
+//! version the objects are stored:
+//!
+//! ```rust,no_run
+//! use integration_test_commons::test::prelude::*;
+//!
+//! let client = TestKubeClient::new();
+//! let group = client.discover("zookeeper.stackable.tech");
+//!
+//! assert_that(&group.versions("ZookeeperCluster")).is_equal_to(vec!["v1beta1", "v1alpha1"]);
+//! assert_that(&group.get("ZookeeperCluster", "v1alpha1").unwrap().deprecated).is_true();
+//! assert_that(&group.storage_version("ZookeeperCluster")).contains_value("v1beta1");
+//! ```
+
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use kube::discovery::{ApiCapabilities, ApiResource, Scope};
+
+/// Resource kinds and versions which are served in an API group
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DiscoveredGroup {
+    pub name: String,
+    pub preferred_version: Option<String>,
+    pub resources: Vec<ServedResource>,
+}
+
+/// A resource kind in a served version
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ServedResource {
+    pub kind: String,
+    pub plural: String,
+    pub version: String,
+    pub namespaced: bool,
+    /// Whether the version is deprecated in the custom resource
+    /// definition; always false for built-in resources
+    pub deprecated: bool,
+    /// Whether the objects are stored in this version according to the
+    /// custom resource definition; always false for built-in resources
+    pub storage: bool,
+}
+
+impl DiscoveredGroup {
+    /// Combines the discovered resources of a group with the custom
+    /// resource definitions of the group.
+    pub(crate) fn from_resources(
+        name: &str,
+        preferred_version: Option<&str>,
+        resources: &[(ApiResource, ApiCapabilities)],
+        crds: &[CustomResourceDefinition],
+    ) -> Self {
+        let resources = resources
+            .iter()
+            .map(|(api_resource, capabilities)| {
+                let crd_version = crds
+                    .iter()
+                    .filter(|crd| crd.spec.names.plural == api_resource.plural)
+                    .flat_map(|crd| &crd.spec.versions)
+                    .find(|version| version.name == api_resource.version);
+                ServedResource {
+                    kind: api_resource.kind.clone(),
+                    plural: api_resource.plural.clone(),
+                    version: api_resource.version.clone(),
+                    namespaced: capabilities.scope == Scope::Namespaced,
+                    deprecated: crd_version
+                        .and_then(|version| version.deprecated)
+                        .unwrap_or_default(),
+                    storage: crd_version.is_some_and(|version| version.storage),
+                }
+            })
+            .collect();
+
+        DiscoveredGroup {
+            name: name.to_string(),
+            preferred_version: preferred_version.map(String::from),
+            resources,
+        }
+    }
+
+    /// Returns the served versions of the given kind, ordered from the
+    /// most stable and most recent one to the least.
+    pub fn versions(&self, kind: &str) -> Vec<&str> {
+        self.resources
+            .iter()
+            .filter(|resource| resource.kind == kind)
+            .map(|resource| resource.version.as_str())
+            .collect()
+    }
+
+    /// Returns the given kind in the given version if it is served.
+    pub fn get(&self, kind: &str, version: &str) -> Option<&ServedResource> {
+        self.resources
+            .iter()
+            .find(|resource| resource.kind == kind && resource.version == version)
+    }
+
+    /// Returns the version in which the objects of the given custom
+    /// resource kind are stored.
+    pub fn storage_version(&self, kind: &str) -> Option<&str> {
+        self.resources
+            .iter()
+            .find(|resource| resource.kind == kind && resource.storage)
+            .map(|resource| resource.version.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test::kube::from_yaml;
+    use indoc::indoc;
+    use kube::core::GroupVersionKind;
+    use spectral::prelude::*;
+
+    fn served(version: &str) -> (ApiResource, ApiCapabilities) {
+        let gvk = GroupVersionKind::gvk("zookeeper.stackable.tech", version, "ZookeeperCluster");
+        (
+            ApiResource::from_gvk_with_plural(&gvk, "zookeeperclusters"),
+            ApiCapabilities {
+                scope: Scope::Namespaced,
+                subresources: vec![],
+                operations: vec![],
+            },
+        )
+    }
+
+    #[test]
+    fn discovered_group_should_contain_the_versions_of_the_crd() {
+        let crd: CustomResourceDefinition = from_yaml(indoc! {"
+            metadata:
+              name: zookeeperclusters.zookeeper.stackable.tech
+            spec:
+              group: zookeeper.stackable.tech
+              names:
+                kind: ZookeeperCluster
+                plural: zookeeperclusters
+              scope: Namespaced
+              versions:
+                - name: v1beta1
+                  served: true
+                  storage: true
+                - name: v1alpha1
+                  served: true
+                  storage: false
+                  deprecated: true
+        "});
+
+        let group = DiscoveredGroup::from_resources(
+            "zookeeper.stackable.tech",
+            Some("v1beta1"),
+            &[served("v1beta1"), served("v1alpha1")],
+            &[crd],
+        );
+
+        assert_that(&group.versions("ZookeeperCluster")).is_equal_to(vec!["v1beta1", "v1alpha1"]);
+        assert_that(&group.storage_version("ZookeeperCluster")).contains_value("v1beta1");
+        assert_that(&group.get("ZookeeperCluster", "v1alpha1").cloned()).contains_value(
+            ServedResource {
+                kind: String::from("ZookeeperCluster"),
+                plural: String::from("zookeeperclusters"),
+                version: String::from("v1alpha1"),
+                namespaced: true,
+                deprecated: true,
+                storage: false,
+            },
+        );
+        assert_that(&group.get("ZookeeperCluster", "v2")).is_none();
+    }
+}
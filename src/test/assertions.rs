@@ -1,14 +1,18 @@
 //! Additional assertions for [`spectral`]
 
+use super::quantity::Resources;
+use super::sidecar::is_injected_sidecar;
+use k8s_openapi::api::core::v1::Pod;
+use serde_json::Value;
 use spectral::{iter::ContainingIntoIterAssertions, vec::VecAssertions, AssertionFailure, Spec};
 use std::fmt::Debug;
 
 /// Additional assertions for Vec
 pub trait ExtendedVecAssertions<'s, T: 's> {
     fn is_not_empty(&self);
-    fn contains_exactly_in_any_order<E: 's>(&mut self, expected_values_iter: &'s E)
+    fn contains_exactly_in_any_order<E>(&mut self, expected_values_iter: &'s E)
     where
-        E: IntoIterator<Item = &'s T> + Clone,
+        E: 's + IntoIterator<Item = &'s T> + Clone,
         E::IntoIter: ExactSizeIterator;
 }
 
@@ -39,9 +43,9 @@ where
     /// # use integration_test_commons::test::assertions::ExtendedVecAssertions;
     /// assert_that(&vec![1, 2, 3]).contains_exactly_in_any_order(&vec![&3, &1, &2]);
     /// ```
-    fn contains_exactly_in_any_order<E: 's>(&mut self, expected_values_iter: &'s E)
+    fn contains_exactly_in_any_order<E>(&mut self, expected_values_iter: &'s E)
     where
-        E: IntoIterator<Item = &'s T> + Clone,
+        E: 's + IntoIterator<Item = &'s T> + Clone,
         E::IntoIter: ExactSizeIterator,
     {
         self.has_length(expected_values_iter.clone().into_iter().len());
@@ -49,10 +53,277 @@ where
     }
 }
 
+/// Additional assertions for JSON values
+pub trait ValueAssertions {
+    fn contains_subtree(&self, expected: &Value);
+}
+
+impl<'s> ValueAssertions for Spec<'s, Value> {
+    /// Asserts that the subject value contains the expected subtree.
+    ///
+    /// Objects must contain at least the expected fields and every
+    /// element of an expected array must be contained in some element of
+    /// the according subject array regardless of the order.
+    ///
+    /// ```rust
+    /// # use serde_json::json;
+    /// # use spectral::prelude::*;
+    /// # use integration_test_commons::test::assertions::ValueAssertions;
+    /// assert_that(&json!({ "a": [1, 2], "b": true })).contains_subtree(&json!({ "a": [2] }));
+    /// ```
+    fn contains_subtree(&self, expected: &Value) {
+        if let Some(mismatch) = find_subtree_mismatch(self.subject, expected, "") {
+            AssertionFailure::from_spec(self)
+                .with_expected(format!("value to contain subtree <{}>", expected))
+                .with_actual(mismatch)
+                .fail();
+        }
+    }
+}
+
+/// Additional assertions for pods
+pub trait PodAssertions {
+    fn has_pod_overrides(&self, pod_overrides: &str);
+    fn has_affinity(&self, expected_affinity: &str);
+    fn has_container_ports(&self, container_name: &str, expected_named_ports: &[(&str, i32)]);
+    fn has_containers(&self, expected_containers: &[&str]);
+    fn has_containers_at_least(&self, expected_containers: &[&str]);
+    fn has_container_resources(&self, container_name: &str, expected_resources: &Resources);
+}
+
+impl<'s> PodAssertions for Spec<'s, Pod> {
+    /// Asserts that the given pod overrides were applied to the subject
+    /// pod.
+    ///
+    /// The pod overrides are given as YAML specification of a pod
+    /// template, e.g. as taken from the `podOverrides` of a custom
+    /// resource. Additional containers, tolerations, environment
+    /// variables, etc. are matched regardless of their order.
+    fn has_pod_overrides(&self, pod_overrides: &str) {
+        let pod_overrides: Value =
+            serde_yaml::from_str(pod_overrides).expect("String is not a well-formed YAML");
+        let pod = serde_json::to_value(self.subject).expect("Pod could not be serialized");
+
+        if let Some(mismatch) = find_subtree_mismatch(&pod, &pod_overrides, "") {
+            AssertionFailure::from_spec(self)
+                .with_expected(format!("pod overrides <{}> to be applied", pod_overrides))
+                .with_actual(mismatch)
+                .fail();
+        }
+    }
+
+    /// Asserts that the subject pod has the expected affinity.
+    ///
+    /// The expected affinity is given as YAML specification. The order
+    /// of list elements, e.g. of the node selector terms or the pod
+    /// affinity terms, is irrelevant.
+    fn has_affinity(&self, expected_affinity: &str) {
+        let expected_affinity: Value =
+            serde_yaml::from_str(expected_affinity).expect("String is not a well-formed YAML");
+        let actual_affinity = self
+            .subject
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.affinity.as_ref())
+            .map(|affinity| {
+                serde_json::to_value(affinity).expect("Affinity could not be serialized")
+            })
+            .unwrap_or(Value::Null);
+
+        if normalize(&actual_affinity) != normalize(&expected_affinity) {
+            AssertionFailure::from_spec(self)
+                .with_expected(format!("pod to have affinity <{}>", expected_affinity))
+                .with_actual(format!("<{}>", actual_affinity))
+                .fail();
+        }
+    }
+
+    /// Asserts that the given container of the subject pod declares the
+    /// expected ports with the conventional names, e.g. `metrics` or
+    /// `http`, which are relied on by services and monitoring discovery.
+    ///
+    /// Further ports of the container are ignored.
+    fn has_container_ports(&self, container_name: &str, expected_named_ports: &[(&str, i32)]) {
+        let container = self
+            .subject
+            .spec
+            .iter()
+            .flat_map(|spec| &spec.containers)
+            .find(|container| container.name == container_name);
+
+        let actual_named_ports = container
+            .iter()
+            .flat_map(|container| container.ports.iter().flatten())
+            .map(|port| {
+                (
+                    port.name.as_deref().unwrap_or_default(),
+                    port.container_port,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let all_ports_declared = container.is_some()
+            && expected_named_ports
+                .iter()
+                .all(|expected_port| actual_named_ports.contains(expected_port));
+
+        if !all_ports_declared {
+            AssertionFailure::from_spec(self)
+                .with_expected(format!(
+                    "container <{}> to declare the ports <{:?}>",
+                    container_name, expected_named_ports
+                ))
+                .with_actual(match container {
+                    Some(_) => format!("<{:?}>", actual_named_ports),
+                    None => String::from("no such container"),
+                })
+                .fail();
+        }
+    }
+
+    /// Asserts that the subject pod has exactly the expected containers
+    /// in any order.
+    ///
+    /// Sidecars injected by service meshes, e.g. `istio-proxy`, are
+    /// ignored.
+    fn has_containers(&self, expected_containers: &[&str]) {
+        let mut actual_containers = container_names(self.subject)
+            .into_iter()
+            .filter(|name| !is_injected_sidecar(name))
+            .collect::<Vec<_>>();
+        actual_containers.sort_unstable();
+        let mut sorted_expected_containers = expected_containers.to_vec();
+        sorted_expected_containers.sort_unstable();
+
+        if actual_containers != sorted_expected_containers {
+            AssertionFailure::from_spec(self)
+                .with_expected(format!(
+                    "pod to have the containers <{:?}>",
+                    expected_containers
+                ))
+                .with_actual(format!("<{:?}>", actual_containers))
+                .fail();
+        }
+    }
+
+    /// Asserts that the subject pod has at least the expected containers.
+    ///
+    /// Further containers, e.g. sidecars injected by service meshes, are
+    /// ignored.
+    fn has_containers_at_least(&self, expected_containers: &[&str]) {
+        let actual_containers = container_names(self.subject);
+
+        if !expected_containers
+            .iter()
+            .all(|expected| actual_containers.contains(expected))
+        {
+            AssertionFailure::from_spec(self)
+                .with_expected(format!(
+                    "pod to have at least the containers <{:?}>",
+                    expected_containers
+                ))
+                .with_actual(format!("<{:?}>", actual_containers))
+                .fail();
+        }
+    }
+
+    /// Asserts that the given container of the subject pod has the
+    /// expected CPU and memory requests and limits.
+    ///
+    /// The quantities are compared by their value, so `1Gi` equals
+    /// `1024Mi`. Requests and limits of other resources are ignored.
+    fn has_container_resources(&self, container_name: &str, expected_resources: &Resources) {
+        let container = self
+            .subject
+            .spec
+            .iter()
+            .flat_map(|spec| &spec.containers)
+            .find(|container| container.name == container_name);
+
+        let actual_resources = container.map(Resources::from_container);
+
+        if !matches!(&actual_resources, Some(Ok(actual)) if actual == expected_resources) {
+            AssertionFailure::from_spec(self)
+                .with_expected(format!(
+                    "container <{}> to have the resources <{:?}>",
+                    container_name, expected_resources
+                ))
+                .with_actual(match actual_resources {
+                    Some(Ok(actual)) => format!("<{:?}>", actual),
+                    Some(Err(error)) => format!("invalid resources: {}", error),
+                    None => String::from("no such container"),
+                })
+                .fail();
+        }
+    }
+}
+
+fn container_names(pod: &Pod) -> Vec<&str> {
+    pod.spec
+        .iter()
+        .flat_map(|spec| &spec.containers)
+        .map(|container| container.name.as_str())
+        .collect()
+}
+
+/// Returns the given value with all arrays and object keys sorted
+/// recursively so that values can be compared regardless of the order
+/// of array elements.
+pub fn normalize(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries = map
+                .iter()
+                .map(|(key, value)| (key.clone(), normalize(value)))
+                .collect::<Vec<_>>();
+            entries.sort_by(|(key1, _), (key2, _)| key1.cmp(key2));
+            Value::Object(entries.into_iter().collect())
+        }
+        Value::Array(values) => {
+            let mut values = values.iter().map(normalize).collect::<Vec<_>>();
+            values.sort_by_cached_key(|value| value.to_string());
+            Value::Array(values)
+        }
+        _ => value.clone(),
+    }
+}
+
+/// Returns a description of the first mismatch between the actual value
+/// and the expected subtree or `None` if the subtree is contained.
+pub fn find_subtree_mismatch(actual: &Value, expected: &Value, path: &str) -> Option<String> {
+    match (actual, expected) {
+        (Value::Object(actual_map), Value::Object(expected_map)) => {
+            expected_map.iter().find_map(|(key, expected_value)| {
+                let path = format!("{}/{}", path, key);
+                match actual_map.get(key) {
+                    Some(actual_value) => {
+                        find_subtree_mismatch(actual_value, expected_value, &path)
+                    }
+                    None => Some(format!("<{}> is missing", path)),
+                }
+            })
+        }
+        (Value::Array(actual_values), Value::Array(expected_values)) => {
+            expected_values.iter().find_map(|expected_value| {
+                if actual_values.iter().any(|actual_value| {
+                    find_subtree_mismatch(actual_value, expected_value, path).is_none()
+                }) {
+                    None
+                } else {
+                    Some(format!("<{}> does not contain <{}>", path, expected_value))
+                }
+            })
+        }
+        _ if actual == expected => None,
+        _ => Some(format!("<{}> is <{}>", path, actual)),
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use serde_json::json;
     use spectral::prelude::*;
 
     #[test]
@@ -95,4 +366,230 @@ mod tests {
     fn should_panic_if_vec_contains_the_same_number_but_different_values_than_the_expected_ones() {
         assert_that(&vec![1, 2, 4]).contains_exactly_in_any_order(&vec![&1, &2, &3]);
     }
+
+    #[test]
+    fn should_not_panic_if_value_contains_the_subtree() {
+        assert_that(&json!({
+            "spec": {
+                "containers": [{ "name": "main" }, { "name": "sidecar", "image": "busybox" }],
+                "tolerations": [{ "key": "a" }, { "key": "b" }]
+            }
+        }))
+        .contains_subtree(&json!({
+            "spec": {
+                "containers": [{ "name": "sidecar" }],
+                "tolerations": [{ "key": "b" }, { "key": "a" }]
+            }
+        }));
+    }
+
+    #[test]
+    #[should_panic(expected = "but was: </spec/containers> does not contain")]
+    fn should_panic_if_value_does_not_contain_an_array_element() {
+        assert_that(&json!({ "spec": { "containers": [{ "name": "main" }] } }))
+            .contains_subtree(&json!({ "spec": { "containers": [{ "name": "sidecar" }] } }));
+    }
+
+    #[test]
+    #[should_panic(expected = "but was: </spec/hostNetwork> is <false>")]
+    fn should_panic_if_value_differs_from_the_subtree() {
+        assert_that(&json!({ "spec": { "hostNetwork": false } }))
+            .contains_subtree(&json!({ "spec": { "hostNetwork": true } }));
+    }
+
+    #[test]
+    fn should_not_panic_if_pod_overrides_were_applied() {
+        let pod: Pod = serde_yaml::from_str(
+            "
+            metadata:
+              name: test
+            spec:
+              containers:
+                - name: main
+                  env:
+                    - name: A
+                      value: a
+                    - name: B
+                      value: b
+            ",
+        )
+        .unwrap();
+
+        assert_that(&pod).has_pod_overrides(
+            "
+            spec:
+              containers:
+                - name: main
+                  env:
+                    - name: B
+                      value: b
+            ",
+        );
+    }
+
+    #[test]
+    fn should_not_panic_if_pod_has_the_affinity_in_a_different_order() {
+        let pod: Pod = serde_yaml::from_str(
+            "
+            metadata:
+              name: test
+            spec:
+              affinity:
+                podAntiAffinity:
+                  preferredDuringSchedulingIgnoredDuringExecution:
+                    - weight: 70
+                      podAffinityTerm:
+                        topologyKey: kubernetes.io/hostname
+                    - weight: 30
+                      podAffinityTerm:
+                        topologyKey: topology.kubernetes.io/zone
+              containers: []
+            ",
+        )
+        .unwrap();
+
+        assert_that(&pod).has_affinity(
+            "
+            podAntiAffinity:
+              preferredDuringSchedulingIgnoredDuringExecution:
+                - weight: 30
+                  podAffinityTerm:
+                    topologyKey: topology.kubernetes.io/zone
+                - weight: 70
+                  podAffinityTerm:
+                    topologyKey: kubernetes.io/hostname
+            ",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "expected: pod to have affinity")]
+    fn should_panic_if_pod_has_no_affinity() {
+        let pod = Pod::default();
+
+        assert_that(&pod).has_affinity("podAntiAffinity: {}");
+    }
+
+    fn pod_with_ports() -> Pod {
+        serde_yaml::from_str(
+            "
+            metadata:
+              name: test
+            spec:
+              containers:
+                - name: zookeeper
+                  ports:
+                    - name: client
+                      containerPort: 2181
+                    - name: metrics
+                      containerPort: 9505
+            ",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn should_not_panic_if_container_declares_the_named_ports() {
+        assert_that(&pod_with_ports())
+            .has_container_ports("zookeeper", &[("metrics", 9505), ("client", 2181)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected: container <zookeeper> to declare the ports \
+        <[(\"metrics\", 9505)]>\n\t but was: <[(\"client\", 2181), (\"prometheus\", 9505)]>")]
+    fn should_panic_if_container_declares_a_port_with_another_name() {
+        let mut pod = pod_with_ports();
+        pod.spec.as_mut().unwrap().containers[0]
+            .ports
+            .as_mut()
+            .unwrap()[1]
+            .name = Some(String::from("prometheus"));
+
+        assert_that(&pod).has_container_ports("zookeeper", &[("metrics", 9505)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "but was: no such container")]
+    fn should_panic_if_container_does_not_exist() {
+        assert_that(&pod_with_ports()).has_container_ports("kafka", &[]);
+    }
+
+    fn meshed_pod() -> Pod {
+        serde_yaml::from_str(
+            "
+            metadata:
+              name: test
+            spec:
+              containers:
+                - name: zookeeper
+                - name: istio-proxy
+            ",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn should_not_panic_if_pod_has_the_containers_besides_injected_sidecars() {
+        assert_that(&meshed_pod()).has_containers(&["zookeeper"]);
+        assert_that(&meshed_pod()).has_containers_at_least(&["zookeeper", "istio-proxy"]);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "expected: pod to have the containers <[\"zookeeper\", \"metrics\"]>\n\t \
+        but was: <[\"zookeeper\"]>"
+    )]
+    fn should_panic_if_pod_misses_a_container() {
+        assert_that(&meshed_pod()).has_containers(&["zookeeper", "metrics"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected: pod to have at least the containers <[\"metrics\"]>")]
+    fn should_panic_if_pod_misses_a_container_of_the_minimum_set() {
+        assert_that(&meshed_pod()).has_containers_at_least(&["metrics"]);
+    }
+
+    #[test]
+    fn should_not_panic_if_container_has_the_resources_in_another_notation() {
+        let pod: Pod = serde_yaml::from_str(
+            "
+            metadata:
+              name: test
+            spec:
+              containers:
+                - name: zookeeper
+                  resources:
+                    requests:
+                      cpu: '1'
+                      memory: 1024Mi
+                    limits:
+                      cpu: 2000m
+                      memory: 1Gi
+            ",
+        )
+        .unwrap();
+
+        assert_that(&pod).has_container_resources(
+            "zookeeper",
+            &Resources {
+                cpu_request_millis: Some(1000),
+                cpu_limit_millis: Some(2000),
+                memory_request_bytes: Some(1 << 30),
+                memory_limit_bytes: Some(1 << 30),
+            },
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "but was: <Resources { cpu_request_millis: None, \
+        cpu_limit_millis: None, memory_request_bytes: None, memory_limit_bytes: None }>")]
+    fn should_panic_if_container_has_no_resources() {
+        assert_that(&meshed_pod()).has_container_resources(
+            "zookeeper",
+            &Resources {
+                memory_limit_bytes: Some(1 << 30),
+                ..Default::default()
+            },
+        );
+    }
 }
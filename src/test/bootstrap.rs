@@ -0,0 +1,423 @@
+//! Setup which is executed only once
+//!
+//! Cluster-wide setup like custom resource definitions or repositories
+//! is shared by all test cases. `cargo test` runs the test cases of a
+//! test binary in parallel threads and several test binaries in
+//! parallel processes. The functions in this module ensure that the
+//! setup is executed only once per process and never concurrently
+//! across processes.
+//!
+//! [cargo-nextest] runs every test case in its own process, so a setup
+//! which is executed once per process is executed for every test case.
+//! [`bootstrap_once_in_cluster`] coordinates the processes of a nextest
+//! run via a Lease and a marker ConfigMap on the cluster instead.
+//!
+//! [cargo-nextest]: https://nexte.st
+
+use super::cluster_lock::LeaseRenewal;
+use super::prelude::{ClusterLock, ConfigMap, KubeClient, TestKubeClient};
+use anyhow::{anyhow, Result};
+use fs2::FileExt;
+use indoc::formatdoc;
+use once_cell::sync::Lazy;
+use std::collections::HashSet;
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Name of the environment variable which cargo-nextest sets to the ID
+/// of the test run
+pub const NEXTEST_RUN_ID_ENV_VAR: &str = "NEXTEST_RUN_ID";
+
+/// Duration of the Lease which serializes the setup across processes
+const BOOTSTRAP_LEASE_DURATION: Duration = Duration::from_secs(300);
+
+/// Keys of the setups which were already executed in this process
+static BOOTSTRAPPED: Lazy<Mutex<HashSet<String>>> = Lazy::new(Default::default);
+
+/// Executes the given setup once for the given key.
+///
+/// The setup is executed at most once per process if it succeeds. A
+/// file lock ensures that the setup is not executed concurrently by
+/// other processes. If the setup fails then it is executed again on the
+/// next call.
+pub fn bootstrap_once<F>(key: &str, setup: F) -> Result<()>
+where
+    F: FnOnce() -> Result<()>,
+{
+    if is_bootstrapped(key) {
+        return Ok(());
+    }
+
+    let lock_file = lock(key)?;
+    let result = if is_bootstrapped(key) {
+        Ok(())
+    } else {
+        setup().map(|()| mark_bootstrapped(key))
+    };
+    FileExt::unlock(&lock_file)?;
+
+    result
+}
+
+/// Executes the given asynchronous setup once for the given key.
+///
+/// See [`bootstrap_once`] for details.
+pub async fn bootstrap_once_async<F>(key: &str, setup: F) -> Result<()>
+where
+    F: Future<Output = Result<()>>,
+{
+    if is_bootstrapped(key) {
+        return Ok(());
+    }
+
+    // The file lock blocks, so it must not be acquired on a worker thread
+    // of the runtime.
+    let lock_key = key.to_owned();
+    let lock_file = tokio::task::spawn_blocking(move || lock(&lock_key)).await??;
+    let result = if is_bootstrapped(key) {
+        Ok(())
+    } else {
+        setup.await.map(|()| mark_bootstrapped(key))
+    };
+    FileExt::unlock(&lock_file)?;
+
+    result
+}
+
+/// Executes the given setup once for the given key per test run.
+///
+/// If the tests are run by cargo-nextest then the setup is executed
+/// only once per test run across all processes. A Lease on the cluster
+/// ensures that the setup is not executed concurrently and a marker
+/// ConfigMap records that it succeeded in the current run. Otherwise
+/// this function behaves like [`bootstrap_once`].
+pub fn bootstrap_once_in_cluster<F>(client: &TestKubeClient, key: &str, setup: F) -> Result<()>
+where
+    F: FnOnce() -> Result<()>,
+{
+    let run_id = match env::var(NEXTEST_RUN_ID_ENV_VAR) {
+        Ok(run_id) => run_id,
+        Err(_) => return bootstrap_once(key, setup),
+    };
+
+    if is_bootstrapped(key) {
+        return Ok(());
+    }
+
+    let name = resource_name(key);
+    let lock = ClusterLock::acquire(client, &name, BOOTSTRAP_LEASE_DURATION);
+
+    let result = if is_marked(client.find_namespaced(&name), &run_id) {
+        Ok(())
+    } else {
+        setup().map(|()| {
+            client.apply::<ConfigMap>(&marker_spec(&name, &run_id));
+        })
+    };
+    drop(lock);
+
+    result.map(|()| mark_bootstrapped(key))
+}
+
+/// Executes the given asynchronous setup once for the given key per
+/// test run.
+///
+/// See [`bootstrap_once_in_cluster`] for details.
+pub async fn bootstrap_once_in_cluster_async<F>(
+    client: &KubeClient,
+    key: &str,
+    setup: F,
+) -> Result<()>
+where
+    F: Future<Output = Result<()>>,
+{
+    let run_id = match env::var(NEXTEST_RUN_ID_ENV_VAR) {
+        Ok(run_id) => run_id,
+        Err(_) => return bootstrap_once_async(key, setup).await,
+    };
+
+    if is_bootstrapped(key) {
+        return Ok(());
+    }
+
+    let name = resource_name(key);
+    let lease = BootstrapLease::acquire(client, &name).await?;
+
+    let result = if is_marked(client.find_namespaced(&name).await, &run_id) {
+        Ok(())
+    } else {
+        match setup.await {
+            Ok(()) => client
+                .apply::<ConfigMap>(&marker_spec(&name, &run_id))
+                .await
+                .map(|_| ()),
+            Err(error) => Err(error),
+        }
+    };
+    lease.release().await?;
+
+    result.map(|()| mark_bootstrapped(key))
+}
+
+/// Lease which serializes an asynchronous setup across processes
+///
+/// The lease is renewed while it is held. If it is not released
+/// explicitly, e.g. because the setup panicked or the future was
+/// cancelled, then it is released in the background when it is dropped,
+/// so that the other processes do not wait until it expires.
+struct BootstrapLease {
+    client: KubeClient,
+    name: String,
+    holder_identity: String,
+    renewal: LeaseRenewal,
+    released: bool,
+}
+
+impl BootstrapLease {
+    async fn acquire(client: &KubeClient, name: &str) -> Result<Self> {
+        let holder_identity = Uuid::new_v4().to_string();
+        client
+            .acquire_lease(name, &holder_identity, BOOTSTRAP_LEASE_DURATION)
+            .await?;
+        let renewal = LeaseRenewal::start(client, name, &holder_identity, BOOTSTRAP_LEASE_DURATION);
+        Ok(BootstrapLease {
+            client: client.in_namespace(client.namespace()),
+            name: name.to_owned(),
+            holder_identity,
+            renewal,
+            released: false,
+        })
+    }
+
+    async fn release(mut self) -> Result<()> {
+        self.released = true;
+        let lost = self.renewal.stop().await;
+        self.client
+            .release_lease(&self.name, &self.holder_identity)
+            .await?;
+        if lost {
+            Err(anyhow!(
+                "Lease [{}] was lost before the setup was completed.",
+                self.name
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Drop for BootstrapLease {
+    fn drop(&mut self) {
+        if self.released {
+            return;
+        }
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            let client = self.client.in_namespace(self.client.namespace());
+            let name = self.name.clone();
+            let holder_identity = self.holder_identity.clone();
+            runtime.spawn(async move {
+                if let Err(error) = client.release_lease(&name, &holder_identity).await {
+                    eprintln!("Lease [{}] could not be released: {}", name, error);
+                }
+            });
+        }
+    }
+}
+
+/// Returns true if the given marker records a setup in the given run.
+fn is_marked(marker: Option<ConfigMap>, run_id: &str) -> bool {
+    marker
+        .and_then(|marker| marker.data)
+        .and_then(|data| data.get("runId").cloned())
+        .as_deref()
+        == Some(run_id)
+}
+
+fn marker_spec(name: &str, run_id: &str) -> String {
+    formatdoc!(
+        r#"
+            apiVersion: v1
+            kind: ConfigMap
+            metadata:
+              name: {name}
+            data:
+              runId: "{run_id}"
+        "#,
+        name = name,
+        run_id = run_id
+    )
+}
+
+/// Returns the name of the Lease and marker ConfigMap of the given key.
+///
+/// The name is a valid DNS label. It contains the key in a sanitized and
+/// possibly truncated form followed by a hash of the original key, so
+/// that keys like `a_b` and `a.b` result in different names.
+fn resource_name(key: &str) -> String {
+    const PREFIX: &str = "integration-test-bootstrap-";
+    const MAX_NAME_LENGTH: usize = 63;
+    const HASH_LENGTH: usize = 8;
+
+    let mut sanitized_key = String::new();
+    for c in key.chars() {
+        if c.is_ascii_alphanumeric() {
+            sanitized_key.push(c.to_ascii_lowercase());
+        } else if !sanitized_key.is_empty() && !sanitized_key.ends_with('-') {
+            sanitized_key.push('-');
+        }
+    }
+    sanitized_key.truncate(MAX_NAME_LENGTH - PREFIX.len() - HASH_LENGTH - 1);
+    let sanitized_key = sanitized_key.trim_end_matches('-');
+
+    let hash = format!("{:08x}", fnv1a_hash(key));
+    if sanitized_key.is_empty() {
+        format!("{}{}", PREFIX, hash)
+    } else {
+        format!("{}{}-{}", PREFIX, sanitized_key, hash)
+    }
+}
+
+/// Returns the 32-bit FNV-1a hash of the given text which is stable
+/// across processes and Rust versions in contrast to the hasher of the
+/// standard library.
+fn fnv1a_hash(text: &str) -> u32 {
+    text.bytes().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+fn is_bootstrapped(key: &str) -> bool {
+    BOOTSTRAPPED
+        .lock()
+        .expect("Bootstrap state is poisoned")
+        .contains(key)
+}
+
+fn mark_bootstrapped(key: &str) {
+    BOOTSTRAPPED
+        .lock()
+        .expect("Bootstrap state is poisoned")
+        .insert(key.to_owned());
+}
+
+/// Locks the file belonging to the given key exclusively and blocks
+/// until the lock is acquired.
+///
+/// The lock is released when the returned file is closed.
+fn lock(key: &str) -> Result<File> {
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(lock_file_path(key))?;
+    file.lock_exclusive()?;
+    Ok(file)
+}
+
+fn lock_file_path(key: &str) -> PathBuf {
+    let file_name = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    std::env::temp_dir().join(format!("integration-test-commons-{}.lock", file_name))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use spectral::prelude::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use uuid::Uuid;
+
+    #[test]
+    fn bootstrap_once_should_execute_the_setup_only_once() {
+        let key = Uuid::new_v4().to_string();
+        let counter = Arc::new(AtomicUsize::new(0));
+
+        let threads = (0..4)
+            .map(|_| {
+                let key = key.clone();
+                let counter = counter.clone();
+                thread::spawn(move || {
+                    bootstrap_once(&key, || {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        Ok(())
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for thread in threads {
+            assert_that(&thread.join().unwrap()).is_ok();
+        }
+        assert_that(&counter.load(Ordering::SeqCst)).is_equal_to(1);
+    }
+
+    #[test]
+    fn bootstrap_once_should_execute_the_setup_again_if_it_failed() {
+        let key = Uuid::new_v4().to_string();
+
+        let first_result = bootstrap_once(&key, || Err(anyhow::anyhow!("setup failed")));
+        let second_result = bootstrap_once(&key, || Ok(()));
+
+        assert_that(&first_result).is_err();
+        assert_that(&second_result).is_ok();
+    }
+
+    #[test]
+    fn is_marked_should_only_accept_markers_of_the_current_run() {
+        let marker: ConfigMap = serde_yaml::from_str(&marker_spec("marker", "run-1")).unwrap();
+
+        assert_that(&is_marked(Some(marker.clone()), "run-1")).is_true();
+        assert_that(&is_marked(Some(marker), "run-2")).is_false();
+        assert_that(&is_marked(None, "run-1")).is_false();
+    }
+
+    #[test]
+    fn resource_name_should_be_a_valid_kubernetes_name() {
+        let is_valid_name = |name: &str| {
+            name.len() <= 63
+                && !name.ends_with('-')
+                && !name.contains("--")
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        };
+
+        assert_that(&resource_name("Stackable_Repository"))
+            .starts_with("integration-test-bootstrap-stackable-repository-");
+        assert_that(&resource_name("repo.")).starts_with("integration-test-bootstrap-repo-");
+        assert_that(&resource_name("..."))
+            .matches(|name| name.len() == "integration-test-bootstrap-".len() + 8);
+
+        for key in &[
+            "Stackable_Repository",
+            "repo.",
+            "a--b",
+            "...",
+            &"x".repeat(100),
+        ] {
+            let name = resource_name(key);
+            assert!(is_valid_name(&name), "[{}] is not a valid name", name);
+        }
+    }
+
+    #[test]
+    fn resource_name_should_differ_for_distinct_keys() {
+        let names = ["a_b", "a.b", "A-b"]
+            .iter()
+            .map(|key| resource_name(key))
+            .collect::<HashSet<_>>();
+
+        assert_that(&names.len()).is_equal_to(3);
+        assert_that(&resource_name("a_b")).is_equal_to(resource_name("a_b"));
+    }
+}
@@ -0,0 +1,403 @@
+//! Capacity of the cluster
+//!
+//! Tests of large products request many replicas which do not fit on
+//! small CI clusters. The capacity of the cluster allows such tests to
+//! scale down the requested replicas or to skip the test instead of
+//! failing with unschedulable pods.
+//!
+//! Tests of stateful products verify the storage capacity up front, so
+//! that they fail fast with a clear message instead of waiting for
+//! persistent volume claims which can never be bound:
+//!
+//! ```rust,no_run
+//! use integration_test_commons::test::capacity::verify_storage_capacity;
+//! use integration_test_commons::test::prelude::*;
+//!
+//! let client = TestKubeClient::new();
+//! verify_storage_capacity(&client, "standard", "10Gi");
+//! ```
+
+use super::kube::{get_allocatable_pods, get_node_taints, is_cordoned};
+use super::prelude::{DynamicObject, GroupVersionKind, TestKubeClient};
+use super::quantity::parse_memory_bytes;
+use super::skip::skip;
+use k8s_openapi::api::core::v1::{Node, Pod};
+use k8s_openapi::api::storage::v1::StorageClass;
+use std::collections::HashMap;
+
+/// Capacity of the schedulable nodes of the cluster
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ClusterCapacity {
+    /// Number of pods which can still be scheduled on each schedulable
+    /// node
+    pub free_pods_per_node: Vec<u32>,
+}
+
+impl ClusterCapacity {
+    /// Detects the capacity of the nodes matching the given label
+    /// selector from their allocatable pods and the pods which are
+    /// already running on them.
+    ///
+    /// Nodes which are cordoned or tainted with `NoSchedule` or
+    /// `NoExecute` are not considered.
+    pub fn detect(client: &TestKubeClient, node_selector: &str) -> Self {
+        let nodes = client
+            .list_without_label_defaults::<Node>(node_selector)
+            .items;
+        let pods = client.list_without_label_defaults::<Pod>("").items;
+        ClusterCapacity::from_nodes(&nodes, &pods)
+    }
+
+    fn from_nodes(nodes: &[Node], pods: &[Pod]) -> Self {
+        let mut used_pods = HashMap::<&str, u32>::new();
+        for pod in pods {
+            let is_terminated = pod
+                .status
+                .as_ref()
+                .and_then(|status| status.phase.as_deref())
+                .is_some_and(|phase| phase == "Succeeded" || phase == "Failed");
+            let node_name = pod.spec.as_ref().and_then(|spec| spec.node_name.as_deref());
+            if let (Some(node_name), false) = (node_name, is_terminated) {
+                *used_pods.entry(node_name).or_default() += 1;
+            }
+        }
+
+        let free_pods_per_node = nodes
+            .iter()
+            .filter(|node| is_schedulable(node))
+            .map(|node| {
+                let node_name = node.metadata.name.as_deref().unwrap_or_default();
+                let used = used_pods.get(node_name).copied().unwrap_or_default();
+                get_allocatable_pods(node).saturating_sub(used)
+            })
+            .collect();
+
+        ClusterCapacity { free_pods_per_node }
+    }
+
+    /// Returns the number of pods which can still be scheduled in the
+    /// cluster.
+    pub fn free_pods(&self) -> u32 {
+        self.free_pods_per_node.iter().sum()
+    }
+
+    /// Returns the number of nodes on which at least one pod can be
+    /// scheduled.
+    ///
+    /// This limits the replicas of products which place at most one pod
+    /// on each node.
+    pub fn nodes_with_free_pods(&self) -> u32 {
+        self.free_pods_per_node
+            .iter()
+            .filter(|free_pods| **free_pods > 0)
+            .count() as u32
+    }
+
+    /// Returns the requested replicas scaled down to the free pods of
+    /// the cluster or `None` if not even the minimum replicas fit and
+    /// the test should be skipped.
+    ///
+    /// A warning is printed if the replicas are scaled down or if the
+    /// test should be skipped.
+    pub fn scale_replicas(&self, requested: u32, minimum: u32) -> Option<u32> {
+        scale(requested, minimum, self.free_pods(), "free pod slots")
+    }
+
+    /// Returns the requested replicas scaled down to the nodes with
+    /// free pods or `None` if not even the minimum replicas fit and the
+    /// test should be skipped.
+    ///
+    /// This is meant for products which place at most one pod on each
+    /// node. A warning is printed if the replicas are scaled down or if
+    /// the test should be skipped.
+    pub fn scale_replicas_one_per_node(&self, requested: u32, minimum: u32) -> Option<u32> {
+        scale(
+            requested,
+            minimum,
+            self.nodes_with_free_pods(),
+            "nodes with free pod slots",
+        )
+    }
+
+    /// Returns the requested replicas scaled down to the free pods of
+    /// the cluster or skips the test with [`skip()`] if not even the
+    /// minimum replicas fit.
+    pub fn scale_replicas_or_skip(&self, requested: u32, minimum: u32) -> u32 {
+        self.scale_replicas(requested, minimum)
+            .unwrap_or_else(|| skip("The cluster has not enough free pod slots"))
+    }
+
+    /// Returns the requested replicas scaled down to the nodes with
+    /// free pods or skips the test with [`skip()`] if not even the
+    /// minimum replicas fit.
+    pub fn scale_replicas_one_per_node_or_skip(&self, requested: u32, minimum: u32) -> u32 {
+        self.scale_replicas_one_per_node(requested, minimum)
+            .unwrap_or_else(|| skip("The cluster has not enough nodes with free pod slots"))
+    }
+}
+
+fn scale(requested: u32, minimum: u32, available: u32, unit: &str) -> Option<u32> {
+    if requested <= available {
+        Some(requested)
+    } else if minimum <= available {
+        println!(
+            "WARNING: {} replicas were requested but the cluster has only {} {}. \
+            The replicas are scaled down to {}.",
+            requested, available, unit, available
+        );
+        Some(available)
+    } else {
+        println!(
+            "WARNING: At least {} replicas are required but the cluster has only {} {}. \
+            The test is skipped.",
+            minimum, available, unit
+        );
+        None
+    }
+}
+
+fn is_schedulable(node: &Node) -> bool {
+    let is_tainted = get_node_taints(node)
+        .iter()
+        .any(|taint| taint.effect == "NoSchedule" || taint.effect == "NoExecute");
+    !is_cordoned(node) && !is_tainted
+}
+
+/// Largest volume which can be provisioned for a storage class
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StorageCapacity {
+    /// Largest capacity in bytes reported by the `CSIStorageCapacity`
+    /// objects of the storage class
+    Csi(u64),
+    /// Largest allocatable ephemeral storage in bytes of the
+    /// schedulable nodes; used if the storage class has no
+    /// `CSIStorageCapacity` objects, e.g. for local path provisioners
+    EphemeralStorage(u64),
+}
+
+impl StorageCapacity {
+    /// Detects the storage capacity of the given storage class.
+    ///
+    /// The `CSIStorageCapacity` objects are used if the API server
+    /// serves them and the CSI driver publishes them. Otherwise the
+    /// allocatable ephemeral storage of the schedulable nodes is used.
+    /// The detection fails if the storage class does not exist because
+    /// volumes could not be provisioned at all.
+    pub fn detect(client: &TestKubeClient, storage_class: &str) -> Self {
+        assert!(
+            client.find::<StorageClass>(storage_class).is_some(),
+            "The storage class [{}] does not exist.",
+            storage_class
+        );
+
+        let csi_storage_capacities = client
+            .discover("storage.k8s.io")
+            .versions("CSIStorageCapacity")
+            .first()
+            .map(|version| {
+                let gvk = GroupVersionKind::gvk("storage.k8s.io", version, "CSIStorageCapacity");
+                client.list_dynamic(&gvk, "")
+            })
+            .unwrap_or_default();
+        let nodes = client.list_all_namespaces::<Node>().items;
+        StorageCapacity::from_objects(storage_class, &csi_storage_capacities, &nodes)
+    }
+
+    fn from_objects(
+        storage_class: &str,
+        csi_storage_capacities: &[DynamicObject],
+        nodes: &[Node],
+    ) -> Self {
+        let csi_capacities = csi_storage_capacities
+            .iter()
+            .filter(|capacity| capacity.data["storageClassName"] == storage_class)
+            .map(|capacity| {
+                // The maximum volume size is smaller than the capacity if
+                // the capacity is fragmented.
+                let quantity = capacity.data["maximumVolumeSize"]
+                    .as_str()
+                    .or_else(|| capacity.data["capacity"].as_str());
+                quantity
+                    .and_then(|quantity| parse_memory_bytes(quantity).ok())
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<_>>();
+
+        if csi_capacities.is_empty() {
+            let ephemeral_storage = nodes
+                .iter()
+                .filter(|node| is_schedulable(node))
+                .filter_map(|node| {
+                    node.status
+                        .as_ref()
+                        .and_then(|status| status.allocatable.as_ref())
+                        .and_then(|allocatable| allocatable.get("ephemeral-storage"))
+                        .and_then(|quantity| parse_memory_bytes(&quantity.0).ok())
+                })
+                .max()
+                .unwrap_or_default();
+            StorageCapacity::EphemeralStorage(ephemeral_storage)
+        } else {
+            StorageCapacity::Csi(csi_capacities.into_iter().max().unwrap_or_default())
+        }
+    }
+
+    /// Returns the size in bytes of the largest volume which can be
+    /// provisioned.
+    pub fn bytes(&self) -> u64 {
+        match self {
+            StorageCapacity::Csi(bytes) | StorageCapacity::EphemeralStorage(bytes) => *bytes,
+        }
+    }
+}
+
+/// Verifies that the given storage class can provision a volume of the
+/// required size, e.g. `10Gi`, and panics with a description of the
+/// available capacity otherwise.
+///
+/// See [`StorageCapacity::detect`] for how the capacity is determined.
+pub fn verify_storage_capacity(client: &TestKubeClient, storage_class: &str, required: &str) {
+    let required_bytes = parse_memory_bytes(required).unwrap_or_else(|error| panic!("{}", error));
+    let capacity = StorageCapacity::detect(client, storage_class);
+    if capacity.bytes() < required_bytes {
+        let source = match capacity {
+            StorageCapacity::Csi(_) => "the CSIStorageCapacity objects report",
+            StorageCapacity::EphemeralStorage(_) => {
+                "the CSIStorageCapacity objects are missing and the largest node has an \
+                allocatable ephemeral storage of"
+            }
+        };
+        panic!(
+            "The storage class [{}] cannot provide a volume of [{}] because {} only [{}] byte(s).",
+            storage_class,
+            required,
+            source,
+            capacity.bytes()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test::kube::from_yaml;
+    use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+    use serde_json::json;
+    use spectral::prelude::*;
+
+    fn node(name: &str, allocatable_pods: u32, taint: Option<&str>) -> Node {
+        let mut node: Node = from_yaml(&format!(
+            "
+            metadata:
+              name: {}
+            status:
+              allocatable:
+                pods: '{}'
+            ",
+            name, allocatable_pods
+        ));
+        if let Some(effect) = taint {
+            node.spec = Some(from_yaml(&format!(
+                "taints: [{{ key: test, effect: {} }}]",
+                effect
+            )));
+        }
+        node
+    }
+
+    fn pod(node_name: &str, phase: &str) -> Pod {
+        from_yaml(&format!(
+            "
+            metadata:
+              name: pod
+            spec:
+              nodeName: {}
+              containers: []
+            status:
+              phase: {}
+            ",
+            node_name, phase
+        ))
+    }
+
+    #[test]
+    fn capacity_should_consider_running_pods_and_unschedulable_nodes() {
+        let capacity = ClusterCapacity::from_nodes(
+            &[
+                node("node-1", 3, None),
+                node("node-2", 2, None),
+                node("node-3", 10, Some("NoSchedule")),
+                node("node-4", 10, Some("PreferNoSchedule")),
+            ],
+            &[
+                pod("node-1", "Running"),
+                pod("node-2", "Running"),
+                pod("node-2", "Pending"),
+                pod("node-2", "Succeeded"),
+            ],
+        );
+
+        assert_that(&capacity.free_pods_per_node).is_equal_to(vec![2, 0, 10]);
+        assert_that(&capacity.free_pods()).is_equal_to(12);
+        assert_that(&capacity.nodes_with_free_pods()).is_equal_to(2);
+    }
+
+    #[test]
+    fn storage_capacity_should_prefer_csi_storage_capacities_of_the_storage_class() {
+        let csi_storage_capacity = |storage_class: &str, capacity: &str| -> DynamicObject {
+            from_yaml(&format!(
+                "
+                apiVersion: storage.k8s.io/v1beta1
+                kind: CSIStorageCapacity
+                metadata:
+                  name: capacity
+                storageClassName: {}
+                capacity: {}
+                ",
+                storage_class, capacity
+            ))
+        };
+        let mut node = node("node-1", 10, None);
+        node.status
+            .get_or_insert_with(Default::default)
+            .allocatable
+            .get_or_insert_with(Default::default)
+            .insert(
+                String::from("ephemeral-storage"),
+                Quantity(String::from("100Gi")),
+            );
+        let mut capacities = [
+            csi_storage_capacity("standard", "5Gi"),
+            csi_storage_capacity("standard", "20Gi"),
+            csi_storage_capacity("fast", "50Gi"),
+        ];
+        capacities[1].data["maximumVolumeSize"] = json!("8Gi");
+
+        assert_that(&StorageCapacity::from_objects(
+            "standard",
+            &capacities,
+            &[node.clone()],
+        ))
+        .is_equal_to(StorageCapacity::Csi(8 << 30));
+        assert_that(&StorageCapacity::from_objects(
+            "local-path",
+            &capacities,
+            &[node],
+        ))
+        .is_equal_to(StorageCapacity::EphemeralStorage(100 << 30));
+    }
+
+    #[test]
+    fn replicas_should_be_scaled_down_or_skipped() {
+        let capacity = ClusterCapacity {
+            free_pods_per_node: vec![2, 1, 0],
+        };
+
+        assert_that(&capacity.scale_replicas(3, 1)).contains_value(3);
+        assert_that(&capacity.scale_replicas(5, 1)).contains_value(3);
+        assert_that(&capacity.scale_replicas(5, 4)).is_none();
+        assert_that(&capacity.scale_replicas_one_per_node(3, 1)).contains_value(2);
+        assert_that(&capacity.scale_replicas_one_per_node(3, 3)).is_none();
+    }
+}
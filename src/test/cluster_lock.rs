@@ -0,0 +1,140 @@
+//! Lock which serializes test cases across processes
+
+use super::prelude::{KubeClient, TestKubeClient};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+/// A lock on the cluster which is released when it goes out of scope
+///
+/// The lock is based on a Lease object of the API group
+/// `coordination.k8s.io`. Therefore it serializes test cases which
+/// cannot run concurrently, e.g. test cases which mutate nodes, across
+/// processes and even across CI jobs which share the cluster.
+///
+/// ```rust,no_run
+/// use integration_test_commons::test::prelude::*;
+/// use std::time::Duration;
+///
+/// let client = TestKubeClient::new();
+/// let _lock = ClusterLock::acquire(&client, "node-mutation", Duration::from_secs(300));
+/// // mutate the nodes
+/// ```
+pub struct ClusterLock<'a> {
+    client: &'a TestKubeClient,
+    name: String,
+    holder_identity: String,
+    renewal: LeaseRenewal,
+}
+
+impl<'a> ClusterLock<'a> {
+    /// Acquires the lock with the given name and blocks until it is
+    /// acquired.
+    ///
+    /// The lease is renewed in the background while the lock is held,
+    /// so the lock is only lost after `lease_duration` if the test
+    /// process crashed or the renewals failed. In the latter case the
+    /// mutual exclusion was not guaranteed and dropping the lock
+    /// panics.
+    pub fn acquire(client: &'a TestKubeClient, name: &str, lease_duration: Duration) -> Self {
+        let holder_identity = Uuid::new_v4().to_string();
+        client.acquire_lease(name, &holder_identity, lease_duration);
+        let renewal = client.block_on(async {
+            LeaseRenewal::start(client.kube_client(), name, &holder_identity, lease_duration)
+        });
+        ClusterLock {
+            client,
+            name: name.to_owned(),
+            holder_identity,
+            renewal,
+        }
+    }
+}
+
+impl<'a> Drop for ClusterLock<'a> {
+    fn drop(&mut self) {
+        let lost = self.client.block_on(self.renewal.stop());
+        let released = self.client.block_on(
+            self.client
+                .kube_client()
+                .release_lease(&self.name, &self.holder_identity),
+        );
+
+        if thread::panicking() {
+            if let Err(error) = released {
+                eprintln!("Lease [{}] could not be released: {}", self.name, error);
+            }
+        } else {
+            released.expect("Lease could not be released");
+            assert!(
+                !lost,
+                "Lease [{}] was lost before the lock was released, so the mutual exclusion \
+                was not guaranteed.",
+                self.name
+            );
+        }
+    }
+}
+
+/// Renewal of a held lease in the background
+///
+/// The lease is renewed after a third of its duration, so that single
+/// failed renewals, e.g. during a short control plane blip, do not let
+/// the lease expire.
+pub(crate) struct LeaseRenewal {
+    task: JoinHandle<()>,
+    lost: Arc<AtomicBool>,
+}
+
+impl LeaseRenewal {
+    /// Starts renewing the given lease on the current runtime.
+    pub(crate) fn start(
+        client: &KubeClient,
+        name: &str,
+        holder_identity: &str,
+        lease_duration: Duration,
+    ) -> Self {
+        let client = client.in_namespace(client.namespace());
+        let name = name.to_owned();
+        let holder_identity = holder_identity.to_owned();
+        let interval = (lease_duration / 3).max(Duration::from_secs(1));
+        let lost = Arc::new(AtomicBool::new(false));
+        let lost_flag = lost.clone();
+
+        let task = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                match client
+                    .renew_lease(&name, &holder_identity, lease_duration)
+                    .await
+                {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        lost_flag.store(true, Ordering::SeqCst);
+                        return;
+                    }
+                    Err(error) => eprintln!("Lease [{}] could not be renewed: {}", name, error),
+                }
+            }
+        });
+
+        LeaseRenewal { task, lost }
+    }
+
+    /// Stops the renewal and returns true if the lease was lost in the
+    /// meantime.
+    pub(crate) async fn stop(&mut self) -> bool {
+        self.task.abort();
+        let _ = (&mut self.task).await;
+        self.lost.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for LeaseRenewal {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,139 @@
+//! Resilience against a temporarily unavailable API server
+//!
+//! Short control plane blips, e.g. on K3s CI nodes, interrupt watches
+//! and requests, and overloaded API servers respond with transient
+//! errors. Operations which only read the cluster state or which are
+//! idempotent, like server-side apply and merge patches, are retried with
+//! an exponential backoff. If the API server stays unreachable then a
+//! [`ClusterUnreachable`] error is returned.
+
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+/// Policy for retrying operations if the API server is unreachable
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReconnectPolicy {
+    /// Maximum number of retries before [`ClusterUnreachable`] is
+    /// returned
+    pub max_retries: usize,
+    /// Backoff before the first retry which is doubled on each retry
+    pub initial_backoff: Duration,
+    /// Upper bound for the backoff
+    pub max_backoff: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        ReconnectPolicy {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(8),
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Returns the backoff before the given retry starting at 0.
+    pub fn backoff(&self, retry: usize) -> Duration {
+        let factor = 2u32.saturating_pow(retry as u32);
+        self.initial_backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
+    }
+}
+
+/// Error which is returned if the API server is still unreachable after
+/// all retries
+///
+/// The error can be detected with `error.downcast_ref::<ClusterUnreachable>()`.
+#[derive(Debug)]
+pub struct ClusterUnreachable {
+    /// Number of failed attempts
+    pub attempts: usize,
+    /// Error of the last attempt
+    pub last_error: anyhow::Error,
+}
+
+impl fmt::Display for ClusterUnreachable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The API server is unreachable after {} attempts: {}",
+            self.attempts, self.last_error
+        )
+    }
+}
+
+impl Error for ClusterUnreachable {}
+
+/// Returns true if the given error indicates that the API server is
+/// unreachable or temporarily unable to handle the request, e.g. if the
+/// connection was refused or reset, a watch was disconnected, or the API
+/// server responded with `429 Too Many Requests` or with one of the
+/// server errors `500`, `502`, `503` and `504`. Server errors caused by
+/// admission webhooks are not transient and therefore not considered.
+pub fn is_connectivity_error(error: &anyhow::Error) -> bool {
+    match error.downcast_ref::<kube::Error>() {
+        Some(kube::Error::Connection(_))
+        | Some(kube::Error::HyperError(_))
+        | Some(kube::Error::Service(_))
+        | Some(kube::Error::ReadEvents(_)) => true,
+        Some(kube::Error::Api(response)) => match response.code {
+            429 => true,
+            500 | 502 | 503 | 504 => !response.message.contains("webhook"),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use kube::error::ErrorResponse;
+    use spectral::prelude::*;
+
+    #[test]
+    fn backoff_should_be_doubled_up_to_the_maximum() {
+        let policy = ReconnectPolicy {
+            max_retries: 10,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(5),
+        };
+
+        assert_that(&policy.backoff(0)).is_equal_to(Duration::from_secs(1));
+        assert_that(&policy.backoff(1)).is_equal_to(Duration::from_secs(2));
+        assert_that(&policy.backoff(2)).is_equal_to(Duration::from_secs(4));
+        assert_that(&policy.backoff(3)).is_equal_to(Duration::from_secs(5));
+        assert_that(&policy.backoff(100)).is_equal_to(Duration::from_secs(5));
+    }
+
+    #[test]
+    fn connectivity_errors_should_be_detected() {
+        let api_error = |code, message: &str| {
+            anyhow::Error::from(kube::Error::Api(ErrorResponse {
+                status: String::from("Failure"),
+                message: String::from(message),
+                reason: String::new(),
+                code,
+            }))
+        };
+
+        assert_that(&is_connectivity_error(&api_error(429, ""))).is_true();
+        assert_that(&is_connectivity_error(&api_error(500, ""))).is_true();
+        assert_that(&is_connectivity_error(&api_error(502, ""))).is_true();
+        assert_that(&is_connectivity_error(&api_error(503, ""))).is_true();
+        assert_that(&is_connectivity_error(&api_error(504, ""))).is_true();
+        assert_that(&is_connectivity_error(&api_error(501, ""))).is_false();
+        assert_that(&is_connectivity_error(&api_error(505, ""))).is_false();
+        assert_that(&is_connectivity_error(&api_error(
+            500,
+            "Internal error occurred: failed calling webhook \"validate.example.com\"",
+        )))
+        .is_false();
+        assert_that(&is_connectivity_error(&api_error(404, ""))).is_false();
+        assert_that(&is_connectivity_error(&anyhow::anyhow!("other"))).is_false();
+    }
+}
@@ -0,0 +1,191 @@
+//! Tar archives for copying files to and from pods
+
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Splits the given absolute path in a container into the directory
+/// and the name of the file or directory.
+pub fn split_remote_path(remote_path: &str) -> Result<(&str, &str)> {
+    let remote_path = remote_path.trim_end_matches('/');
+    match remote_path.rsplit_once('/') {
+        Some((dir, name)) if !name.is_empty() && name != ".." => {
+            Ok((if dir.is_empty() { "/" } else { dir }, name))
+        }
+        _ => Err(anyhow!(
+            "[{}] is not an absolute path of a file or directory.",
+            remote_path
+        )),
+    }
+}
+
+/// Packs the given local file or directory into a tar archive under the
+/// given name.
+pub fn pack(local_path: &Path, name: &str) -> Result<Vec<u8>> {
+    let mut builder = tar::Builder::new(Vec::new());
+    if local_path.is_dir() {
+        builder.append_dir_all(name, local_path)?;
+    } else {
+        builder.append_path_with_name(local_path, name)?;
+    }
+    Ok(builder.into_inner()?)
+}
+
+/// Unpacks the entries of the tar archive below the given name to the
+/// local path.
+///
+/// If the archive contains a single file under the given name then it
+/// is unpacked to the local path itself. Symbolic and hard links which
+/// point outside of the local path are rejected.
+pub fn unpack(archive: &[u8], name: &str, local_path: &Path) -> Result<()> {
+    let mut archive = tar::Archive::new(archive);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.into_owned();
+        let relative_path = entry_path.strip_prefix(name).map_err(|_| {
+            anyhow!(
+                "Archive contains the unexpected entry [{}].",
+                entry_path.display()
+            )
+        })?;
+
+        if relative_path
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_)))
+        {
+            return Err(anyhow!(
+                "Archive contains the unsafe entry [{}].",
+                entry_path.display()
+            ));
+        }
+
+        let target = if relative_path.as_os_str().is_empty() {
+            local_path.to_path_buf()
+        } else {
+            local_path.join(relative_path)
+        };
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_symlink() || entry_type.is_hard_link() {
+            let link_name = entry
+                .link_name()?
+                .map(|link_name| link_name.into_owned())
+                .unwrap_or_default();
+            let link_target = if entry_type.is_symlink() {
+                // Symbolic links are resolved relative to their directory.
+                relative_path.parent().map(|dir| dir.join(&link_name))
+            } else {
+                // Hard links refer to other entries of the archive.
+                link_name.strip_prefix(name).ok().map(PathBuf::from)
+            };
+            match link_target {
+                Some(link_target) if is_contained(&link_target) => {
+                    if entry_type.is_hard_link() {
+                        fs::hard_link(local_path.join(link_target), &target)?;
+                        continue;
+                    }
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "Archive contains the link [{}] to [{}] which points outside of [{}].",
+                        entry_path.display(),
+                        link_name.display(),
+                        local_path.display()
+                    ))
+                }
+            }
+        }
+
+        entry.unpack(target)?;
+    }
+    Ok(())
+}
+
+/// Returns true if the given relative path does not leave the directory
+/// it is relative to.
+fn is_contained(path: &Path) -> bool {
+    let mut depth = 0usize;
+    for component in path.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => depth -= 1,
+            _ => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use spectral::prelude::*;
+    use std::env;
+    use uuid::Uuid;
+
+    #[test]
+    fn split_remote_path_should_split_absolute_paths() {
+        assert_that(&split_remote_path("/stackable/conf/zoo.cfg").unwrap())
+            .is_equal_to(("/stackable/conf", "zoo.cfg"));
+        assert_that(&split_remote_path("/data/").unwrap()).is_equal_to(("/", "data"));
+        assert_that(&split_remote_path("zoo.cfg")).is_err();
+        assert_that(&split_remote_path("/")).is_err();
+    }
+
+    #[test]
+    fn unpack_should_restore_packed_directories() {
+        let temp_dir = env::temp_dir().join(format!("copy-{}", Uuid::new_v4()));
+        let source = temp_dir.join("source");
+        fs::create_dir_all(source.join("nested")).unwrap();
+        fs::write(source.join("nested/data.txt"), "data").unwrap();
+
+        let archive = pack(&source, "conf").unwrap();
+        unpack(&archive, "conf", &temp_dir.join("target")).unwrap();
+
+        let copied = fs::read_to_string(temp_dir.join("target/nested/data.txt"));
+        fs::remove_dir_all(&temp_dir).unwrap();
+        assert_that(&copied.unwrap()).is_equal_to(String::from("data"));
+    }
+
+    #[test]
+    fn unpack_should_restore_packed_files() {
+        let temp_dir = env::temp_dir().join(format!("copy-{}", Uuid::new_v4()));
+        fs::create_dir_all(&temp_dir).unwrap();
+        fs::write(temp_dir.join("source.txt"), "data").unwrap();
+
+        let archive = pack(&temp_dir.join("source.txt"), "zoo.cfg").unwrap();
+        unpack(&archive, "zoo.cfg", &temp_dir.join("target.txt")).unwrap();
+
+        let copied = fs::read_to_string(temp_dir.join("target.txt"));
+        fs::remove_dir_all(&temp_dir).unwrap();
+        assert_that(&copied.unwrap()).is_equal_to(String::from("data"));
+    }
+
+    #[test]
+    fn unpack_should_reject_links_outside_of_the_local_path() {
+        let link = |target: &str| {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            let mut builder = tar::Builder::new(Vec::new());
+            builder
+                .append_link(&mut header, "conf/nested/link", target)
+                .unwrap();
+            builder.into_inner().unwrap()
+        };
+        let temp_dir = env::temp_dir().join(format!("copy-{}", Uuid::new_v4()));
+
+        let outside = unpack(&link("../../passwd"), "conf", &temp_dir);
+        let absolute = unpack(&link("/etc/passwd"), "conf", &temp_dir);
+        let inside = unpack(&link("../data.txt"), "conf", &temp_dir);
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+        assert_that(&outside).is_err();
+        assert_that(&absolute).is_err();
+        assert_that(&inside).is_ok();
+    }
+}
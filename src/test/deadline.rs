@@ -0,0 +1,122 @@
+//! Wall-clock budget of a test case
+
+use super::redaction::redact_text;
+use std::io::{self, Write};
+use std::process;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// A guard which aborts the test process if the test case exceeds its
+/// wall-clock budget
+///
+/// The individual waits of a test case are bounded by their own
+/// timeouts, but if they are set too high then a single stuck test case
+/// can consume the entire time limit of a CI job. The deadline bounds
+/// the whole test case. When it expires, the diagnostics are printed
+/// and the process is aborted because a running thread cannot be
+/// stopped from the outside. Other test cases running in the same
+/// process are aborted as well.
+///
+/// The deadline is cancelled when the guard is dropped.
+///
+/// ```rust,no_run
+/// use integration_test_commons::test::prelude::*;
+/// use std::time::Duration;
+///
+/// let _deadline = TestDeadline::start(Duration::from_secs(600));
+/// // run the test
+/// ```
+pub struct TestDeadline {
+    cancel: Option<Sender<()>>,
+    watchdog: Option<JoinHandle<()>>,
+}
+
+impl TestDeadline {
+    /// Starts a deadline which expires after the given budget.
+    pub fn start(budget: Duration) -> Self {
+        TestDeadline::start_with_diagnostics(budget, String::new)
+    }
+
+    /// Starts a deadline which expires after the given budget and prints
+    /// the diagnostics returned by the given function on expiry, e.g.
+    /// the state of the pods under test. Credentials in the diagnostics
+    /// are redacted.
+    pub fn start_with_diagnostics<F>(budget: Duration, diagnostics: F) -> Self
+    where
+        F: FnOnce() -> String + Send + 'static,
+    {
+        let test_name = thread::current().name().unwrap_or("<unnamed>").to_owned();
+        let (cancel, cancelled) = mpsc::channel::<()>();
+
+        let watchdog = thread::spawn(move || {
+            if let Err(RecvTimeoutError::Timeout) = cancelled.recv_timeout(budget) {
+                // The output of eprintln! is captured by the test harness
+                // and lost on abort, so the message is written directly
+                // to stderr.
+                let message = expiry_message(&test_name, budget, &diagnostics());
+                let mut stderr = io::stderr();
+                let _ = stderr
+                    .write_all(format!("{}\n", message).as_bytes())
+                    .and_then(|_| stderr.flush());
+                process::abort();
+            }
+        });
+
+        TestDeadline {
+            cancel: Some(cancel),
+            watchdog: Some(watchdog),
+        }
+    }
+}
+
+impl Drop for TestDeadline {
+    fn drop(&mut self) {
+        if let Some(cancel) = self.cancel.take() {
+            let _ = cancel.send(());
+        }
+        if let Some(watchdog) = self.watchdog.take() {
+            let _ = watchdog.join();
+        }
+    }
+}
+
+fn expiry_message(test_name: &str, budget: Duration, diagnostics: &str) -> String {
+    let mut message = format!(
+        "Test [{}] exceeded its deadline of {} second(s) and is aborted.",
+        test_name,
+        budget.as_secs()
+    );
+    if !diagnostics.is_empty() {
+        message.push_str("\nDiagnostics:\n");
+        message.push_str(&redact_text(diagnostics));
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn deadline_should_be_cancelled_when_dropped() {
+        let deadline = TestDeadline::start(Duration::from_millis(100));
+        drop(deadline);
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    #[test]
+    fn expiry_message_should_contain_the_diagnostics() {
+        assert_that(&expiry_message("test", Duration::from_secs(60), "")).is_equal_to(
+            String::from("Test [test] exceeded its deadline of 60 second(s) and is aborted."),
+        );
+        assert_that(&expiry_message(
+            "test",
+            Duration::from_secs(60),
+            "pod pending",
+        ))
+        .ends_with("\nDiagnostics:\npod pending");
+    }
+}
@@ -0,0 +1,129 @@
+//! Debugging of failed test cases on the live cluster
+//!
+//! If the environment variable `KUBE_TEST_PAUSE_ON_FAILURE` is set to
+//! `1`, a failed test case is paused before its resources are cleaned
+//! up. Hints how to inspect the cluster are printed and the test case
+//! continues after a key was pressed or the timeout elapsed. The
+//! timeout in seconds can be set with the environment variable
+//! `KUBE_TEST_PAUSE_TIMEOUT` and defaults to 10 minutes.
+//!
+//! The hook is installed when a [`TestKubeClient`] is created.
+//!
+//! [`TestKubeClient`]: super::kube::TestKubeClient
+
+use super::skip::skip_reason;
+use once_cell::sync::Lazy;
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc;
+use std::time::Duration;
+use std::{env, panic, thread};
+
+/// Name of the environment variable which enables the pause on failure
+pub const PAUSE_ON_FAILURE_ENV_VAR: &str = "KUBE_TEST_PAUSE_ON_FAILURE";
+
+/// Name of the environment variable which contains the pause timeout in
+/// seconds
+pub const PAUSE_TIMEOUT_ENV_VAR: &str = "KUBE_TEST_PAUSE_TIMEOUT";
+
+const DEFAULT_PAUSE_TIMEOUT: Duration = Duration::from_secs(600);
+
+static PAUSE_ON_FAILURE_HOOK: Lazy<()> = Lazy::new(|| {
+    if env::var(PAUSE_ON_FAILURE_ENV_VAR).as_deref() != Ok("1") {
+        return;
+    }
+
+    let timeout = parse_timeout(env::var(PAUSE_TIMEOUT_ENV_VAR).ok().as_deref());
+
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        if skip_reason(info.payload()).is_some() {
+            return;
+        }
+        // The output of eprintln! is captured by the test harness and
+        // only shown after the test case finished, so the hints are
+        // written directly to stderr.
+        let thread = thread::current();
+        let hints = hints(thread.name().unwrap_or("<unnamed>"), timeout);
+        let mut stderr = io::stderr();
+        let _ = writeln!(stderr, "{}", hints).and_then(|_| stderr.flush());
+        wait_for_keypress(timeout);
+    }));
+});
+
+/// Installs the panic hook which pauses failed test cases if it is
+/// enabled by the environment variable `KUBE_TEST_PAUSE_ON_FAILURE`.
+///
+/// The hook is installed only once, so this function can be called
+/// repeatedly.
+pub fn install_pause_on_failure_hook() {
+    Lazy::force(&PAUSE_ON_FAILURE_HOOK);
+}
+
+/// Parses the pause timeout in seconds or returns the default timeout.
+fn parse_timeout(timeout: Option<&str>) -> Duration {
+    timeout
+        .map(|secs| {
+            secs.parse()
+                .map(Duration::from_secs)
+                .unwrap_or_else(|_| panic!("{} must be an unsigned integer", PAUSE_TIMEOUT_ENV_VAR))
+        })
+        .unwrap_or(DEFAULT_PAUSE_TIMEOUT)
+}
+
+/// Returns the hints how to inspect the cluster after the given test
+/// case failed.
+fn hints(test_name: &str, timeout: Duration) -> String {
+    format!(
+        "Test [{test_name}] failed and is paused for {timeout} seconds \
+        before its resources are cleaned up. The cluster can be inspected with:\n\
+        \n\
+        \x20   kubectl get pods --all-namespaces --show-labels\n\
+        \x20   kubectl describe pod <pod> --namespace <namespace>\n\
+        \x20   kubectl logs <pod> --namespace <namespace> --all-containers\n\
+        \x20   kubectl exec -it <pod> --namespace <namespace> -- sh\n\
+        \x20   kubectl port-forward <pod> <local-port>:<pod-port> --namespace <namespace>\n\
+        \n\
+        Press Enter to continue.",
+        test_name = test_name,
+        timeout = timeout.as_secs()
+    )
+}
+
+/// Blocks until a line was read from stdin or the timeout elapsed.
+fn wait_for_keypress(timeout: Duration) {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut line = String::new();
+        let _ = io::stdin().lock().read_line(&mut line);
+        let _ = sender.send(());
+    });
+    let _ = receiver.recv_timeout(timeout);
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn parse_timeout_should_fall_back_to_the_default_timeout() {
+        assert_that(&parse_timeout(None)).is_equal_to(DEFAULT_PAUSE_TIMEOUT);
+        assert_that(&parse_timeout(Some("30"))).is_equal_to(Duration::from_secs(30));
+    }
+
+    #[test]
+    #[should_panic(expected = "KUBE_TEST_PAUSE_TIMEOUT must be an unsigned integer")]
+    fn parse_timeout_should_reject_invalid_values() {
+        parse_timeout(Some("ten"));
+    }
+
+    #[test]
+    fn hints_should_contain_the_test_name_and_timeout() {
+        let hints = hints("tests::it_works", Duration::from_secs(60));
+
+        assert_that(&hints).contains("Test [tests::it_works] failed and is paused for 60 seconds");
+        assert_that(&hints).contains("kubectl exec -it <pod>");
+    }
+}
@@ -0,0 +1,283 @@
+//! Report of the test environment
+//!
+//! Failures in CI often depend on the environment, e.g. on the
+//! Kubernetes version, the architecture of the nodes, or the versions
+//! of the installed operators. The [`EnvironmentReport`] collects this
+//! metadata at the start of a test suite, prints it, and writes it as
+//! JSON artifact into the directory given by
+//! `INTEGRATION_TEST_ARTIFACTS_DIR`, so that it can be archived next to
+//! the JSON or JUnit output of the test runner.
+
+use super::bootstrap::bootstrap_once_in_cluster;
+use super::prelude::TestKubeClient;
+use anyhow::Result;
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::core::v1::Node;
+use serde::Serialize;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+/// Name of the environment variable which contains the directory of
+/// the test artifacts
+pub const ARTIFACTS_DIR_ENV_VAR: &str = "INTEGRATION_TEST_ARTIFACTS_DIR";
+
+/// File name of the report in the artifacts directory
+pub const REPORT_FILE_NAME: &str = "environment.json";
+
+/// Label which contains the name of an application
+const NAME_LABEL: &str = "app.kubernetes.io/name";
+
+/// Label which contains the version of an application
+const VERSION_LABEL: &str = "app.kubernetes.io/version";
+
+/// Metadata of the cluster and the installed operators
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvironmentReport {
+    pub kubernetes_version: String,
+    pub nodes: Vec<NodeReport>,
+    pub operators: Vec<OperatorReport>,
+}
+
+/// Metadata of a node
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeReport {
+    pub name: String,
+    pub os_image: String,
+    pub architecture: String,
+    pub kubelet_version: String,
+    pub cpu: String,
+    pub memory: String,
+}
+
+/// Metadata of an installed Stackable operator
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperatorReport {
+    pub name: String,
+    pub namespace: String,
+    pub version: String,
+    pub images: Vec<String>,
+}
+
+impl EnvironmentReport {
+    /// Collects the metadata of the cluster and of the operators, i.e.
+    /// the Deployments whose name label ends with `-operator`.
+    pub fn collect(client: &TestKubeClient) -> Self {
+        EnvironmentReport::from_resources(
+            client.get_kubernetes_version(),
+            &client.list_without_label_defaults::<Node>("").items,
+            &client
+                .list_without_label_defaults::<Deployment>(NAME_LABEL)
+                .items,
+        )
+    }
+
+    fn from_resources(
+        kubernetes_version: String,
+        nodes: &[Node],
+        deployments: &[Deployment],
+    ) -> Self {
+        let nodes = nodes
+            .iter()
+            .map(|node| {
+                let status = node.status.clone().unwrap_or_default();
+                let node_info = status.node_info.unwrap_or_default();
+                let capacity = status.capacity.unwrap_or_default();
+                let quantity = |resource: &str| {
+                    capacity
+                        .get(resource)
+                        .map(|quantity| quantity.0.clone())
+                        .unwrap_or_default()
+                };
+                NodeReport {
+                    name: node.metadata.name.clone().unwrap_or_default(),
+                    os_image: node_info.os_image,
+                    architecture: node_info.architecture,
+                    kubelet_version: node_info.kubelet_version,
+                    cpu: quantity("cpu"),
+                    memory: quantity("memory"),
+                }
+            })
+            .collect();
+
+        let operators = deployments
+            .iter()
+            .filter_map(|deployment| {
+                let labels = deployment.metadata.labels.clone().unwrap_or_default();
+                let name = labels.get(NAME_LABEL)?;
+                if !name.ends_with("-operator") {
+                    return None;
+                }
+                let images = deployment
+                    .spec
+                    .as_ref()
+                    .and_then(|spec| spec.template.spec.as_ref())
+                    .map(|pod_spec| {
+                        pod_spec
+                            .containers
+                            .iter()
+                            .filter_map(|container| container.image.clone())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                Some(OperatorReport {
+                    name: name.clone(),
+                    namespace: deployment.metadata.namespace.clone().unwrap_or_default(),
+                    version: labels.get(VERSION_LABEL).cloned().unwrap_or_default(),
+                    images,
+                })
+            })
+            .collect();
+
+        EnvironmentReport {
+            kubernetes_version,
+            nodes,
+            operators,
+        }
+    }
+
+    /// Writes the report as JSON into the artifacts directory if
+    /// `INTEGRATION_TEST_ARTIFACTS_DIR` is set and returns the path of
+    /// the written file.
+    pub fn write_artifact(&self) -> Result<Option<PathBuf>> {
+        let directory = match env::var(ARTIFACTS_DIR_ENV_VAR) {
+            Ok(directory) => PathBuf::from(directory),
+            Err(_) => return Ok(None),
+        };
+
+        fs::create_dir_all(&directory)?;
+        let path = directory.join(REPORT_FILE_NAME);
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(Some(path))
+    }
+}
+
+impl fmt::Display for EnvironmentReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Kubernetes version: {}", self.kubernetes_version)?;
+        for node in &self.nodes {
+            writeln!(
+                f,
+                "Node {}: {} ({}), kubelet {}, {} CPU, {} memory",
+                node.name,
+                node.os_image,
+                node.architecture,
+                node.kubelet_version,
+                node.cpu,
+                node.memory
+            )?;
+        }
+        for operator in &self.operators {
+            writeln!(
+                f,
+                "Operator {}/{}: {} [{}]",
+                operator.namespace,
+                operator.name,
+                operator.version,
+                operator.images.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects, prints, and writes the report of the environment once per
+/// test run.
+///
+/// This function is intended to be called at the start of every test
+/// case, so that the report is available regardless of which test
+/// cases are run.
+pub fn report_environment_once(client: &TestKubeClient) {
+    bootstrap_once_in_cluster(client, "environment-report", || {
+        let report = EnvironmentReport::collect(client);
+        println!("{}", report);
+        if let Some(path) = report.write_artifact()? {
+            println!("Environment report written to [{}]", path.display());
+        }
+        Ok(())
+    })
+    .expect("Environment report could not be written")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test::kube::from_yaml;
+    use spectral::prelude::*;
+
+    #[test]
+    fn report_should_contain_the_nodes_and_the_operators() {
+        let node: Node = from_yaml(
+            "
+            metadata:
+              name: node-1
+            status:
+              capacity:
+                cpu: '4'
+                memory: 8Gi
+              nodeInfo:
+                architecture: arm64
+                bootID: ''
+                containerRuntimeVersion: containerd://1.5.5
+                kernelVersion: 5.10.0
+                kubeProxyVersion: v1.22.2
+                kubeletVersion: v1.22.2
+                machineID: ''
+                operatingSystem: linux
+                osImage: Ubuntu 20.04.3 LTS
+                systemUUID: ''
+            ",
+        );
+        let deployment = |name: &str| -> Deployment {
+            from_yaml(&format!(
+                "
+                metadata:
+                  name: {name}
+                  namespace: stackable
+                  labels:
+                    app.kubernetes.io/name: {name}
+                    app.kubernetes.io/version: 0.3.0
+                spec:
+                  selector: {{}}
+                  template:
+                    spec:
+                      containers:
+                        - name: {name}
+                          image: docker.stackable.tech/stackable/{name}:0.3.0
+                ",
+                name = name
+            ))
+        };
+
+        let report = EnvironmentReport::from_resources(
+            String::from("v1.22.2"),
+            &[node],
+            &[deployment("zookeeper-operator"), deployment("zookeeper")],
+        );
+
+        assert_that(&report).is_equal_to(EnvironmentReport {
+            kubernetes_version: String::from("v1.22.2"),
+            nodes: vec![NodeReport {
+                name: String::from("node-1"),
+                os_image: String::from("Ubuntu 20.04.3 LTS"),
+                architecture: String::from("arm64"),
+                kubelet_version: String::from("v1.22.2"),
+                cpu: String::from("4"),
+                memory: String::from("8Gi"),
+            }],
+            operators: vec![OperatorReport {
+                name: String::from("zookeeper-operator"),
+                namespace: String::from("stackable"),
+                version: String::from("0.3.0"),
+                images: vec![String::from(
+                    "docker.stackable.tech/stackable/zookeeper-operator:0.3.0",
+                )],
+            }],
+        });
+    }
+}
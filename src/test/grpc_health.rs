@@ -0,0 +1,230 @@
+//! Checks of the gRPC health checking protocol
+//!
+//! Products and sidecars which expose gRPC usually implement the
+//! standard health service `grpc.health.v1.Health`. The service is
+//! called through a port forwarding to the pod. The two messages of the
+//! protocol are encoded here, so that no gRPC framework and no code
+//! generation are needed.
+//!
+//! ```rust,no_run
+//! use integration_test_commons::test::grpc_health::{wait_for_grpc_serving, ServingStatus};
+//! use integration_test_commons::test::prelude::*;
+//!
+//! let client = TestKubeClient::new();
+//! let pod = client.find_namespaced::<Pod>("product-0").unwrap();
+//!
+//! wait_for_grpc_serving(&client, &pod, 9090, "", Duration::from_secs(60));
+//! ```
+
+use super::prelude::TestKubeClient;
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use k8s_openapi::api::core::v1::Pod;
+use std::net::SocketAddr;
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+
+/// Path of the `Check` method of the health service
+const CHECK_PATH: &str = "/grpc.health.v1.Health/Check";
+
+/// Serving status of a gRPC service
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ServingStatus {
+    Unknown,
+    Serving,
+    NotServing,
+    ServiceUnknown,
+}
+
+impl From<u64> for ServingStatus {
+    fn from(value: u64) -> Self {
+        match value {
+            1 => ServingStatus::Serving,
+            2 => ServingStatus::NotServing,
+            3 => ServingStatus::ServiceUnknown,
+            _ => ServingStatus::Unknown,
+        }
+    }
+}
+
+/// Returns the serving status of the given service at the given port
+/// of the pod.
+///
+/// The empty service name queries the overall health of the server.
+pub fn check_grpc_health(
+    client: &TestKubeClient,
+    pod: &Pod,
+    port: u16,
+    service: &str,
+) -> Result<ServingStatus> {
+    let port_forward = client.port_forward(pod, port);
+    client.block_on(check(port_forward.local_address(), service))
+}
+
+/// Waits until the given service at the given port of the pod reports
+/// that it is serving within the given timeout.
+///
+/// Failed calls are retried because the server may not listen yet.
+pub fn wait_for_grpc_serving(
+    client: &TestKubeClient,
+    pod: &Pod,
+    port: u16,
+    service: &str,
+    timeout: Duration,
+) {
+    let start = Instant::now();
+    loop {
+        let status = check_grpc_health(client, pod, port, service);
+        if let Ok(ServingStatus::Serving) = status {
+            return;
+        }
+        if start.elapsed() > timeout {
+            panic!(
+                "gRPC service [{}] of pod [{}] is not serving within {} seconds: {:?}",
+                service,
+                pod.metadata.name.as_deref().unwrap_or_default(),
+                timeout.as_secs(),
+                status
+            );
+        }
+        thread::sleep(Duration::from_secs(2));
+    }
+}
+
+/// Calls the `Check` method of the health service at the given address.
+async fn check(address: SocketAddr, service: &str) -> Result<ServingStatus> {
+    let connection = TcpStream::connect(address).await?;
+    let (send_request, connection) = h2::client::handshake(connection).await?;
+    tokio::spawn(async move {
+        let _ = connection.await;
+    });
+
+    let request = http::Request::post(format!("http://{}{}", address, CHECK_PATH))
+        .header(http::header::CONTENT_TYPE, "application/grpc")
+        .header(http::header::TE, "trailers")
+        .body(())?;
+    let (response, mut body) = send_request.ready().await?.send_request(request, false)?;
+    body.send_data(Bytes::from(encode_request(service)), true)?;
+
+    let (parts, mut body) = response.await?.into_parts();
+    let mut message = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        body.flow_control().release_capacity(chunk.len())?;
+        message.extend_from_slice(&chunk);
+    }
+
+    // Errors are sent in the trailers or, if there is no response
+    // message, in the headers.
+    let trailers = body.trailers().await?.unwrap_or(parts.headers);
+    let grpc_status = trailers
+        .get("grpc-status")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if grpc_status != "0" {
+        let grpc_message = trailers
+            .get("grpc-message")
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        return Err(anyhow!(
+            "Health check failed with gRPC status [{}]: {}",
+            grpc_status,
+            grpc_message
+        ));
+    }
+
+    decode_response(&message)
+}
+
+/// Encodes a length-prefixed `HealthCheckRequest` with the given
+/// service name.
+fn encode_request(service: &str) -> Vec<u8> {
+    let mut message = Vec::new();
+    if !service.is_empty() {
+        // field 1 (service), wire type 2 (length-delimited)
+        message.push(0x0a);
+        encode_varint(service.len() as u64, &mut message);
+        message.extend_from_slice(service.as_bytes());
+    }
+
+    let mut frame = vec![0];
+    frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&message);
+    frame
+}
+
+/// Decodes the serving status from a length-prefixed
+/// `HealthCheckResponse`.
+fn decode_response(frame: &[u8]) -> Result<ServingStatus> {
+    let invalid = || anyhow!("Invalid health check response: {:?}", frame);
+
+    if frame.len() < 5 || frame[0] != 0 {
+        return Err(invalid());
+    }
+    let mut message = &frame[5..];
+
+    let mut status = ServingStatus::Unknown;
+    while !message.is_empty() {
+        let key = decode_varint(&mut message).ok_or_else(invalid)?;
+        match key & 0x07 {
+            0 => {
+                let value = decode_varint(&mut message).ok_or_else(invalid)?;
+                if key >> 3 == 1 {
+                    status = ServingStatus::from(value);
+                }
+            }
+            2 => {
+                let length = decode_varint(&mut message).ok_or_else(invalid)? as usize;
+                message = message.get(length..).ok_or_else(invalid)?;
+            }
+            _ => return Err(invalid()),
+        }
+    }
+
+    Ok(status)
+}
+
+fn encode_varint(mut value: u64, buffer: &mut Vec<u8>) {
+    while value >= 0x80 {
+        buffer.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+fn decode_varint(buffer: &mut &[u8]) -> Option<u64> {
+    let mut value = 0;
+    for (index, byte) in buffer.iter().enumerate().take(10) {
+        value |= u64::from(byte & 0x7f) << (7 * index);
+        if byte & 0x80 == 0 {
+            *buffer = &buffer[index + 1..];
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn encode_request_should_prefix_the_message_with_its_length() {
+        assert_that(&encode_request("")).is_equal_to(vec![0, 0, 0, 0, 0]);
+        assert_that(&encode_request("zk")).is_equal_to(vec![0, 0, 0, 0, 4, 0x0a, 2, b'z', b'k']);
+    }
+
+    #[test]
+    fn decode_response_should_return_the_serving_status() {
+        assert_that(&decode_response(&[0, 0, 0, 0, 2, 0x08, 1]).unwrap())
+            .is_equal_to(ServingStatus::Serving);
+        assert_that(&decode_response(&[0, 0, 0, 0, 2, 0x08, 2]).unwrap())
+            .is_equal_to(ServingStatus::NotServing);
+        assert_that(&decode_response(&[0, 0, 0, 0, 0]).unwrap())
+            .is_equal_to(ServingStatus::Unknown);
+        assert_that(&decode_response(&[0, 0, 0])).is_err();
+    }
+}
@@ -0,0 +1,269 @@
+//! HTTP probes of product endpoints
+//!
+//! Products like Superset, NiFi, or Airflow secure their UIs and APIs
+//! with different authentication methods. An [`Authenticator`] sources
+//! the credentials from a Secret in the cluster and applies them to the
+//! requests of an [`HttpProbe`], so that authenticated checks do not
+//! need to be reimplemented in every operator.
+//!
+//! ```rust,no_run
+//! use integration_test_commons::test::http_probe::{Authenticator, HttpProbe};
+//! use integration_test_commons::test::prelude::*;
+//!
+//! let client = TestKubeClient::new();
+//! let authenticator = Authenticator::basic_from_secret(&client, "superset-credentials");
+//! let probe = HttpProbe::new(&client, authenticator);
+//!
+//! let response = probe.get("http://localhost:8088/api/v1/database/");
+//! assert_that(&response.status).is_equal_to(200);
+//! ```
+
+use super::prelude::TestKubeClient;
+use super::redaction::REDACTED;
+use anyhow::Result;
+use reqwest::{Certificate, Client, Identity, RequestBuilder};
+use std::fmt;
+
+/// Default key of the username in a Secret with basic credentials
+pub const USERNAME_KEY: &str = "username";
+/// Default key of the password in a Secret with basic credentials
+pub const PASSWORD_KEY: &str = "password";
+/// Default key of the token in a Secret with a bearer token
+pub const TOKEN_KEY: &str = "token";
+/// Key of the certificate in a Secret of type `kubernetes.io/tls`
+pub const TLS_CERT_KEY: &str = "tls.crt";
+/// Key of the private key in a Secret of type `kubernetes.io/tls`
+pub const TLS_KEY_KEY: &str = "tls.key";
+
+/// Authentication method of a product endpoint
+///
+/// The `Debug` output redacts the credentials.
+#[derive(Clone, Eq, PartialEq)]
+pub enum Authenticator {
+    /// No authentication
+    None,
+    /// HTTP basic authentication
+    Basic { username: String, password: String },
+    /// Bearer token in the `Authorization` header
+    Bearer { token: String },
+    /// TLS client certificate with its private key in PEM format
+    ClientCertificate { certificate: String, key: String },
+}
+
+impl Authenticator {
+    /// Reads the basic credentials from the keys `username` and
+    /// `password` of the given Secret.
+    ///
+    /// The Secret does not need to exist yet, e.g. if it is generated by
+    /// the operator.
+    pub fn basic_from_secret(client: &TestKubeClient, secret_name: &str) -> Self {
+        Authenticator::basic_from_secret_keys(client, secret_name, USERNAME_KEY, PASSWORD_KEY)
+    }
+
+    /// Reads the basic credentials from the given keys of the Secret.
+    pub fn basic_from_secret_keys(
+        client: &TestKubeClient,
+        secret_name: &str,
+        username_key: &str,
+        password_key: &str,
+    ) -> Self {
+        Authenticator::Basic {
+            username: client.wait_for_secret_key(secret_name, username_key, |_| true),
+            password: client.wait_for_secret_key(secret_name, password_key, |_| true),
+        }
+    }
+
+    /// Reads the bearer token from the key `token` of the given Secret.
+    pub fn bearer_from_secret(client: &TestKubeClient, secret_name: &str) -> Self {
+        Authenticator::Bearer {
+            token: client.wait_for_secret_key(secret_name, TOKEN_KEY, |_| true),
+        }
+    }
+
+    /// Reads the client certificate and its private key from the keys
+    /// `tls.crt` and `tls.key` of the given Secret.
+    pub fn client_certificate_from_secret(client: &TestKubeClient, secret_name: &str) -> Self {
+        Authenticator::ClientCertificate {
+            certificate: client.wait_for_secret_key(secret_name, TLS_CERT_KEY, |_| true),
+            key: client.wait_for_secret_key(secret_name, TLS_KEY_KEY, |_| true),
+        }
+    }
+
+    /// Adds the client certificate to the HTTP client.
+    fn configure(&self, builder: reqwest::ClientBuilder) -> Result<reqwest::ClientBuilder> {
+        match self {
+            Authenticator::ClientCertificate { certificate, key } => {
+                let pem = format!("{}\n{}", key, certificate);
+                Ok(builder.identity(Identity::from_pem(pem.as_bytes())?))
+            }
+            _ => Ok(builder),
+        }
+    }
+
+    /// Adds the credentials to the request.
+    fn authenticate(&self, request: RequestBuilder) -> RequestBuilder {
+        match self {
+            Authenticator::Basic { username, password } => {
+                request.basic_auth(username, Some(password))
+            }
+            Authenticator::Bearer { token } => request.bearer_auth(token),
+            Authenticator::None | Authenticator::ClientCertificate { .. } => request,
+        }
+    }
+}
+
+impl fmt::Debug for Authenticator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Authenticator::None => f.write_str("None"),
+            Authenticator::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .field("password", &REDACTED)
+                .finish(),
+            Authenticator::Bearer { .. } => {
+                f.debug_struct("Bearer").field("token", &REDACTED).finish()
+            }
+            Authenticator::ClientCertificate { certificate, .. } => f
+                .debug_struct("ClientCertificate")
+                .field("certificate", certificate)
+                .field("key", &REDACTED)
+                .finish(),
+        }
+    }
+}
+
+/// Response of a product endpoint
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+/// Client for probing product endpoints with the given authentication
+pub struct HttpProbe<'a> {
+    client: &'a TestKubeClient,
+    http_client: Client,
+    authenticator: Authenticator,
+}
+
+impl<'a> HttpProbe<'a> {
+    /// Creates a probe which authenticates its requests with the given
+    /// authenticator.
+    ///
+    /// The runtime of the given client is used to execute the requests.
+    pub fn new(client: &'a TestKubeClient, authenticator: Authenticator) -> Self {
+        HttpProbe::with_ca_certificate(client, authenticator, None)
+    }
+
+    /// Creates a probe which additionally trusts the given CA
+    /// certificate in PEM format, e.g. the certificate of a
+    /// `kubernetes.io/tls` Secret generated for the product.
+    pub fn with_ca_certificate(
+        client: &'a TestKubeClient,
+        authenticator: Authenticator,
+        ca_certificate: Option<&str>,
+    ) -> Self {
+        let build = || -> Result<Client> {
+            let mut builder = authenticator.configure(Client::builder())?;
+            if let Some(ca_certificate) = ca_certificate {
+                builder =
+                    builder.add_root_certificate(Certificate::from_pem(ca_certificate.as_bytes())?);
+            }
+            Ok(builder.build()?)
+        };
+
+        HttpProbe {
+            client,
+            http_client: build().expect("HTTP client could not be created"),
+            authenticator,
+        }
+    }
+
+    /// Sends a GET request to the given URL and returns the response.
+    pub fn get(&self, url: &str) -> HttpResponse {
+        self.send(self.http_client.get(url))
+            .expect("GET request failed")
+    }
+
+    /// Sends a POST request with the given JSON body to the given URL
+    /// and returns the response.
+    pub fn post_json(&self, url: &str, body: &serde_json::Value) -> HttpResponse {
+        self.send(self.http_client.post(url).json(body))
+            .expect("POST request failed")
+    }
+
+    /// Verifies that a GET request to the given URL is answered with the
+    /// expected status code.
+    pub fn verify_status(&self, url: &str, expected_status: u16) {
+        let response = self.get(url);
+        assert_eq!(
+            response.status, expected_status,
+            "Unexpected status of [{}]: {}",
+            url, response.body
+        );
+    }
+
+    fn send(&self, request: RequestBuilder) -> Result<HttpResponse> {
+        let request = self.authenticator.authenticate(request);
+        self.client.block_on(async {
+            let response = request.send().await?;
+            Ok(HttpResponse {
+                status: response.status().as_u16(),
+                body: response.text().await?,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use spectral::prelude::*;
+
+    fn authorization_header(authenticator: &Authenticator) -> Option<String> {
+        authenticator
+            .authenticate(Client::new().get("http://localhost"))
+            .build()
+            .unwrap()
+            .headers()
+            .get(reqwest::header::AUTHORIZATION)
+            .map(|value| value.to_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn authenticate_should_set_the_authorization_header() {
+        let basic = Authenticator::Basic {
+            username: String::from("admin"),
+            password: String::from("admin"),
+        };
+        let bearer = Authenticator::Bearer {
+            token: String::from("secret-token"),
+        };
+
+        assert_that(&authorization_header(&basic))
+            .contains_value(String::from("Basic YWRtaW46YWRtaW4="));
+        assert_that(&authorization_header(&bearer))
+            .contains_value(String::from("Bearer secret-token"));
+        assert_that(&authorization_header(&Authenticator::None)).is_none();
+    }
+
+    #[test]
+    fn debug_output_should_redact_the_credentials() {
+        let basic = Authenticator::Basic {
+            username: String::from("admin"),
+            password: String::from("s3cr3t"),
+        };
+        let bearer = Authenticator::Bearer {
+            token: String::from("secret-token"),
+        };
+
+        assert_that(&format!("{:?}", basic)).is_equal_to(String::from(
+            "Basic { username: \"admin\", password: \"<redacted>\" }",
+        ));
+        assert_that(&format!("{:?}", bearer))
+            .is_equal_to(String::from("Bearer { token: \"<redacted>\" }"));
+        assert_that(&format!("{:?}", Authenticator::None)).is_equal_to(String::from("None"));
+    }
+}
@@ -0,0 +1,134 @@
+//! IP families of the cluster
+//!
+//! Connectivity tests must format endpoint addresses depending on the
+//! IP family, e.g. IPv6 addresses are enclosed in brackets. The
+//! functions in this module allow tests to run on IPv4, IPv6, and
+//! dual-stack clusters.
+
+use super::prelude::TestKubeClient;
+use k8s_openapi::api::core::v1::Node;
+use std::net::IpAddr;
+
+/// IP families supported by the cluster
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct IpFamilies {
+    pub ipv4: bool,
+    pub ipv6: bool,
+}
+
+impl IpFamilies {
+    /// Detects the IP families of the cluster from the pod CIDRs of the
+    /// nodes or, if they are not set, from the internal node addresses.
+    pub fn detect(client: &TestKubeClient) -> Self {
+        IpFamilies::from_nodes(&client.list_without_label_defaults::<Node>("").items)
+    }
+
+    fn from_nodes(nodes: &[Node]) -> Self {
+        let pod_cidrs = nodes
+            .iter()
+            .filter_map(|node| node.spec.as_ref())
+            .flat_map(|spec| {
+                spec.pod_cidrs
+                    .clone()
+                    .or_else(|| spec.pod_cidr.clone().map(|cidr| vec![cidr]))
+                    .unwrap_or_default()
+            })
+            .filter_map(|cidr| cidr.split('/').next().and_then(|ip| ip.parse().ok()))
+            .collect::<Vec<IpAddr>>();
+
+        let addresses = if pod_cidrs.is_empty() {
+            nodes
+                .iter()
+                .filter_map(|node| node.status.as_ref())
+                .flat_map(|status| status.addresses.clone().unwrap_or_default())
+                .filter(|address| address.type_ == "InternalIP")
+                .filter_map(|address| address.address.parse().ok())
+                .collect()
+        } else {
+            pod_cidrs
+        };
+
+        IpFamilies {
+            ipv4: addresses.iter().any(IpAddr::is_ipv4),
+            ipv6: addresses.iter().any(IpAddr::is_ipv6),
+        }
+    }
+
+    /// Returns true if the cluster supports IPv4 and IPv6.
+    pub fn is_dual_stack(&self) -> bool {
+        self.ipv4 && self.ipv6
+    }
+}
+
+/// Returns the address of an endpoint consisting of the given host and
+/// port.
+///
+/// IPv6 addresses are enclosed in brackets, e.g. `[fd00::1]:8080`.
+pub fn endpoint_address(host: &str, port: u16) -> String {
+    if matches!(host.parse(), Ok(IpAddr::V6(_))) {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test::kube::from_yaml;
+    use spectral::prelude::*;
+
+    #[test]
+    fn endpoint_address_should_enclose_ipv6_addresses_in_brackets() {
+        assert_that(&endpoint_address("10.0.0.1", 80)).is_equal_to(String::from("10.0.0.1:80"));
+        assert_that(&endpoint_address("fd00::1", 80)).is_equal_to(String::from("[fd00::1]:80"));
+        assert_that(&endpoint_address("zookeeper", 2181))
+            .is_equal_to(String::from("zookeeper:2181"));
+    }
+
+    #[test]
+    fn ip_families_should_be_detected_from_the_pod_cidrs() {
+        let node: Node = from_yaml(
+            "
+            metadata:
+              name: node
+            spec:
+              podCIDRs:
+                - 10.42.0.0/24
+                - fd00:10:42::/64
+            status:
+              addresses:
+                - type: InternalIP
+                  address: 172.18.0.2
+            ",
+        );
+
+        let ip_families = IpFamilies::from_nodes(&[node]);
+
+        assert_that(&ip_families.is_dual_stack()).is_true();
+    }
+
+    #[test]
+    fn ip_families_should_be_detected_from_the_node_addresses_without_pod_cidrs() {
+        let node: Node = from_yaml(
+            "
+            metadata:
+              name: node
+            status:
+              addresses:
+                - type: Hostname
+                  address: node
+                - type: InternalIP
+                  address: fd00::2
+            ",
+        );
+
+        let ip_families = IpFamilies::from_nodes(&[node]);
+
+        assert_that(&ip_families).is_equal_to(IpFamilies {
+            ipv4: false,
+            ipv6: true,
+        });
+    }
+}
@@ -2,25 +2,129 @@
 //!
 //! These clients simplify testing.
 
+use super::api_discovery::DiscoveredGroup;
+use super::connectivity::{is_connectivity_error, ClusterUnreachable, ReconnectPolicy};
+#[cfg(feature = "copy")]
+use super::copy::{pack, split_remote_path, unpack};
+use super::debug::install_pause_on_failure_hook;
+use super::metrics::{NodeMetrics, PodMetrics, METRICS_API_PATH};
+#[cfg(feature = "port-forward")]
+use super::port_forward::PortForward;
+use super::redaction::{redact_text, to_redacted_yaml};
+use super::rollout::Rollout;
+use super::sidecar::SIDECAR_INJECTION_DISABLED;
+use super::snapshot::{ClusterSnapshot, ResourceId};
+use super::table::{Table, TABLE_MEDIA_TYPE};
 use anyhow::{anyhow, Result};
+use chrono::{DateTime, SubsecRound, Utc};
 use futures::{StreamExt, TryStreamExt};
-use k8s_openapi::api::core::v1::{Node, NodeCondition, Pod, PodCondition, Taint};
+use http::{HeaderMap, HeaderValue};
+use hyper::client::connect::Connect;
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, StatefulSet};
+use k8s_openapi::api::authentication::v1::{TokenRequest, TokenRequestSpec};
+use k8s_openapi::api::authorization::v1::{
+    ResourceAttributes, SelfSubjectAccessReview, SelfSubjectAccessReviewSpec, SubjectAccessReview,
+    SubjectAccessReviewSpec, SubjectAccessReviewStatus,
+};
+use k8s_openapi::api::coordination::v1::{Lease, LeaseSpec};
+use k8s_openapi::api::core::v1::{
+    ConfigMap, Event, Node, NodeCondition, Pod, PodCondition, Secret, ServiceAccount, Taint,
+};
 use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::{
     CustomResourceDefinition, CustomResourceDefinitionCondition,
 };
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{APIResourceList, MicroTime, Status, Time};
+use k8s_openapi::kube_aggregator::pkg::apis::apiregistration::v1::{
+    APIService, APIServiceCondition,
+};
+use k8s_openapi::ByteString;
 use kube::api::{
-    Api, DeleteParams, ListParams, ObjectList, Patch, PatchParams, PostParams, WatchEvent,
+    Api, AttachParams, DeleteParams, EvictParams, ListParams, ObjectList, Patch, PatchParams,
+    PostParams, WatchEvent,
 };
-use kube::{Client, Resource, ResourceExt};
+use kube::client::ConfigExt;
+use kube::config::{KubeConfigOptions, Kubeconfig};
+use kube::core::{ApiResource, DynamicObject, GroupVersionKind, ObjectMeta, Request, TypeMeta};
+use kube::discovery::{self, Scope};
+use kube::{Client, Config, Resource, ResourceExt};
+use regex::Regex;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
-use serde_json::Value;
-use std::{fmt::Debug, time::Duration};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::env;
+use std::fmt::Debug;
+use std::fs::{self, File};
+use std::future::Future;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::runtime::Runtime;
+use tokio::task::JoinHandle;
+use tower::ServiceBuilder;
 use uuid::Uuid;
 
+pub use http::Method;
 pub use kube::api::LogParams;
 
+/// Name of the environment variable which contains the path of the
+/// kubeconfig file which is used instead of the default one
+pub const KUBECONFIG_ENV_VAR: &str = "KUBE_TEST_KUBECONFIG";
+
+/// Name of the environment variable which contains the name of the
+/// kubeconfig context which is used instead of the current one
+pub const CONTEXT_ENV_VAR: &str = "KUBE_TEST_CONTEXT";
+
+/// Defines methods of [`TestKubeClient`] which block on the method of
+/// [`KubeClient`] with the same name and panic with the given message
+/// if it fails.
+///
+/// The methods are written like regular method signatures except that
+/// the where clause is enclosed in braces and the panic message follows
+/// after `=>`:
+///
+/// ```text
+/// blocking! {
+///     /// Applies a resource with the given YAML specification.
+///     pub fn apply<K>(&self, spec: &str) -> K
+///     where {
+///         K: Clone + Debug + DeserializeOwned + Resource + Serialize,
+///         <K as Resource>::DynamicType: Default,
+///     }
+///     => "Resource could not be applied";
+/// }
+/// ```
+///
+/// The generic parameters are passed on explicitly, so they must be
+/// declared in the same order as in the asynchronous method.
+macro_rules! blocking {
+    ($(
+        $(#[$attr:meta])*
+        pub fn $name:ident $(<$($generic:ident),+>)? (
+            &self $(, $arg:ident: $arg_ty:ty)* $(,)?
+        ) $(-> $ret:ty)?
+        $(where { $($bound:tt)* })?
+        => $message:literal;
+    )*) => {
+        $(
+            $(#[$attr])*
+            pub fn $name $(<$($generic),+>)? (&self $(, $arg: $arg_ty)*) $(-> $ret)?
+            $(where $($bound)*)?
+            {
+                self.runtime.block_on(async {
+                    self.kube_client
+                        .$name $(::<$($generic),+>)? ($($arg),*)
+                        .await
+                        .expect($message)
+                })
+            }
+        )*
+    };
+}
+
 /// A client for interacting with the Kubernetes API
 ///
 /// [`TestKubeClient`] is a synchronous version of [`KubeClient`] which
@@ -33,10 +137,54 @@ pub struct TestKubeClient {
 
 impl TestKubeClient {
     /// Creates a [`TestKubeClient`].
+    ///
+    /// The cluster is selected like [`KubeClient::new`] does. Failed test
+    /// cases are paused before cleanup if `KUBE_TEST_PAUSE_ON_FAILURE=1`
+    /// is set, see [`super::debug`].
     pub fn new() -> TestKubeClient {
+        TestKubeClient::connect(KubeClient::new)
+    }
+
+    /// Creates a [`TestKubeClient`] for the given context of the given
+    /// kubeconfig file.
+    ///
+    /// See [`KubeClient::from_kubeconfig`].
+    pub fn from_kubeconfig(path: Option<&Path>, context: Option<&str>) -> TestKubeClient {
+        TestKubeClient::connect(|| KubeClient::from_kubeconfig(path, context))
+    }
+
+    /// Creates a [`TestKubeClient`] with the given configuration.
+    pub fn from_config(config: Config) -> TestKubeClient {
+        TestKubeClient::connect(|| KubeClient::from_config(config))
+    }
+
+    /// Creates a [`TestKubeClient`] which impersonates the given user and
+    /// groups.
+    ///
+    /// See [`KubeClient::impersonating`].
+    pub fn impersonating(user: &str, groups: &[&str]) -> TestKubeClient {
+        TestKubeClient::connect(|| KubeClient::impersonating(user, groups))
+    }
+
+    /// Creates the given ServiceAccount in the namespace of this client
+    /// if it does not exist yet and returns a client with the same
+    /// settings which is authenticated as this ServiceAccount.
+    ///
+    /// See [`KubeClient::as_service_account`].
+    pub fn as_service_account(&self, name: &str, expiration: Duration) -> TestKubeClient {
+        let token = self.create_service_account_token(name, expiration);
+        TestKubeClient::connect(|| self.kube_client.with_bearer_token(&token))
+    }
+
+    fn connect<F, Fut>(create_kube_client: F) -> TestKubeClient
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<KubeClient>>,
+    {
+        install_pause_on_failure_hook();
         let runtime = Runtime::new().expect("Tokio runtime could not be created");
         let kube_client = runtime.block_on(async {
-            KubeClient::new()
+            create_kube_client()
                 .await
                 .expect("Kubernetes client could not be created")
         });
@@ -46,35 +194,57 @@ impl TestKubeClient {
         }
     }
 
+    /// Returns this client operating in the given namespace instead of
+    /// `default`.
+    ///
+    /// The namespace must already exist.
+    pub fn with_namespace(mut self, namespace: &str) -> TestKubeClient {
+        self.kube_client = self.kube_client.in_namespace(namespace);
+        self
+    }
+
+    /// Returns the namespace in which namespaced resources are managed.
+    pub fn namespace(&self) -> &str {
+        self.kube_client.namespace()
+    }
+
     pub fn timeouts(&mut self) -> &mut Timeouts {
         &mut self.kube_client.timeouts
     }
 
-    /// Gets a list of resources restricted by the label selector.
-    ///
-    /// The label selector supports `=`, `==`, `!=`, and can be comma
-    /// separated: `key1=value1,key2=value2`.
-    pub fn list_labeled<K>(&self, label_selector: &str) -> ObjectList<K>
+    /// Returns the underlying asynchronous client.
+    pub(crate) fn kube_client(&self) -> &KubeClient {
+        &self.kube_client
+    }
+
+    /// Runs the given future on the runtime of this client and blocks
+    /// until it is completed.
+    pub(crate) fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    /// Spawns the given future on the runtime of this client.
+    pub(crate) fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
     where
-        K: Clone + Debug + DeserializeOwned + Resource,
-        <K as Resource>::DynamicType: Default,
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
     {
-        self.runtime.block_on(async {
-            self.kube_client
-                .list_labeled(label_selector)
-                .await
-                .expect("List of Stackable nodes could not be retrieved")
-        })
+        self.runtime.spawn(future)
     }
 
-    /// Applies the given custom resource definition and blocks until it is accepted.
-    pub fn apply_crd(&self, crd: &CustomResourceDefinition) {
-        self.runtime.block_on(async {
-            self.kube_client
-                .apply_crd(crd)
-                .await
-                .expect("Custom resource definition could not be applied")
-        })
+    pub fn list_defaults(&mut self) -> &mut ListDefaults {
+        &mut self.kube_client.list_defaults
+    }
+
+    pub fn reconnect(&mut self) -> &mut ReconnectPolicy {
+        &mut self.kube_client.reconnect
+    }
+
+    /// Returns the conditions which must be true before
+    /// [`TestKubeClient::apply_crd`] returns, by default `NamesAccepted`
+    /// and `Established`.
+    pub fn crd_conditions(&mut self) -> &mut Vec<String> {
+        &mut self.kube_client.crd_conditions
     }
 
     /// Searches for a resource.
@@ -97,111 +267,817 @@ impl TestKubeClient {
             .block_on(async { self.kube_client.find_namespaced::<K>(name).await })
     }
 
-    /// Applies a resource with the given YAML specification.
-    pub fn apply<K>(&self, spec: &str) -> K
+    /// Waits until an event which involves the given resource and has
+    /// the given reason occurs and returns it.
+    pub fn wait_for_event<K>(&self, resource: &K, reason: &str) -> Event
     where
-        K: Clone + Debug + DeserializeOwned + Resource + Serialize,
+        K: Resource,
         <K as Resource>::DynamicType: Default,
     {
-        self.runtime.block_on(async {
-            self.kube_client
-                .apply::<K>(spec)
-                .await
-                .expect("Resource could not be applied")
-        })
+        self.wait_for_event_matching(resource, |event| event.reason.as_deref() == Some(reason))
     }
 
-    /// Creates a resource with the given YAML specification.
-    pub fn create<K>(&self, spec: &str) -> K
+    /// Executes the given operation and panics if Warning events, e.g.
+    /// `FailedScheduling`, `BackOff`, or `Unhealthy`, were emitted for
+    /// resources matching the label selector in the meantime.
+    ///
+    /// The matching resources are determined with a [`ClusterSnapshot`]
+    /// before and after the operation. Pods which match the label
+    /// selector are additionally watched during the operation, so pods
+    /// which only existed in the meantime are considered as well. The
+    /// owners of the pods and workloads, e.g. the custom resource, are
+    /// included too.
+    ///
+    /// ```rust,no_run
+    /// use integration_test_commons::test::prelude::*;
+    ///
+    /// let client = TestKubeClient::new();
+    /// client.with_no_warning_events("app.kubernetes.io/instance=simple", || {
+    ///     // scale the cluster
+    /// });
+    /// ```
+    pub fn with_no_warning_events<F, R>(&self, label_selector: &str, operation: F) -> R
     where
-        K: Clone + Debug + DeserializeOwned + Resource + Serialize,
-        <K as Resource>::DynamicType: Default,
+        F: FnOnce() -> R,
     {
-        self.runtime.block_on(async {
-            self.kube_client
-                .create(spec)
-                .await
-                .expect("Resource could not be created")
-        })
-    }
+        let warnings_before = self.list_warning_events();
+        let snapshot_before = ClusterSnapshot::take(self, label_selector);
+        let observed_pods = Arc::new(Mutex::new(BTreeSet::new()));
+        let observation = self.spawn(observe_pods(
+            Api::all(self.kube_client.client()),
+            ListParams::default().labels(label_selector),
+            observed_pods.clone(),
+        ));
 
-    /// Deletes the given resource.
-    pub fn delete<K>(&self, resource: K)
-    where
-        K: Clone + Debug + DeserializeOwned + Resource,
-        <K as Resource>::DynamicType: Default,
-    {
-        self.runtime.block_on(async {
-            self.kube_client
-                .delete(resource)
-                .await
-                .expect("Resource could not be deleted")
-        })
-    }
+        let result = operation();
 
-    /// Returns the value of an annotation for the given resource.
-    pub fn get_annotation<K>(&self, resource: &K, key: &str) -> String
-    where
-        K: Clone + Debug + DeserializeOwned + Resource,
-        <K as Resource>::DynamicType: Default,
-    {
-        self.runtime.block_on(async {
-            self.kube_client
-                .get_annotation(resource, key)
-                .await
-                .expect("Annotation could not be retrieved")
-        })
-    }
+        observation.abort();
+        let snapshot_after = ClusterSnapshot::take(self, label_selector);
+        let mut resources = observed_pods.lock().unwrap().clone();
+        resources.extend(
+            snapshot_before
+                .resources()
+                .into_iter()
+                .chain(snapshot_after.resources())
+                .cloned(),
+        );
+        for statefulset in self.list_labeled::<StatefulSet>(label_selector).items {
+            resources.extend(owner_ids(&statefulset));
+        }
+        for deployment in self.list_labeled::<Deployment>(label_selector).items {
+            resources.extend(owner_ids(&deployment));
+        }
+        for daemonset in self.list_labeled::<DaemonSet>(label_selector).items {
+            resources.extend(owner_ids(&daemonset));
+        }
+        let warnings = new_warning_events(&warnings_before, self.list_warning_events(), &resources);
 
-    /// Verifies that the given pod condition becomes true within the
-    /// specified timeout.
-    pub fn verify_pod_condition(&self, pod: &Pod, condition_type: &str) -> Pod {
-        self.runtime.block_on(async {
-            self.kube_client
-                .verify_pod_condition(pod, condition_type)
-                .await
-                .expect("Pod condition could not be verified")
-        })
-    }
+        if !warnings.is_empty() {
+            panic!(
+                "Warning events were emitted for [{}]:\n{}",
+                label_selector,
+                warnings.join("\n")
+            );
+        }
 
-    /// Verifies that the status of a resource fulfills the given
-    /// predicate within the specified timeout.
-    pub fn verify_status<K, P>(&self, resource: &K, predicate: P) -> K
-    where
-        P: Fn(&K) -> bool,
-        K: Clone + Debug + DeserializeOwned + Resource,
-        <K as Resource>::DynamicType: Default,
-    {
-        self.runtime.block_on(async {
-            self.kube_client
-                .verify_status(resource, predicate)
-                .await
-                .expect("Resource did not reach the expected status")
-        })
+        result
     }
 
-    /// Returns the given resource with an updated status.
-    pub fn get_status<K>(&self, resource: &K) -> K
-    where
-        K: DeserializeOwned + Resource,
-        <K as Resource>::DynamicType: Default,
-    {
-        self.runtime.block_on(async {
-            self.kube_client
-                .get_status(resource)
-                .await
-                .expect("Status could not be retrieved")
-        })
-    }
+    blocking! {
+        /// Gets a list of resources restricted by the label selector.
+        ///
+        /// The label selector supports `=`, `==`, `!=`, and can be comma
+        /// separated: `key1=value1,key2=value2`.
+        ///
+        /// Resources are listed across all namespaces.
+        pub fn list_labeled<K>(&self, label_selector: &str) -> ObjectList<K>
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "List of Stackable nodes could not be retrieved";
 
-    /// Returns the logs for the given pod.
-    pub fn get_logs(&self, pod: &Pod, params: &LogParams) -> Vec<String> {
-        self.runtime.block_on(async {
-            self.kube_client
-                .get_logs(pod, params)
-                .await
-                .expect("Logs could not be retrieved")
-        })
+        /// Gets a list of resources in the given namespace restricted by
+        /// the label selector.
+        pub fn list_labeled_in<K>(&self, namespace: &str, label_selector: &str) -> ObjectList<K>
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "List of resources could not be retrieved";
+
+        /// Gets a list of resources across all namespaces restricted by
+        /// the given label selector without the default label selector.
+        pub fn list_without_label_defaults<K>(&self, label_selector: &str) -> ObjectList<K>
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "List of resources could not be retrieved";
+
+        /// Gets a list of all resources of the given kind across all
+        /// namespaces without the default label selector.
+        pub fn list_all_namespaces<K>(&self) -> ObjectList<K>
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "List of resources could not be retrieved";
+
+        /// Gets a list of resources restricted by the field selector,
+        /// e.g. `spec.nodeName=node-1` or `status.phase=Pending`.
+        ///
+        /// Resources are listed across all namespaces.
+        pub fn list_with_fields<K>(&self, field_selector: &str) -> ObjectList<K>
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "List of resources could not be retrieved";
+
+        /// Gets the resources restricted by the label selector as table like
+        /// it is printed by `kubectl get`.
+        pub fn get_table<K>(&self, label_selector: &str) -> Table
+        where {
+            K: Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Table could not be retrieved";
+
+        /// Applies the given custom resource definition and blocks until it is established.
+        pub fn apply_crd(&self, crd: &CustomResourceDefinition)
+        => "Custom resource definition could not be applied";
+
+        /// Deletes the custom resource definition with the given name and
+        /// blocks until its custom resources and the definition itself are
+        /// removed.
+        pub fn delete_crd(&self, name: &str)
+        => "Custom resource definition could not be deleted";
+
+        /// Applies a resource with the given YAML specification.
+        pub fn apply<K>(&self, spec: &str) -> K
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource + Serialize,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Resource could not be applied";
+
+        /// Applies a resource with the given YAML specification and the
+        /// given dynamic type.
+        pub fn apply_with<K>(&self, spec: &str, dynamic_type: &K::DynamicType) -> K
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource + Serialize,
+            <K as Resource>::DynamicType: Clone,
+        }
+        => "Resource could not be applied";
+
+        /// Applies a resource of the given group, version, and kind which
+        /// has no Rust type, e.g. a custom resource of another operator.
+        ///
+        /// ```rust,no_run
+        /// use integration_test_commons::test::prelude::*;
+        ///
+        /// let client = TestKubeClient::new();
+        /// let gvk =
+        ///     GroupVersionKind::gvk("command.zookeeper.stackable.tech", "v1alpha1", "Restart");
+        /// client.apply_dynamic(&gvk, indoc!("
+        ///     apiVersion: command.zookeeper.stackable.tech/v1alpha1
+        ///     kind: Restart
+        ///     metadata:
+        ///       name: restart-simple
+        ///     spec:
+        ///       name: simple
+        /// "));
+        /// ```
+        pub fn apply_dynamic(&self, gvk: &GroupVersionKind, spec: &str) -> DynamicObject
+        => "Resource could not be applied";
+
+        /// Searches for a resource of the given group, version, and kind
+        /// which has no Rust type.
+        pub fn find_dynamic(&self, gvk: &GroupVersionKind, name: &str) -> Option<DynamicObject>
+        => "Resource could not be retrieved";
+
+        /// Discovers the resource kinds and versions which are served in
+        /// the given API group.
+        pub fn discover(&self, group: &str) -> DiscoveredGroup
+        => "API group could not be discovered";
+
+        /// Lists the resources of the given group, version, and kind which
+        /// have no Rust type and match the label selector.
+        pub fn list_dynamic(&self, gvk: &GroupVersionKind, label_selector: &str) -> Vec<DynamicObject>
+        => "Resources could not be listed";
+
+        /// Validates a resource with the given YAML specification on the
+        /// server without persisting it.
+        ///
+        /// Returns the resource with the defaults applied by the server or
+        /// the message of the rejection:
+        ///
+        /// ```rust,no_run
+        /// use integration_test_commons::test::prelude::*;
+        ///
+        /// let client = TestKubeClient::new();
+        /// let result = client.validate::<ConfigMap>(indoc!("
+        ///     apiVersion: v1
+        ///     kind: ConfigMap
+        ///     metadata:
+        ///       name: INVALID
+        /// "));
+        /// assert_that(&result).is_err();
+        /// ```
+        pub fn validate<K>(&self, spec: &str) -> Result<K, String>
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource + Serialize,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Resource could not be validated";
+
+        /// Creates a resource with the given YAML specification.
+        pub fn create<K>(&self, spec: &str) -> K
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource + Serialize,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Resource could not be created";
+
+        /// Deletes the given resource.
+        ///
+        /// Resources which do not exist anymore are ignored.
+        pub fn delete<K>(&self, resource: K)
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Resource could not be deleted";
+
+        /// Deletes the given resource with the given dynamic type.
+        ///
+        /// Resources which do not exist anymore are ignored.
+        pub fn delete_with<K>(&self, resource: K, dynamic_type: &K::DynamicType)
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource,
+            <K as Resource>::DynamicType: Clone,
+        }
+        => "Resource could not be deleted";
+
+        /// Deletes all resources matching the label selector and blocks
+        /// until they are gone.
+        pub fn delete_collection<K>(&self, label_selector: &str)
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Resources could not be deleted";
+
+        /// Waits until the resource with the given name is gone.
+        ///
+        /// In contrast to [`TestKubeClient::delete`], the deletion need not
+        /// be issued by the test case, e.g. if the operator deletes the
+        /// resource.
+        pub fn wait_for_deletion<K>(&self, name: &str)
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Resource was not deleted";
+
+        /// Patches the given resource with the given JSON patch (RFC 6902).
+        ///
+        /// ```rust,no_run
+        /// use integration_test_commons::test::prelude::*;
+        ///
+        /// let client = TestKubeClient::new();
+        /// # let config_map = ConfigMap::default();
+        /// client.patch_json(
+        ///     &config_map,
+        ///     json!([{ "op": "replace", "path": "/data/key", "value": "value" }]),
+        /// );
+        /// ```
+        pub fn patch_json<K>(&self, resource: &K, patch: Value) -> K
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Resource could not be patched";
+
+        /// Patches the given resource with the given JSON merge patch
+        /// (RFC 7386).
+        pub fn patch_merge<K>(&self, resource: &K, patch: Value) -> K
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Resource could not be patched";
+
+        /// Patches the given resource with the given strategic merge patch.
+        pub fn patch_strategic<K>(&self, resource: &K, patch: Value) -> K
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Resource could not be patched";
+
+        /// Adds the given labels to the resource or overwrites them and
+        /// waits until the change is visible.
+        pub fn add_labels<K>(&self, resource: &K, labels: &BTreeMap<String, String>) -> K
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Labels could not be added";
+
+        /// Removes the labels with the given keys from the resource and
+        /// waits until the change is visible.
+        pub fn remove_labels<K>(&self, resource: &K, keys: &[&str]) -> K
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Labels could not be removed";
+
+        /// Adds the given annotations to the resource or overwrites them
+        /// and waits until the change is visible.
+        pub fn add_annotations<K>(
+            &self,
+            resource: &K,
+            annotations: &BTreeMap<String, String>,
+        ) -> K
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Annotations could not be added";
+
+        /// Removes the annotations with the given keys from the resource
+        /// and waits until the change is visible.
+        pub fn remove_annotations<K>(&self, resource: &K, keys: &[&str]) -> K
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Annotations could not be removed";
+
+        /// Returns the value of an annotation for the given resource.
+        pub fn get_annotation<K>(&self, resource: &K, key: &str) -> String
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Annotation could not be retrieved";
+
+        /// Waits until the given key of the config map fulfills the
+        /// predicate within the specified timeout and returns its value.
+        pub fn wait_for_configmap_key<P>(&self, name: &str, key: &str, predicate: P) -> String
+        where {
+            P: Fn(&str) -> bool,
+        }
+        => "Config map key did not reach the expected value";
+
+        /// Creates a secret with the given data in plain text in the
+        /// namespace of this client and returns it.
+        pub fn create_secret(&self, name: &str, data: &BTreeMap<String, String>) -> Secret
+        => "Secret could not be created";
+
+        /// Returns the data of the given secret with the values decoded
+        /// from base64 and UTF-8.
+        pub fn get_secret_data(&self, name: &str) -> BTreeMap<String, String>
+        => "Secret could not be retrieved";
+
+        /// Returns the data of the given config map.
+        pub fn get_configmap_data(&self, name: &str) -> BTreeMap<String, String>
+        => "Config map could not be retrieved";
+
+        /// Returns the properties in the given key of the config map, e.g.
+        /// the rendered `zoo.cfg` or `server.properties`.
+        pub fn get_configmap_properties(&self, name: &str, key: &str) -> BTreeMap<String, String>
+        => "Properties could not be retrieved from the config map";
+
+        /// Waits until the given key of the secret fulfills the predicate
+        /// within the specified timeout and returns its value.
+        pub fn wait_for_secret_key<P>(&self, name: &str, key: &str, predicate: P) -> String
+        where {
+            P: Fn(&str) -> bool,
+        }
+        => "Secret key did not reach the expected value";
+
+        /// Requests a token for the given service account.
+        ///
+        /// The token is bound to the given audiences and expires after the
+        /// given duration.
+        pub fn request_service_account_token(
+            &self,
+            service_account_name: &str,
+            audiences: &[&str],
+            expiration: Duration,
+        ) -> String
+        => "Service account token could not be requested";
+
+        /// Creates the given ServiceAccount if it does not exist yet and
+        /// requests a token for it which is valid for the API server.
+        pub fn create_service_account_token(&self, name: &str, expiration: Duration) -> String
+        => "Service account token could not be created";
+
+        /// Returns true if the user of this client is allowed to perform
+        /// the given verb on the given resource.
+        ///
+        /// See [`KubeClient::can_i`].
+        pub fn can_i(&self, verb: &str, resource: &str, namespace: Option<&str>) -> bool
+        => "Access could not be reviewed";
+
+        /// Returns true if the given user with the given groups is allowed
+        /// to perform the given verb on the given resource.
+        ///
+        /// See [`KubeClient::can_i_as`].
+        pub fn can_i_as(
+            &self,
+            user: &str,
+            groups: &[&str],
+            verb: &str,
+            resource: &str,
+            namespace: Option<&str>,
+        ) -> bool
+        => "Access could not be reviewed";
+
+        /// Verifies that no resource matching the label selector is created
+        /// within the given observation window.
+        pub fn verify_not_created<K>(&self, label_selector: &str, observation_window: Duration)
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Resource was created unexpectedly";
+
+        /// Returns the events which involve the given resource.
+        pub fn list_events<K>(&self, resource: &K) -> Vec<Event>
+        where {
+            K: Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Events could not be retrieved";
+
+        /// Returns the Warning events in all namespaces.
+        pub fn list_warning_events(&self) -> Vec<Event>
+        => "Warning events could not be retrieved";
+
+        /// Returns the reason why the given pod cannot be scheduled or
+        /// `None` if it is scheduled or the reason is not known yet.
+        pub fn get_scheduling_failure(&self, pod: &Pod) -> Option<String>
+        => "Scheduling failure could not be retrieved";
+
+        /// Waits until an event which involves the given resource and
+        /// fulfills the given predicate occurs and returns it.
+        pub fn wait_for_event_matching<K, P>(&self, resource: &K, predicate: P) -> Event
+        where {
+            K: Resource,
+            <K as Resource>::DynamicType: Default,
+            P: Fn(&Event) -> bool,
+        }
+        => "Event did not occur";
+
+        /// Waits until the operator published an event for the given
+        /// custom resource with the given reason and a message which
+        /// contains the given substring, and returns it.
+        ///
+        /// ```rust,no_run
+        /// use integration_test_commons::test::prelude::*;
+        /// use kube::Resource;
+        ///
+        /// fn verify_upgrade<K>(client: &TestKubeClient, cluster: &K)
+        /// where
+        ///     K: Resource<DynamicType = ()>,
+        /// {
+        ///     client.wait_for_operator_event(cluster, "Upgrading", "from 3.5.8 to 3.6.3");
+        /// }
+        /// ```
+        pub fn wait_for_operator_event<K>(
+            &self,
+            resource: &K,
+            reason: &str,
+            message_substring: &str,
+        ) -> Event
+        where {
+            K: Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Operator event did not occur";
+
+        /// Verifies that the given labels of the parent resource were
+        /// copied to all child resources matching the label selector.
+        pub fn verify_labels_propagated<P, C>(
+            &self,
+            parent: &P,
+            child_selector: &str,
+            keys: &[&str],
+        )
+        where {
+            P: Resource,
+            C: Clone + Debug + DeserializeOwned + Resource,
+            <C as Resource>::DynamicType: Default,
+        }
+        => "Labels were not propagated";
+
+        /// Verifies that the given annotations of the parent resource were
+        /// copied to all child resources matching the label selector.
+        pub fn verify_annotations_propagated<P, C>(
+            &self,
+            parent: &P,
+            child_selector: &str,
+            keys: &[&str],
+        )
+        where {
+            P: Resource,
+            C: Clone + Debug + DeserializeOwned + Resource,
+            <C as Resource>::DynamicType: Default,
+        }
+        => "Annotations were not propagated";
+
+        /// Sets the status of the given pod condition and returns the
+        /// updated pod.
+        pub fn set_pod_condition(&self, pod: &Pod, condition_type: &str, status: bool) -> Pod
+        => "Pod condition could not be set";
+
+        /// Marks the given node as unschedulable and returns the updated
+        /// node.
+        pub fn cordon(&self, node: &Node) -> Node
+        => "Node could not be cordoned";
+
+        /// Marks the given node as schedulable and returns the updated node.
+        pub fn uncordon(&self, node: &Node) -> Node
+        => "Node could not be uncordoned";
+
+        /// Cordons the given node, evicts all pods which are not managed
+        /// by a DaemonSet, and waits until the evicted pods of controllers
+        /// are rescheduled and ready on other nodes.
+        pub fn drain(&self, node: &Node) -> Node
+        => "Node could not be drained";
+
+        /// Verifies that the given pod condition becomes true within the
+        /// specified timeout.
+        pub fn verify_pod_condition(&self, pod: &Pod, condition_type: &str) -> Pod
+        => "Pod condition could not be verified";
+
+        /// Verifies that the status of a resource fulfills the given
+        /// predicate within the specified timeout.
+        pub fn verify_status<K, P>(&self, resource: &K, predicate: P) -> K
+        where {
+            P: Fn(&K) -> bool,
+            K: Clone + Debug + DeserializeOwned + Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Resource did not reach the expected status";
+
+        /// Waits until the condition of the given type is true and its
+        /// `lastTransitionTime` is not before `since`.
+        ///
+        /// In contrast to [`TestKubeClient::verify_status`], a condition
+        /// which was already true before the action under test is not
+        /// accepted. `since` should be a server timestamp, e.g. the
+        /// creation timestamp of a resource created by the action, because
+        /// the clocks of the test runner and the cluster may differ.
+        pub fn wait_for_condition_transition<K>(&self, resource: &K, type_: &str, since: &Time) -> K
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource + Serialize,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Condition did not transition";
+
+        /// Waits until the status field at the given JSON pointer fulfills
+        /// the given predicate.
+        ///
+        /// The JSON pointer is relative to the status of the resource. This
+        /// allows assertions on custom resources without typed status
+        /// structs:
+        ///
+        /// ```no_run
+        /// use integration_test_commons::test::prelude::*;
+        ///
+        /// let client = TestKubeClient::new();
+        /// # let deployment = k8s_openapi::api::apps::v1::Deployment::default();
+        /// client.wait_for_status_field(&deployment, "/readyReplicas", |replicas| {
+        ///     replicas.as_u64() == Some(3)
+        /// });
+        /// ```
+        pub fn wait_for_status_field<K, P>(
+            &self,
+            resource: &K,
+            json_pointer: &str,
+            predicate: P,
+        ) -> K
+        where {
+            P: Fn(&Value) -> bool,
+            K: Clone + Debug + DeserializeOwned + Resource + Serialize,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Status field did not reach the expected value";
+
+        /// Waits until the given workload, i.e. a Deployment, StatefulSet,
+        /// or DaemonSet, is fully rolled out like `kubectl rollout status`
+        /// does and returns it.
+        pub fn wait_for_rollout<K>(&self, workload: &K) -> K
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource + Rollout,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Workload was not rolled out";
+
+        /// Verifies that the status of a resource does not fulfill the given
+        /// predicate within the given observation window.
+        pub fn verify_status_not_reached<K, P>(
+            &self,
+            resource: &K,
+            predicate: P,
+            observation_window: Duration,
+        )
+        where {
+            P: Fn(&K) -> bool,
+            K: Clone + Debug + DeserializeOwned + Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Resource reached the unexpected status";
+
+        /// Waits until the resource version of the given resource has not
+        /// changed for the given quiet period and returns the stable
+        /// resource.
+        pub fn wait_until_stable<K>(&self, resource: &K, quiet_period: Duration) -> K
+        where {
+            K: Clone + Debug + DeserializeOwned + Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Resource did not become stable";
+
+        /// Returns the given resource with an updated status.
+        pub fn get_status<K>(&self, resource: &K) -> K
+        where {
+            K: DeserializeOwned + Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Status could not be retrieved";
+
+        /// Verifies that the product binary in the image of the given pod
+        /// reports a version matching the given regular expression and
+        /// returns the output of the command.
+        pub fn verify_product_version_via_cli(
+            &self,
+            pod: &Pod,
+            command: &[&str],
+            expected_version_regex: &str,
+        ) -> String
+        => "Product version could not be verified";
+
+        /// Executes the given command in the default container of the given
+        /// pod and returns its output.
+        pub fn exec(&self, pod: &Pod, command: &[&str]) -> ExecOutput
+        => "Command could not be executed";
+
+        /// Executes the given command in the given container of the pod and
+        /// returns its output.
+        pub fn exec_in_container(&self, pod: &Pod, container: &str, command: &[&str]) -> ExecOutput
+        => "Command could not be executed";
+
+        /// Copies the given local file or directory to the given path in the
+        /// default container of the pod.
+        #[cfg(feature = "copy")]
+        pub fn copy_to_pod(&self, pod: &Pod, local_path: &Path, remote_path: &str)
+        => "Files could not be copied to the pod";
+
+        /// Copies the given file or directory from the default container of
+        /// the pod to the given local path.
+        #[cfg(feature = "copy")]
+        pub fn copy_from_pod(&self, pod: &Pod, remote_path: &str, local_path: &Path)
+        => "Files could not be copied from the pod";
+
+        /// Forwards the given port of the pod to a local address.
+        ///
+        /// The forwarding is stopped when the returned [`PortForward`] is
+        /// dropped.
+        #[cfg(feature = "port-forward")]
+        pub fn port_forward(&self, pod: &Pod, port: u16) -> PortForward
+        => "Port could not be forwarded";
+
+        /// Acquires the lease with the given name for the given holder and
+        /// blocks until the lease is acquired or the timeout is reached.
+        pub fn acquire_lease(
+            &self,
+            name: &str,
+            holder_identity: &str,
+            lease_duration: Duration,
+        ) -> Lease
+        => "Lease could not be acquired";
+
+        /// Renews the lease with the given name if it is still held by
+        /// the given holder and returns false otherwise.
+        pub fn renew_lease(
+            &self,
+            name: &str,
+            holder_identity: &str,
+            lease_duration: Duration,
+        ) -> bool
+        => "Lease could not be renewed";
+
+        /// Releases the lease with the given name if it is held by the
+        /// given holder.
+        pub fn release_lease(&self, name: &str, holder_identity: &str)
+        => "Lease could not be released";
+
+        /// Sends a request to the given URL path of the API server and
+        /// returns the response.
+        pub fn raw_request(&self, method: Method, url_path: &str, body: Option<&Value>) -> Value
+        => "Request failed";
+
+        /// Sends a GET request to the given port and path of the pod or
+        /// service via the proxy of the API server and returns the
+        /// response body.
+        pub fn proxy_get<K>(&self, resource: &K, port: u16, path: &str) -> String
+        where {
+            K: Resource,
+            <K as Resource>::DynamicType: Default,
+        }
+        => "Proxy request failed";
+
+        /// Returns the version of the Kubernetes API server, e.g.
+        /// `v1.22.2`.
+        pub fn get_kubernetes_version(&self) -> String
+        => "Kubernetes version could not be retrieved";
+
+        /// Returns true if the given API service, e.g.
+        /// `v1beta1.metrics.k8s.io`, exists and is available.
+        ///
+        /// This can be used to skip test cases which depend on an
+        /// aggregated API.
+        pub fn is_apiservice_available(&self, name: &str) -> bool
+        => "API service could not be retrieved";
+
+        /// Verifies that the given API service becomes available within the
+        /// specified timeout.
+        pub fn verify_apiservice_available(&self, name: &str)
+        => "API service is not available";
+
+        /// Returns the resource usage of the given pod reported by the
+        /// metrics-server.
+        pub fn get_pod_metrics(&self, pod_name: &str) -> PodMetrics
+        => "Pod metrics could not be retrieved";
+
+        /// Returns the resource usage of the pods matching the label
+        /// selector reported by the metrics-server.
+        pub fn list_pod_metrics(&self, label_selector: &str) -> Vec<PodMetrics>
+        => "Pod metrics could not be retrieved";
+
+        /// Returns the resource usage of the given node reported by the
+        /// metrics-server.
+        pub fn get_node_metrics(&self, node_name: &str) -> NodeMetrics
+        => "Node metrics could not be retrieved";
+
+        /// Returns the CPU usage of all containers of the given pod in
+        /// millicores.
+        pub fn get_pod_cpu_usage(&self, pod_name: &str) -> u64
+        => "CPU usage could not be retrieved";
+
+        /// Returns the memory usage of all containers of the given pod in
+        /// bytes.
+        pub fn get_pod_memory_usage(&self, pod_name: &str) -> u64
+        => "Memory usage could not be retrieved";
+
+        /// Returns the logs for the given pod.
+        pub fn get_logs(&self, pod: &Pod, params: &LogParams) -> Vec<String>
+        => "Logs could not be retrieved";
+
+        /// Returns the logs of the given container of the pod.
+        pub fn get_container_logs(&self, pod: &Pod, container: &str) -> Vec<String>
+        => "Container logs could not be retrieved";
+
+        /// Returns the logs of all containers of the given pod by
+        /// container name.
+        pub fn get_all_container_logs(&self, pod: &Pod) -> BTreeMap<String, Vec<String>>
+        => "Container logs could not be retrieved";
+
+        /// Returns the logs of the previous instance of the given
+        /// container, e.g. of a crash-looping container.
+        pub fn get_previous_logs(&self, pod: &Pod, container: &str) -> Vec<String>
+        => "Previous logs could not be retrieved";
+
+        /// Returns the last lines of the logs of the given container.
+        pub fn get_tail_logs(&self, pod: &Pod, container: &str, tail_lines: i64) -> Vec<String>
+        => "Container logs could not be retrieved";
+
+        /// Returns the lines which the given container logged within the
+        /// given duration before now.
+        pub fn get_recent_logs(&self, pod: &Pod, container: &str, since: Duration) -> Vec<String>
+        => "Container logs could not be retrieved";
+
+        /// Returns the logs of the given container up to the given number
+        /// of bytes.
+        pub fn get_limited_logs(&self, pod: &Pod, container: &str, limit_bytes: i64) -> Vec<String>
+        => "Container logs could not be retrieved";
+
+        /// Streams the logs for the given pod into the given file and
+        /// returns the number of written bytes.
+        pub fn write_logs(&self, pod: &Pod, params: &LogParams, path: &Path) -> u64
+        => "Logs could not be written";
+
+        /// Follows the logs of the given pod until a line matches the
+        /// given regular expression and returns this line.
+        pub fn wait_for_log_line(&self, pod: &Pod, pattern: &str) -> String
+        => "Log line did not appear";
     }
 }
 
@@ -218,103 +1094,573 @@ impl Default for TestKubeClient {
 /// defined timeouts.
 pub struct KubeClient {
     client: Client,
+    /// Configuration from which the client was created, e.g. to derive
+    /// clients with other credentials for the same cluster
+    config: Config,
     namespace: String,
     pub timeouts: Timeouts,
+    pub list_defaults: ListDefaults,
+    pub reconnect: ReconnectPolicy,
+    /// Conditions of a custom resource definition which must be true
+    /// before [`KubeClient::apply_crd`] returns
+    pub crd_conditions: Vec<String>,
+}
+
+/// Defaults which are applied to all list and watch operations
+///
+/// The defaults allow a test suite to consistently restrict and
+/// configure the requests of a [`KubeClient`].
+///
+/// The default label selector is not applied to listings of cluster
+/// infrastructure like Nodes and Events, which do not carry the labels
+/// of the test suite, see [`KubeClient::list_without_label_defaults`].
+#[derive(Clone, Debug)]
+pub struct ListDefaults {
+    /// Label selector which is prepended to the label selector of every
+    /// list operation
+    pub label_selector: Option<String>,
+    /// Timeout for list operations
+    pub timeout: Option<Duration>,
+    /// Resource version from which watches are started
+    pub resource_version: ResourceVersion,
+    /// Resource version at which lists are served, by default the most
+    /// recent one
+    pub list_resource_version: ResourceVersion,
+}
+
+impl Default for ListDefaults {
+    fn default() -> Self {
+        ListDefaults {
+            label_selector: None,
+            timeout: None,
+            resource_version: ResourceVersion::NotOlderThanAny,
+            list_resource_version: ResourceVersion::MostRecent,
+        }
+    }
+}
+
+/// Semantics of the resource version from which a watch is started or
+/// at which a list is served
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ResourceVersion {
+    /// Starts at any resource version which is available in the cache
+    /// of the API server (`resourceVersion=0`). Lists are served from
+    /// the cache with `resourceVersionMatch=NotOlderThan`.
+    #[default]
+    NotOlderThanAny,
+    /// Starts at the most recent resource version which requires a
+    /// quorum read from etcd (`resourceVersion=""`).
+    MostRecent,
+}
+
+impl ResourceVersion {
+    /// Returns the resource version as expected by the API server.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResourceVersion::NotOlderThanAny => "0",
+            ResourceVersion::MostRecent => "",
+        }
+    }
+
+    /// Returns the query parameters of a list request with this
+    /// resource version.
+    fn list_query(&self) -> Option<&'static str> {
+        match self {
+            ResourceVersion::NotOlderThanAny => {
+                Some("resourceVersion=0&resourceVersionMatch=NotOlderThan")
+            }
+            ResourceVersion::MostRecent => None,
+        }
+    }
+}
+
+impl ListDefaults {
+    /// Returns the list parameters for the given label selector with
+    /// the defaults applied.
+    pub fn list_params(&self, label_selector: &str) -> ListParams {
+        let label_selector = [self.label_selector.as_deref(), Some(label_selector)]
+            .iter()
+            .flatten()
+            .filter(|selector| !selector.is_empty())
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(",");
+
+        self.list_params_without_label_defaults(&label_selector)
+    }
+
+    /// Returns the list parameters for the given label selector with
+    /// the defaults applied except for the default label selector.
+    pub fn list_params_without_label_defaults(&self, label_selector: &str) -> ListParams {
+        let mut list_params = ListParams::default().labels(label_selector);
+        list_params.timeout = self.timeout.map(|timeout| timeout.as_secs() as u32);
+        list_params
+    }
 }
 
 /// Timeouts for operations
+#[derive(Clone, Debug)]
 pub struct Timeouts {
     pub apply_crd: Duration,
+    pub delete_crd: Duration,
     pub create: Duration,
     pub delete: Duration,
     pub get_annotation: Duration,
     pub verify_status: Duration,
+    pub wait_for_key: Duration,
+    pub acquire_lease: Duration,
+    pub wait_until_stable: Duration,
+    pub wait_for_event: Duration,
+    pub rollout: Duration,
+    pub wait_for_log_line: Duration,
+    pub drain: Duration,
 }
 
 impl Default for Timeouts {
     fn default() -> Self {
         Timeouts {
             apply_crd: Duration::from_secs(30),
+            delete_crd: Duration::from_secs(120),
             create: Duration::from_secs(10),
             delete: Duration::from_secs(10),
             get_annotation: Duration::from_secs(10),
             verify_status: Duration::from_secs(30),
+            wait_for_key: Duration::from_secs(30),
+            acquire_lease: Duration::from_secs(600),
+            wait_until_stable: Duration::from_secs(120),
+            wait_for_event: Duration::from_secs(60),
+            rollout: Duration::from_secs(300),
+            wait_for_log_line: Duration::from_secs(120),
+            drain: Duration::from_secs(300),
         }
     }
 }
 
 impl KubeClient {
     /// Creates a [`KubeClient`].
+    ///
+    /// If the environment variable `KUBE_TEST_KUBECONFIG` or
+    /// `KUBE_TEST_CONTEXT` is set then the given kubeconfig file or
+    /// context is used, see [`KubeClient::from_kubeconfig`], so that test
+    /// suites can target different clusters in a CI matrix. Otherwise the
+    /// configuration is inferred from the environment, i.e. from the
+    /// default kubeconfig file or the service account of the pod.
     pub async fn new() -> Result<KubeClient> {
-        let client = Client::try_default().await?;
-        Ok(KubeClient {
-            client,
-            namespace: String::from("default"),
-            timeouts: Default::default(),
-        })
+        KubeClient::from_config(infer_config().await?).await
+    }
+
+    /// Creates a [`KubeClient`] for the given context of the given
+    /// kubeconfig file.
+    ///
+    /// The kubeconfig file defaults to the one given by `KUBECONFIG` or
+    /// `~/.kube/config` and the context defaults to the current context
+    /// of the kubeconfig file.
+    pub async fn from_kubeconfig(path: Option<&Path>, context: Option<&str>) -> Result<KubeClient> {
+        KubeClient::from_config(load_kubeconfig(path, context).await?).await
+    }
+
+    /// Creates a [`KubeClient`] with the given configuration.
+    pub async fn from_config(config: Config) -> Result<KubeClient> {
+        let client = Client::try_from(config.clone())?;
+        Ok(KubeClient::with_client(config, client))
+    }
+
+    /// Creates a [`KubeClient`] which impersonates the given user and
+    /// groups.
+    ///
+    /// The configuration is selected like [`KubeClient::new`] does and
+    /// its user must be allowed to impersonate. This allows to test the
+    /// RBAC rules of an operator from the perspective of restricted users,
+    /// e.g. `system:serviceaccount:default:zookeeper`, without separate
+    /// kubeconfig files.
+    pub async fn impersonating(user: &str, groups: &[&str]) -> Result<KubeClient> {
+        let config = infer_config().await?;
+        let headers = impersonation_headers(user, groups)?;
+        let client = client_with_headers(&config, headers)?;
+        Ok(KubeClient::with_client(config, client))
+    }
+
+    /// Returns a client with the same settings which authenticates with
+    /// the given bearer token instead of the credentials of the
+    /// configuration.
+    async fn with_bearer_token(&self, token: &str) -> Result<KubeClient> {
+        let config = without_credentials(&self.config);
+        let mut authorization = HeaderValue::from_str(&format!("Bearer {}", token))?;
+        authorization.set_sensitive(true);
+        let mut headers = HeaderMap::new();
+        headers.insert(http::header::AUTHORIZATION, authorization);
+
+        Ok(KubeClient {
+            client: client_with_headers(&config, headers)?,
+            ..self.in_namespace(&self.namespace)
+        })
+    }
+
+    fn with_client(config: Config, client: Client) -> KubeClient {
+        KubeClient {
+            client,
+            config,
+            namespace: String::from("default"),
+            timeouts: Default::default(),
+            list_defaults: Default::default(),
+            reconnect: Default::default(),
+            crd_conditions: vec![String::from("NamesAccepted"), String::from("Established")],
+        }
+    }
+
+    /// Creates a [`KubeClient`] which operates in the given namespace.
+    ///
+    /// The namespace must already exist.
+    pub async fn new_in_namespace(namespace: &str) -> Result<KubeClient> {
+        Ok(KubeClient::new().await?.in_namespace(namespace))
+    }
+
+    /// Returns the namespace in which namespaced resources are managed.
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    /// Returns a client with the same settings which operates in the
+    /// given namespace.
+    pub(crate) fn in_namespace(&self, namespace: &str) -> KubeClient {
+        KubeClient {
+            client: self.client.clone(),
+            config: self.config.clone(),
+            namespace: namespace.to_owned(),
+            timeouts: self.timeouts.clone(),
+            list_defaults: self.list_defaults.clone(),
+            reconnect: self.reconnect.clone(),
+            crd_conditions: self.crd_conditions.clone(),
+        }
+    }
+
+    /// Returns the underlying [`Client`][kube::Client].
+    pub(crate) fn client(&self) -> Client {
+        self.client.clone()
+    }
+
+    /// Runs the given operation and retries it with a backoff according
+    /// to the reconnect policy if the API server is unreachable or
+    /// responds with a transient error.
+    ///
+    /// If the error persists after all retries then a
+    /// [`ClusterUnreachable`] error is returned. The operation must be
+    /// idempotent, e.g. only read the cluster state or apply a resource,
+    /// because it is executed again from the start.
+    pub async fn retry_on_disconnect<F, Fut, T>(&self, operation: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut retry = 0;
+        loop {
+            match operation().await {
+                Err(error) if is_connectivity_error(&error) => {
+                    if retry >= self.reconnect.max_retries {
+                        return Err(ClusterUnreachable {
+                            attempts: retry + 1,
+                            last_error: error,
+                        }
+                        .into());
+                    }
+                    tokio::time::sleep(self.reconnect.backoff(retry)).await;
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Runs the given operation like [`KubeClient::retry_on_disconnect`]
+    /// but bounds all attempts including the backoffs by the given
+    /// timeout.
+    ///
+    /// The operation receives the time which remains until the deadline,
+    /// e.g. to limit a watch to it. No retry is started if its backoff
+    /// would exceed the deadline; a [`ClusterUnreachable`] error with the
+    /// last error is returned instead.
+    async fn retry_on_disconnect_within<F, Fut, T>(
+        &self,
+        timeout: Duration,
+        operation: F,
+    ) -> Result<T>
+    where
+        F: Fn(Duration) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let deadline = Instant::now() + timeout;
+        let mut retry = 0;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match operation(remaining).await {
+                Err(error) if is_connectivity_error(&error) => {
+                    let backoff = self.reconnect.backoff(retry);
+                    if retry >= self.reconnect.max_retries || Instant::now() + backoff >= deadline {
+                        return Err(ClusterUnreachable {
+                            attempts: retry + 1,
+                            last_error: error,
+                        }
+                        .into());
+                    }
+                    tokio::time::sleep(backoff).await;
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
     }
 
     /// Gets a list of resources restricted by the label selector.
     ///
     /// The label selector supports `=`, `==`, `!=`, and can be comma separated:
     /// `key1=value1,key2=value2`.
+    ///
+    /// Resources are listed across all namespaces.
     pub async fn list_labeled<K>(&self, label_selector: &str) -> Result<ObjectList<K>>
     where
         K: Clone + Debug + DeserializeOwned + Resource,
         <K as Resource>::DynamicType: Default,
     {
-        let api: Api<K> = Api::all(self.client.clone());
-        let lp = ListParams::default().labels(label_selector);
-        Ok(api.list(&lp).await?)
+        self.retry_on_disconnect(|| async move {
+            let lp = self.list_defaults.list_params(label_selector);
+            self.list_with_defaults(None, &lp).await
+        })
+        .await
     }
 
-    /// Applies the given custom resource definition and awaits the accepted status.
-    pub async fn apply_crd(&self, crd: &CustomResourceDefinition) -> Result<()> {
-        let is_ready = |crd: &CustomResourceDefinition| {
-            get_crd_conditions(crd)
-                .iter()
-                .any(|condition| condition.type_ == "NamesAccepted" && condition.status == "True")
-        };
+    /// Gets a list of resources across all namespaces restricted by the
+    /// given label selector without the default label selector of
+    /// [`KubeClient::list_defaults`].
+    ///
+    /// Cluster infrastructure like Nodes, Events, or the Deployments of
+    /// the operators does not carry the labels of the test suite and
+    /// would not be listed with the default label selector.
+    pub async fn list_without_label_defaults<K>(
+        &self,
+        label_selector: &str,
+    ) -> Result<ObjectList<K>>
+    where
+        K: Clone + Debug + DeserializeOwned + Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        self.retry_on_disconnect(|| async move {
+            let lp = self
+                .list_defaults
+                .list_params_without_label_defaults(label_selector);
+            self.list_with_defaults(None, &lp).await
+        })
+        .await
+    }
+
+    /// Lists the resources in the given namespace or in all namespaces
+    /// with the given list parameters at the resource version of the
+    /// list defaults.
+    ///
+    /// The requests are built manually because `kube` does not support
+    /// `resourceVersionMatch` for lists.
+    async fn list_with_defaults<K>(
+        &self,
+        namespace: Option<&str>,
+        lp: &ListParams,
+    ) -> Result<ObjectList<K>>
+    where
+        K: Clone + DeserializeOwned + Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        let url_path = K::url_path(&Default::default(), namespace);
+        let mut request = Request::new(url_path).list(lp)?;
+        if let Some(query) = self.list_defaults.list_resource_version.list_query() {
+            *request.uri_mut() = format!("{}&{}", request.uri(), query).parse()?;
+        }
+        Ok(self.client.request::<ObjectList<K>>(request).await?)
+    }
 
+    /// Gets a list of resources in the given namespace restricted by the
+    /// label selector.
+    ///
+    /// This allows tests to list resources outside of the namespace of
+    /// the client, e.g. the pods of an operator which is deployed in
+    /// another namespace than the product.
+    pub async fn list_labeled_in<K>(
+        &self,
+        namespace: &str,
+        label_selector: &str,
+    ) -> Result<ObjectList<K>>
+    where
+        K: Clone + Debug + DeserializeOwned + Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        self.retry_on_disconnect(|| async move {
+            let lp = self.list_defaults.list_params(label_selector);
+            self.list_with_defaults(Some(namespace), &lp).await
+        })
+        .await
+    }
+
+    /// Gets a list of all resources of the given kind across all
+    /// namespaces.
+    ///
+    /// The default label selector of [`KubeClient::list_defaults`] is not
+    /// applied, see [`KubeClient::list_without_label_defaults`].
+    pub async fn list_all_namespaces<K>(&self) -> Result<ObjectList<K>>
+    where
+        K: Clone + Debug + DeserializeOwned + Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        self.list_without_label_defaults("").await
+    }
+
+    /// Gets a list of resources restricted by the field selector.
+    ///
+    /// The field selector supports `=`, `==`, `!=`, and can be comma
+    /// separated: `spec.nodeName=node-1,status.phase=Running`. The
+    /// supported fields depend on the kind of the resource, e.g. pods
+    /// can be selected by `spec.nodeName` and `status.phase`.
+    ///
+    /// Resources are listed across all namespaces.
+    pub async fn list_with_fields<K>(&self, field_selector: &str) -> Result<ObjectList<K>>
+    where
+        K: Clone + Debug + DeserializeOwned + Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        self.retry_on_disconnect(|| async move {
+            let lp = self.list_defaults.list_params("").fields(field_selector);
+            self.list_with_defaults(None, &lp).await
+        })
+        .await
+    }
+
+    /// Gets the resources restricted by the label selector as table like
+    /// it is printed by `kubectl get`.
+    ///
+    /// The table is rendered by the API server and contains the
+    /// additional printer columns of custom resources.
+    pub async fn get_table<K>(&self, label_selector: &str) -> Result<Table>
+    where
+        K: Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        let url_path = K::url_path(&Default::default(), None);
+        let lp = self.list_defaults.list_params(label_selector);
+
+        let mut request = Request::new(url_path).list(&lp)?;
+        if let Some(query) = self.list_defaults.list_resource_version.list_query() {
+            *request.uri_mut() = format!("{}&{}", request.uri(), query).parse()?;
+        }
+        request.headers_mut().insert(
+            http::header::ACCEPT,
+            http::HeaderValue::from_static(TABLE_MEDIA_TYPE),
+        );
+
+        let response = self.client.request_text(request).await?;
+        Table::from_value(&serde_json::from_str(&response)?)
+    }
+
+    /// Applies the given custom resource definition and awaits that the
+    /// conditions in [`KubeClient::crd_conditions`] are true.
+    ///
+    /// By default, the names must be accepted and the definition must be
+    /// established. Otherwise, the API server may not serve the custom
+    /// resources yet and subsequent calls to apply them fail.
+    pub async fn apply_crd(&self, crd: &CustomResourceDefinition) -> Result<()> {
         let timeout_secs = self.timeouts.apply_crd.as_secs() as u32;
         let crds: Api<CustomResourceDefinition> = Api::all(self.client.clone());
 
         let lp = ListParams::default()
             .fields(&format!("metadata.name={}", crd.name()))
             .timeout(timeout_secs);
-        let mut stream = crds.watch(&lp, "0").await?.boxed();
+        let mut stream = crds
+            .watch(&lp, self.list_defaults.resource_version.as_str())
+            .await?
+            .boxed();
 
         let apply_params = PatchParams::apply("agent_integration_test").force();
-        crds.patch(&crd.name(), &apply_params, &Patch::Apply(crd))
+        let applied_crd = crds
+            .patch(&crd.name(), &apply_params, &Patch::Apply(crd))
             .await?;
 
-        if crds.get(&crd.name()).await.is_ok() {
+        if has_crd_conditions(&applied_crd, &self.crd_conditions) {
             return Ok(());
         }
 
         while let Some(status) = stream.try_next().await? {
-            if let WatchEvent::Modified(crd) = status {
-                if is_ready(&crd) {
+            if let WatchEvent::Added(crd) | WatchEvent::Modified(crd) = status {
+                if has_crd_conditions(&crd, &self.crd_conditions) {
                     return Ok(());
                 }
             }
         }
 
         Err(anyhow!(
-            "Custom resource definition [{}] could not be applied within {} seconds.",
+            "Custom resource definition [{}] did not fulfill the conditions {:?} within {} seconds.",
             crd.name(),
+            self.crd_conditions,
             timeout_secs
         ))
     }
 
+    /// Deletes the custom resource definition with the given name and
+    /// awaits that all its custom resources are garbage-collected and
+    /// that the definition itself is removed.
+    ///
+    /// Custom resource definitions which do not exist are ignored.
+    /// Custom resources whose finalizers are not removed, e.g. because
+    /// the operator is not running anymore, block the deletion until the
+    /// timeout expires.
+    pub async fn delete_crd(&self, name: &str) -> Result<()> {
+        let crds: Api<CustomResourceDefinition> = Api::all(self.client.clone());
+        let crd = match crds.get(name).await {
+            Err(kube::Error::Api(error)) if error.code == 404 => return Ok(()),
+            result => result?,
+        };
+        let custom_resources: Api<DynamicObject> =
+            Api::all_with(self.client.clone(), &crd_api_resource(&crd));
+
+        match crds.delete(name, &DeleteParams::default()).await {
+            Err(kube::Error::Api(error)) if error.code == 404 => return Ok(()),
+            result => result?,
+        };
+
+        let timeout = self.timeouts.delete_crd;
+        let start = Instant::now();
+
+        loop {
+            let remaining = match custom_resources.list(&ListParams::default()).await {
+                Err(kube::Error::Api(error)) if error.code == 404 => 0,
+                result => result?.items.len(),
+            };
+            let is_removed = match crds.get(name).await {
+                Err(kube::Error::Api(error)) if error.code == 404 => true,
+                result => result.map(|_| false)?,
+            };
+            if remaining == 0 && is_removed {
+                return Ok(());
+            }
+            if start.elapsed() > timeout {
+                return Err(anyhow!(
+                    "Custom resource definition [{}] could not be deleted within {} seconds; \
+                    {} custom resource(s) remain.",
+                    name,
+                    timeout.as_secs(),
+                    remaining
+                ));
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+
     /// Searches for a resource.
     pub async fn find<K>(&self, name: &str) -> Option<K>
     where
         K: Clone + Debug + DeserializeOwned + Resource,
         <K as Resource>::DynamicType: Default,
     {
-        let api: Api<K> = Api::all(self.client.clone());
-        api.get(name).await.ok()
+        self.retry_on_disconnect(|| async move {
+            let api: Api<K> = Api::all(self.client.clone());
+            Ok(api.get(name).await?)
+        })
+        .await
+        .ok()
     }
 
     /// Searches for a namespaced resource.
@@ -323,40 +1669,246 @@ impl KubeClient {
         K: Clone + Debug + DeserializeOwned + Resource,
         <K as Resource>::DynamicType: Default,
     {
-        let api: Api<K> = Api::namespaced(self.client.clone(), &self.namespace);
-        api.get(name).await.ok()
+        self.retry_on_disconnect(|| async move {
+            let api: Api<K> = Api::namespaced(self.client.clone(), &self.namespace);
+            Ok(api.get(name).await?)
+        })
+        .await
+        .ok()
+    }
+
+    /// Returns an API for the given resource type which operates
+    /// cluster-wide for cluster-scoped resources and in the namespace of
+    /// this client otherwise.
+    ///
+    /// The scope is looked up in the API discovery, so it is also known
+    /// for custom resources.
+    async fn scoped_api<K>(&self) -> Result<Api<K>>
+    where
+        K: Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        self.scoped_api_with(&Default::default()).await
+    }
+
+    /// Returns an API for the resource type with the given dynamic type
+    /// like [`KubeClient::scoped_api`] does.
+    async fn scoped_api_with<K>(&self, dynamic_type: &K::DynamicType) -> Result<Api<K>>
+    where
+        K: Resource,
+    {
+        let api_version = K::api_version(dynamic_type);
+        let plural = K::plural(dynamic_type);
+
+        let api_resources = if api_version.contains('/') {
+            self.client.list_api_group_resources(&api_version).await?
+        } else {
+            self.client.list_core_api_resources(&api_version).await?
+        };
+
+        if is_namespaced(&api_resources, &plural)? {
+            Ok(Api::namespaced_with(
+                self.client.clone(),
+                &self.namespace,
+                dynamic_type,
+            ))
+        } else {
+            Ok(Api::all_with(self.client.clone(), dynamic_type))
+        }
     }
 
     /// Applies a resource with the given YAML specification.
+    ///
+    /// Cluster-scoped resources are supported as well.
     pub async fn apply<K>(&self, spec: &str) -> Result<K>
     where
         K: Clone + Debug + DeserializeOwned + Resource + Serialize,
         <K as Resource>::DynamicType: Default,
+    {
+        self.apply_with(spec, &Default::default()).await
+    }
+
+    /// Applies a resource with the given YAML specification and the
+    /// given dynamic type, e.g. the [`kube::core::ApiResource`] of a
+    /// [`kube::core::DynamicObject`].
+    pub async fn apply_with<K>(&self, spec: &str, dynamic_type: &K::DynamicType) -> Result<K>
+    where
+        K: Clone + Debug + DeserializeOwned + Resource + Serialize,
     {
         let resource: K = from_yaml(spec);
-        let apply_params = PatchParams::apply("agent_integration_test").force();
-        let api: Api<K> = Api::namespaced(self.client.clone(), &self.namespace);
-        Ok(api
+        let resource = &resource;
+        self.retry_on_disconnect(|| async move {
+            let apply_params = PatchParams::apply("agent_integration_test").force();
+            let api: Api<K> = self.scoped_api_with(dynamic_type).await?;
+            Ok(api
+                .patch(&resource.name(), &apply_params, &Patch::Apply(resource))
+                .await?)
+        })
+        .await
+    }
+
+    /// Returns an API for the resources of the given group, version, and
+    /// kind which operates cluster-wide for cluster-scoped resources and
+    /// in the namespace of this client otherwise.
+    ///
+    /// The plural name and the scope are looked up in the API discovery.
+    async fn dynamic_api(&self, gvk: &GroupVersionKind) -> Result<Api<DynamicObject>> {
+        let (api_resource, capabilities) = discovery::pinned_kind(&self.client, gvk).await?;
+        if capabilities.scope == Scope::Namespaced {
+            Ok(Api::namespaced_with(
+                self.client.clone(),
+                &self.namespace,
+                &api_resource,
+            ))
+        } else {
+            Ok(Api::all_with(self.client.clone(), &api_resource))
+        }
+    }
+
+    /// Applies a resource of the given group, version, and kind with the
+    /// given YAML specification.
+    ///
+    /// The resource type is looked up in the API discovery, so no Rust
+    /// type is needed, e.g. for the custom resources of other operators.
+    pub async fn apply_dynamic(&self, gvk: &GroupVersionKind, spec: &str) -> Result<DynamicObject> {
+        let mut resource: DynamicObject = from_yaml(spec);
+        resource.types = Some(TypeMeta {
+            api_version: gvk.api_version(),
+            kind: gvk.kind.clone(),
+        });
+        let resource = &resource;
+        self.retry_on_disconnect(|| async move {
+            let apply_params = PatchParams::apply("agent_integration_test").force();
+            let api = self.dynamic_api(gvk).await?;
+            Ok(api
+                .patch(&resource.name(), &apply_params, &Patch::Apply(resource))
+                .await?)
+        })
+        .await
+    }
+
+    /// Searches for a resource of the given group, version, and kind.
+    ///
+    /// Namespaced resources are searched in the namespace of this client.
+    pub async fn find_dynamic(
+        &self,
+        gvk: &GroupVersionKind,
+        name: &str,
+    ) -> Result<Option<DynamicObject>> {
+        self.retry_on_disconnect(|| async move {
+            let api = self.dynamic_api(gvk).await?;
+            match api.get(name).await {
+                Err(kube::Error::Api(error)) if error.code == 404 => Ok(None),
+                result => Ok(Some(result?)),
+            }
+        })
+        .await
+    }
+
+    /// Lists the resources of the given group, version, and kind in all
+    /// namespaces which match the label selector.
+    pub async fn list_dynamic(
+        &self,
+        gvk: &GroupVersionKind,
+        label_selector: &str,
+    ) -> Result<Vec<DynamicObject>> {
+        self.retry_on_disconnect(|| async move {
+            let (api_resource, _) = discovery::pinned_kind(&self.client, gvk).await?;
+            let api: Api<DynamicObject> = Api::all_with(self.client.clone(), &api_resource);
+            let lp = self.list_defaults.list_params(label_selector);
+            Ok(api.list(&lp).await?.items)
+        })
+        .await
+    }
+
+    /// Discovers the resource kinds and versions which are served in
+    /// the given API group, e.g. `zookeeper.stackable.tech` or the empty
+    /// string for the core group.
+    ///
+    /// For custom resources, the deprecated versions and the storage
+    /// version are taken from the custom resource definitions.
+    pub async fn discover(&self, group: &str) -> Result<DiscoveredGroup> {
+        let api_group = discovery::group(&self.client, group).await?;
+        let resources = api_group
+            .versions()
+            .flat_map(|version| api_group.versioned_resources(version))
+            .collect::<Vec<_>>();
+
+        let mut crds = Vec::new();
+        if !group.is_empty() {
+            let api: Api<CustomResourceDefinition> = Api::all(self.client.clone());
+            let plurals = resources
+                .iter()
+                .map(|(api_resource, _)| api_resource.plural.as_str())
+                .collect::<BTreeSet<_>>();
+            for plural in plurals {
+                match api.get(&format!("{}.{}", plural, group)).await {
+                    Ok(crd) => crds.push(crd),
+                    Err(kube::Error::Api(error)) if error.code == 404 => {}
+                    Err(error) => return Err(error.into()),
+                }
+            }
+        }
+
+        Ok(DiscoveredGroup::from_resources(
+            group,
+            api_group.preferred_version(),
+            &resources,
+            &crds,
+        ))
+    }
+
+    /// Validates a resource with the given YAML specification with a
+    /// server-side dry-run (`dryRun=All`).
+    ///
+    /// The outer result contains errors which prevented the validation,
+    /// e.g. an unreachable API server. The inner result contains either
+    /// the resource with the defaults applied by the server or the
+    /// message of the rejection, e.g. if the resource violates the
+    /// schema of a custom resource definition. Nothing is persisted.
+    pub async fn validate<K>(&self, spec: &str) -> Result<std::result::Result<K, String>>
+    where
+        K: Clone + Debug + DeserializeOwned + Resource + Serialize,
+        <K as Resource>::DynamicType: Default,
+    {
+        let resource: K = from_yaml(spec);
+        let mut apply_params = PatchParams::apply("agent_integration_test").force();
+        apply_params.dry_run = true;
+        let api: Api<K> = self.scoped_api().await?;
+
+        match api
             .patch(&resource.name(), &apply_params, &Patch::Apply(&resource))
-            .await?)
+            .await
+        {
+            Ok(resource) => Ok(Ok(resource)),
+            Err(kube::Error::Api(error)) if error.code == 400 || error.code == 422 => {
+                Ok(Err(error.message))
+            }
+            Err(error) => Err(error.into()),
+        }
     }
 
     /// Creates a resource with the given YAML specification and awaits the
     /// confirmation of the creation.
+    ///
+    /// Cluster-scoped resources are supported as well.
     pub async fn create<K>(&self, spec: &str) -> Result<K>
     where
         K: Clone + Debug + DeserializeOwned + Resource + Serialize,
         <K as Resource>::DynamicType: Default,
     {
         let timeout_secs = self.timeouts.create.as_secs() as u32;
-        let api: Api<K> = Api::namespaced(self.client.clone(), &self.namespace);
+        let api: Api<K> = self.scoped_api().await?;
 
         let resource: K = from_yaml(spec);
 
         let list_params = ListParams::default()
             .fields(&format!("metadata.name={}", resource.name()))
             .timeout(timeout_secs);
-        let mut stream = api.watch(&list_params, "0").await?.boxed();
+        let mut stream = api
+            .watch(&list_params, self.list_defaults.resource_version.as_str())
+            .await?
+            .boxed();
 
         api.create(&PostParams::default(), &resource).await?;
 
@@ -374,22 +1926,37 @@ impl KubeClient {
     }
 
     /// Deletes the given resource and awaits the confirmation of the deletion.
+    ///
+    /// Cluster-scoped resources are supported as well.
     pub async fn delete<K>(&self, resource: K) -> Result<()>
     where
         K: Clone + Debug + DeserializeOwned + Resource,
         <K as Resource>::DynamicType: Default,
+    {
+        self.delete_with(resource, &Default::default()).await
+    }
+
+    /// Deletes the given resource with the given dynamic type and awaits
+    /// the confirmation of the deletion.
+    pub async fn delete_with<K>(&self, resource: K, dynamic_type: &K::DynamicType) -> Result<()>
+    where
+        K: Clone + Debug + DeserializeOwned + Resource,
     {
         let timeout_secs = self.timeouts.delete.as_secs() as u32;
-        let api: Api<K> = Api::namespaced(self.client.clone(), &self.namespace);
+        let api: Api<K> = self.scoped_api_with(dynamic_type).await?;
 
         let list_params = ListParams::default()
             .fields(&format!("metadata.name={}", resource.name()))
             .timeout(timeout_secs);
-        let mut stream = api.watch(&list_params, "0").await?.boxed();
+        let mut stream = api
+            .watch(&list_params, self.list_defaults.resource_version.as_str())
+            .await?
+            .boxed();
 
-        let result = api
-            .delete(&resource.name(), &DeleteParams::default())
-            .await?;
+        let result = match api.delete(&resource.name(), &DeleteParams::default()).await {
+            Err(kube::Error::Api(error)) if error.code == 404 => return Ok(()),
+            result => result?,
+        };
 
         if result.is_right() {
             return Ok(());
@@ -408,154 +1975,2666 @@ impl KubeClient {
         ))
     }
 
-    /// Returns the value of an annotation for the given resource.
-    pub async fn get_annotation<K>(&self, resource: &K, key: &str) -> Result<String>
+    /// Deletes all resources matching the label selector and awaits the
+    /// confirmation of their deletion within the specified timeout.
+    ///
+    /// This is useful to clean up leftovers of a previous test run.
+    /// Cluster-scoped resources are supported as well. An empty label
+    /// selector is rejected because it would delete all resources of
+    /// the type.
+    pub async fn delete_collection<K>(&self, label_selector: &str) -> Result<()>
     where
         K: Clone + Debug + DeserializeOwned + Resource,
         <K as Resource>::DynamicType: Default,
     {
-        let get_value = |resource: &K| {
-            resource
-                .meta()
-                .annotations
-                .as_ref()
-                .and_then(|annotations| annotations.get(key).cloned())
-        };
+        if label_selector.trim().is_empty() {
+            return Err(anyhow!(
+                "An empty label selector would delete all resources of the type."
+            ));
+        }
 
-        let timeout_secs = self.timeouts.get_annotation.as_secs() as u32;
-        let api: Api<K> = Api::namespaced(self.client.clone(), &self.namespace);
+        let timeout_secs = self.timeouts.delete.as_secs() as u32;
+        let api: Api<K> = self.scoped_api().await?;
 
-        let lp = ListParams::default()
-            .fields(&format!("metadata.name={}", resource.name()))
+        let lp = self
+            .list_defaults
+            .list_params(label_selector)
             .timeout(timeout_secs);
-        let mut stream = api.watch(&lp, "0").await?.boxed();
+        let mut stream = api
+            .watch(&lp, self.list_defaults.resource_version.as_str())
+            .await?
+            .boxed();
+
+        let mut remaining = match api
+            .delete_collection(&DeleteParams::default(), &lp)
+            .await?
+            .left()
+        {
+            Some(resources) => resources
+                .items
+                .iter()
+                .map(ResourceExt::name)
+                .collect::<BTreeSet<_>>(),
+            None => return Ok(()),
+        };
 
-        if let Some(value) = get_value(resource) {
-            return Ok(value);
+        if remaining.is_empty() {
+            return Ok(());
         }
 
         while let Some(event) = stream.try_next().await? {
-            if let WatchEvent::Added(resource) | WatchEvent::Modified(resource) = event {
-                if let Some(value) = get_value(&resource) {
-                    return Ok(value);
+            if let WatchEvent::Deleted(resource) = event {
+                remaining.remove(&resource.name());
+                if remaining.is_empty() {
+                    return Ok(());
                 }
             }
         }
 
         Err(anyhow!(
-            "Annotation [{}] could not be retrieved from [{}] within {} seconds",
-            key,
-            resource.name(),
+            "Resources {:?} matching [{}] could not be deleted within {} seconds.",
+            remaining,
+            label_selector,
             timeout_secs
         ))
     }
 
-    /// Verifies that the given pod condition becomes true within the specified timeout.
-    pub async fn verify_pod_condition(&self, pod: &Pod, condition_type: &str) -> Result<Pod> {
-        let is_condition_true = |pod: &Pod| {
-            get_pod_conditions(pod)
-                .iter()
-                .any(|condition| condition.type_ == condition_type && condition.status == "True")
-        };
-        self.verify_status(pod, is_condition_true).await
-    }
-
-    /// Verifies that the status of a resource fulfills the given
-    /// predicate within the specified timeout.
-    pub async fn verify_status<K, P>(&self, resource: &K, predicate: P) -> Result<K>
+    /// Waits until the resource with the given name is gone within the
+    /// specified deletion timeout.
+    ///
+    /// Resources with finalizers are only gone after all finalizers
+    /// are removed. Resources which do not exist are regarded as
+    /// deleted.
+    pub async fn wait_for_deletion<K>(&self, name: &str) -> Result<()>
     where
-        P: Fn(&K) -> bool,
         K: Clone + Debug + DeserializeOwned + Resource,
         <K as Resource>::DynamicType: Default,
     {
-        let timeout_secs = self.timeouts.verify_status.as_secs() as u32;
-        let api: Api<K> = Api::namespaced(self.client.clone(), &self.namespace);
+        let timeout_secs = self.timeouts.delete.as_secs() as u32;
+        let api: Api<K> = self.scoped_api().await?;
 
-        let lp = ListParams::default()
-            .fields(&format!("metadata.name={}", resource.name()))
+        let list_params = ListParams::default()
+            .fields(&format!("metadata.name={}", name))
             .timeout(timeout_secs);
-        let mut stream = api.watch(&lp, "0").await?.boxed();
-
-        let resource = api.get_status(&resource.name()).await?;
+        let mut stream = api
+            .watch(&list_params, self.list_defaults.resource_version.as_str())
+            .await?
+            .boxed();
 
-        if predicate(&resource) {
-            return Ok(resource);
-        }
+        match api.get(name).await {
+            Err(kube::Error::Api(error)) if error.code == 404 => return Ok(()),
+            result => result?,
+        };
 
         while let Some(status) = stream.try_next().await? {
-            if let WatchEvent::Modified(resource) = status {
-                if predicate(&resource) {
-                    return Ok(resource);
-                }
+            if let WatchEvent::Deleted(_) = status {
+                return Ok(());
             }
         }
 
         Err(anyhow!(
-            "Resource [{}] did not reach the expected status within {} seconds.",
-            resource.name(),
+            "Resource [{}] was not deleted within {} seconds.",
+            name,
             timeout_secs
         ))
     }
 
-    /// Returns the given resource with an updated status.
-    pub async fn get_status<K>(&self, resource: &K) -> Result<K>
+    /// Patches the given resource with the given JSON patch (RFC 6902).
+    pub async fn patch_json<K>(&self, resource: &K, patch: Value) -> Result<K>
     where
-        K: DeserializeOwned + Resource,
+        K: Clone + Debug + DeserializeOwned + Resource,
         <K as Resource>::DynamicType: Default,
     {
-        let api: Api<K> = Api::namespaced(self.client.clone(), &self.namespace);
-        Ok(api.get_status(&resource.name()).await?)
+        self.patch(resource, &Patch::Json::<()>(serde_json::from_value(patch)?))
+            .await
     }
 
-    /// Returns the logs for the given pod.
-    pub async fn get_logs(&self, pod: &Pod, params: &LogParams) -> Result<Vec<String>> {
-        let pods: Api<Pod> = Api::namespaced(self.client.clone(), &self.namespace);
-
-        let bytes = pods
-            .log_stream(&pod.name(), params)
+    /// Patches the given resource with the given JSON merge patch
+    /// (RFC 7386).
+    pub async fn patch_merge<K>(&self, resource: &K, patch: Value) -> Result<K>
+    where
+        K: Clone + Debug + DeserializeOwned + Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        self.patch(resource, &Patch::Merge(patch)).await
+    }
+
+    /// Patches the given resource with the given strategic merge patch.
+    ///
+    /// Strategic merge patches are only supported by built-in resources
+    /// but not by custom resources.
+    pub async fn patch_strategic<K>(&self, resource: &K, patch: Value) -> Result<K>
+    where
+        K: Clone + Debug + DeserializeOwned + Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        self.patch(resource, &Patch::Strategic(patch)).await
+    }
+
+    /// Adds the given labels to the resource or overwrites them and
+    /// waits until the change is visible within the specified timeout.
+    pub async fn add_labels<K>(&self, resource: &K, labels: &BTreeMap<String, String>) -> Result<K>
+    where
+        K: Clone + Debug + DeserializeOwned + Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        let entries = labels
+            .iter()
+            .map(|(key, value)| (key.clone(), Some(value.clone())))
+            .collect();
+        self.patch_metadata(resource, "labels", entries, |meta| meta.labels.as_ref())
+            .await
+    }
+
+    /// Removes the labels with the given keys from the resource and
+    /// waits until the change is visible within the specified timeout.
+    pub async fn remove_labels<K>(&self, resource: &K, keys: &[&str]) -> Result<K>
+    where
+        K: Clone + Debug + DeserializeOwned + Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        let entries = keys.iter().map(|key| (key.to_string(), None)).collect();
+        self.patch_metadata(resource, "labels", entries, |meta| meta.labels.as_ref())
+            .await
+    }
+
+    /// Adds the given annotations to the resource or overwrites them and
+    /// waits until the change is visible within the specified timeout.
+    pub async fn add_annotations<K>(
+        &self,
+        resource: &K,
+        annotations: &BTreeMap<String, String>,
+    ) -> Result<K>
+    where
+        K: Clone + Debug + DeserializeOwned + Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        let entries = annotations
+            .iter()
+            .map(|(key, value)| (key.clone(), Some(value.clone())))
+            .collect();
+        self.patch_metadata(resource, "annotations", entries, |meta| {
+            meta.annotations.as_ref()
+        })
+        .await
+    }
+
+    /// Removes the annotations with the given keys from the resource and
+    /// waits until the change is visible within the specified timeout.
+    pub async fn remove_annotations<K>(&self, resource: &K, keys: &[&str]) -> Result<K>
+    where
+        K: Clone + Debug + DeserializeOwned + Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        let entries = keys.iter().map(|key| (key.to_string(), None)).collect();
+        self.patch_metadata(resource, "annotations", entries, |meta| {
+            meta.annotations.as_ref()
+        })
+        .await
+    }
+
+    /// Sets the given entries of a map in the metadata of the resource
+    /// with a merge patch and waits until the resource contains them.
+    ///
+    /// Entries without a value are removed.
+    async fn patch_metadata<K, F>(
+        &self,
+        resource: &K,
+        field: &str,
+        entries: BTreeMap<String, Option<String>>,
+        get_map: F,
+    ) -> Result<K>
+    where
+        K: Clone + Debug + DeserializeOwned + Resource,
+        <K as Resource>::DynamicType: Default,
+        F: Fn(&ObjectMeta) -> Option<&BTreeMap<String, String>>,
+    {
+        let mut metadata = serde_json::Map::new();
+        metadata.insert(field.to_string(), serde_json::to_value(&entries)?);
+        self.patch_merge(resource, json!({ "metadata": metadata }))
+            .await?;
+
+        let timeout = self.timeouts.verify_status;
+        let start = Instant::now();
+        let api: Api<K> = self.scoped_api().await?;
+
+        loop {
+            let current = api.get(&resource.name()).await?;
+            if has_entries(get_map(current.meta()), &entries) {
+                return Ok(current);
+            }
+            if start.elapsed() > timeout {
+                return Err(anyhow!(
+                    "The {} of [{}] were not updated within {} seconds.",
+                    field,
+                    resource.name(),
+                    timeout.as_secs()
+                ));
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+
+    async fn patch<K, P>(&self, resource: &K, patch: &Patch<P>) -> Result<K>
+    where
+        K: Clone + Debug + DeserializeOwned + Resource,
+        <K as Resource>::DynamicType: Default,
+        P: Serialize + Debug,
+    {
+        let apply_patch = || async move {
+            let api: Api<K> = self.scoped_api().await?;
+            Ok(api
+                .patch(&resource.name(), &PatchParams::default(), patch)
+                .await?)
+        };
+
+        // JSON patches are not idempotent, e.g. an "add" operation on an
+        // array would be applied twice, and are therefore not retried.
+        if let Patch::Json(_) = patch {
+            apply_patch().await
+        } else {
+            self.retry_on_disconnect(apply_patch).await
+        }
+    }
+
+    /// Returns the value of an annotation for the given resource.
+    pub async fn get_annotation<K>(&self, resource: &K, key: &str) -> Result<String>
+    where
+        K: Clone + Debug + DeserializeOwned + Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        let timeout = self.timeouts.get_annotation;
+
+        self.retry_on_disconnect_within(timeout, |remaining| async move {
+            let get_value = |resource: &K| {
+                resource
+                    .meta()
+                    .annotations
+                    .as_ref()
+                    .and_then(|annotations| annotations.get(key).cloned())
+            };
+
+            let api: Api<K> = Api::namespaced(self.client.clone(), &self.namespace);
+
+            let lp = ListParams::default()
+                .fields(&format!("metadata.name={}", resource.name()))
+                .timeout(remaining.as_secs().max(1) as u32);
+            let mut stream = api
+                .watch(&lp, self.list_defaults.resource_version.as_str())
+                .await?
+                .boxed();
+
+            if let Some(value) = get_value(resource) {
+                return Ok(value);
+            }
+
+            while let Some(event) = stream.try_next().await? {
+                if let WatchEvent::Added(resource) | WatchEvent::Modified(resource) = event {
+                    if let Some(value) = get_value(&resource) {
+                        return Ok(value);
+                    }
+                }
+            }
+
+            Err(anyhow!(
+                "Annotation [{}] could not be retrieved from [{}] within {} seconds",
+                key,
+                resource.name(),
+                timeout.as_secs()
+            ))
+        })
+        .await
+    }
+
+    /// Waits until the given key of the config map fulfills the
+    /// predicate within the specified timeout and returns its value.
+    ///
+    /// The config map does not need to exist when this method is called.
+    pub async fn wait_for_configmap_key<P>(
+        &self,
+        name: &str,
+        key: &str,
+        predicate: P,
+    ) -> Result<String>
+    where
+        P: Fn(&str) -> bool,
+    {
+        let get_value = |config_map: &ConfigMap| {
+            config_map
+                .data
+                .as_ref()
+                .and_then(|data| data.get(key).cloned())
+        };
+        self.wait_for_key(name, key, get_value, predicate).await
+    }
+
+    /// Returns the data of the given config map.
+    pub async fn get_configmap_data(&self, name: &str) -> Result<BTreeMap<String, String>> {
+        let api: Api<ConfigMap> = Api::namespaced(self.client.clone(), &self.namespace);
+        Ok(api.get(name).await?.data.unwrap_or_default())
+    }
+
+    /// Returns the properties in the given key of the config map, e.g.
+    /// the rendered `zoo.cfg` or `server.properties`.
+    pub async fn get_configmap_properties(
+        &self,
+        name: &str,
+        key: &str,
+    ) -> Result<BTreeMap<String, String>> {
+        let data = self.get_configmap_data(name).await?;
+        let content = data
+            .get(key)
+            .ok_or_else(|| anyhow!("Config map [{}] does not contain the key [{}].", name, key))?;
+        Ok(parse_properties(content))
+    }
+
+    /// Waits until the given key of the secret fulfills the predicate
+    /// within the specified timeout and returns its value.
+    ///
+    /// The secret does not need to exist when this method is called.
+    /// The value is decoded as UTF-8 before it is passed to the
+    /// predicate.
+    pub async fn wait_for_secret_key<P>(
+        &self,
+        name: &str,
+        key: &str,
+        predicate: P,
+    ) -> Result<String>
+    where
+        P: Fn(&str) -> bool,
+    {
+        let get_value = |secret: &Secret| {
+            secret
+                .data
+                .as_ref()
+                .and_then(|data| data.get(key))
+                .map(|value| String::from_utf8_lossy(&value.0).into_owned())
+        };
+        self.wait_for_key(name, key, get_value, predicate).await
+    }
+
+    /// Creates a secret with the given data in the namespace of this
+    /// client and returns it.
+    ///
+    /// The values are encoded as base64 by the client, so they can be
+    /// given in plain text.
+    pub async fn create_secret(
+        &self,
+        name: &str,
+        data: &BTreeMap<String, String>,
+    ) -> Result<Secret> {
+        let secret = Secret {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                ..Default::default()
+            },
+            data: Some(
+                data.iter()
+                    .map(|(key, value)| (key.clone(), ByteString(value.as_bytes().to_vec())))
+                    .collect(),
+            ),
+            ..Default::default()
+        };
+        self.create(&serde_yaml::to_string(&secret)?).await
+    }
+
+    /// Returns the data of the given secret with the values decoded
+    /// from base64 and UTF-8.
+    pub async fn get_secret_data(&self, name: &str) -> Result<BTreeMap<String, String>> {
+        let api: Api<Secret> = Api::namespaced(self.client.clone(), &self.namespace);
+        Ok(decode_secret_data(&api.get(name).await?))
+    }
+
+    /// Waits until the value extracted from the named resource fulfills
+    /// the predicate within the specified timeout.
+    async fn wait_for_key<K, F, P>(
+        &self,
+        name: &str,
+        key: &str,
+        get_value: F,
+        predicate: P,
+    ) -> Result<String>
+    where
+        K: Clone + Debug + DeserializeOwned + Resource,
+        <K as Resource>::DynamicType: Default,
+        F: Fn(&K) -> Option<String>,
+        P: Fn(&str) -> bool,
+    {
+        let get_value = &get_value;
+        let predicate = &predicate;
+        let timeout = self.timeouts.wait_for_key;
+
+        self.retry_on_disconnect_within(timeout, |remaining| async move {
+            let api: Api<K> = Api::namespaced(self.client.clone(), &self.namespace);
+
+            let lp = ListParams::default()
+                .fields(&format!("metadata.name={}", name))
+                .timeout(remaining.as_secs().max(1) as u32);
+            let mut stream = api
+                .watch(&lp, self.list_defaults.resource_version.as_str())
+                .await?
+                .boxed();
+
+            let resource = match api.get(name).await {
+                Err(kube::Error::Api(error)) if error.code == 404 => None,
+                result => Some(result?),
+            };
+            if let Some(value) = resource.as_ref().and_then(get_value) {
+                if predicate(&value) {
+                    return Ok(value);
+                }
+            }
+
+            while let Some(event) = stream.try_next().await? {
+                if let WatchEvent::Added(resource) | WatchEvent::Modified(resource) = event {
+                    if let Some(value) = get_value(&resource) {
+                        if predicate(&value) {
+                            return Ok(value);
+                        }
+                    }
+                }
+            }
+
+            Err(anyhow!(
+                "Key [{}] of [{}] did not reach the expected value within {} seconds",
+                key,
+                name,
+                timeout.as_secs()
+            ))
+        })
+        .await
+    }
+
+    /// Requests a token for the given service account via the
+    /// TokenRequest API.
+    ///
+    /// The token is bound to the given audiences and expires after the
+    /// given duration. It can be used to call product APIs which are
+    /// secured via a Kubernetes token review.
+    pub async fn request_service_account_token(
+        &self,
+        service_account_name: &str,
+        audiences: &[&str],
+        expiration: Duration,
+    ) -> Result<String> {
+        let service_accounts: Api<ServiceAccount> =
+            Api::namespaced(self.client.clone(), &self.namespace);
+
+        let token_request = TokenRequest {
+            spec: TokenRequestSpec {
+                audiences: audiences
+                    .iter()
+                    .map(|audience| audience.to_string())
+                    .collect(),
+                expiration_seconds: Some(expiration.as_secs() as i64),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let request = Request::new(format!(
+            "{}/{}/token",
+            service_accounts.resource_url(),
+            service_account_name
+        ))
+        .create(&PostParams::default(), serde_json::to_vec(&token_request)?)?;
+
+        let token_request: TokenRequest = self.client.request(request).await?;
+
+        token_request
+            .status
+            .map(|status| status.token)
+            .ok_or_else(|| {
+                anyhow!(
+                    "No token was issued for service account [{}].",
+                    service_account_name
+                )
+            })
+    }
+
+    /// Creates the given ServiceAccount in the namespace of this client if
+    /// it does not exist yet and requests a token for it via the
+    /// TokenRequest API.
+    ///
+    /// The token is bound to the default audience of the API server and
+    /// expires after the given duration.
+    pub async fn create_service_account_token(
+        &self,
+        name: &str,
+        expiration: Duration,
+    ) -> Result<String> {
+        self.apply::<ServiceAccount>(&format!(
+            "
+            apiVersion: v1
+            kind: ServiceAccount
+            metadata:
+              name: {}
+            ",
+            name
+        ))
+        .await?;
+        self.request_service_account_token(name, &[], expiration)
+            .await
+    }
+
+    /// Creates the given ServiceAccount in the namespace of this client if
+    /// it does not exist yet and returns a client with the same settings
+    /// which is authenticated as this ServiceAccount.
+    ///
+    /// The client uses a token of the TokenRequest API which expires after
+    /// the given duration. Only the permissions which are granted to the
+    /// ServiceAccount, e.g. with a RoleBinding, are available, so
+    /// least-privilege and multi-tenant scenarios can be tested with real
+    /// credentials instead of impersonation:
+    ///
+    /// ```rust,no_run
+    /// use integration_test_commons::test::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// let client = TestKubeClient::new();
+    /// let tenant = client.as_service_account("tenant-a", Duration::from_secs(3600));
+    ///
+    /// assert_that(&tenant.can_i("list", "pods", Some("default"))).is_false();
+    /// ```
+    pub async fn as_service_account(&self, name: &str, expiration: Duration) -> Result<KubeClient> {
+        let token = self.create_service_account_token(name, expiration).await?;
+        self.with_bearer_token(&token).await
+    }
+
+    /// Returns true if the user of this client is allowed to perform the
+    /// given verb on the given resource in the given namespace or in all
+    /// namespaces if it is `None`, like `kubectl auth can-i` does.
+    ///
+    /// The resource is specified like `pods`, `statefulsets.apps`, or
+    /// `pods/log` with an optional API group and subresource. The access
+    /// is reviewed with a SelfSubjectAccessReview, so in combination with
+    /// [`KubeClient::impersonating`] the permissions of other users can
+    /// be tested.
+    pub async fn can_i(&self, verb: &str, resource: &str, namespace: Option<&str>) -> Result<bool> {
+        let review = SelfSubjectAccessReview {
+            spec: SelfSubjectAccessReviewSpec {
+                resource_attributes: Some(resource_attributes(verb, resource, namespace)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let reviews: Api<SelfSubjectAccessReview> = Api::all(self.client.clone());
+        let review = reviews.create(&PostParams::default(), &review).await?;
+        is_access_allowed(review.status)
+    }
+
+    /// Returns true if the given user with the given groups is allowed to
+    /// perform the given verb on the given resource in the given
+    /// namespace or in all namespaces if it is `None`.
+    ///
+    /// The resource is specified like in [`KubeClient::can_i`]. The access
+    /// is reviewed with a SubjectAccessReview which the user of this
+    /// client must be allowed to create. This allows to assert that the
+    /// ServiceAccount of an operator has exactly the permissions it needs:
+    ///
+    /// ```rust,no_run
+    /// use integration_test_commons::test::kube::service_account_subject;
+    /// use integration_test_commons::test::prelude::*;
+    ///
+    /// let client = TestKubeClient::new();
+    /// let (user, groups) = service_account_subject("default", "zookeeper-operator");
+    /// let groups = groups.iter().map(String::as_str).collect::<Vec<_>>();
+    ///
+    /// assert_that(&client.can_i_as(&user, &groups, "create", "statefulsets.apps", Some("default")))
+    ///     .is_true();
+    /// assert_that(&client.can_i_as(&user, &groups, "get", "secrets", None)).is_false();
+    /// ```
+    pub async fn can_i_as(
+        &self,
+        user: &str,
+        groups: &[&str],
+        verb: &str,
+        resource: &str,
+        namespace: Option<&str>,
+    ) -> Result<bool> {
+        let review = SubjectAccessReview {
+            spec: SubjectAccessReviewSpec {
+                user: Some(user.to_string()),
+                groups: Some(groups.iter().map(|group| group.to_string()).collect()),
+                resource_attributes: Some(resource_attributes(verb, resource, namespace)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let reviews: Api<SubjectAccessReview> = Api::all(self.client.clone());
+        let review = reviews.create(&PostParams::default(), &review).await?;
+        is_access_allowed(review.status)
+    }
+
+    /// Verifies that no resource matching the label selector is created
+    /// within the given observation window.
+    ///
+    /// This is useful for negative tests, e.g. to verify that an
+    /// operator does not create a resource if a feature is disabled.
+    ///
+    /// If the API server closes the watch before the observation window
+    /// elapsed then the watch is resumed from the last seen resource
+    /// version.
+    pub async fn verify_not_created<K>(
+        &self,
+        label_selector: &str,
+        observation_window: Duration,
+    ) -> Result<()>
+    where
+        K: Clone + Debug + DeserializeOwned + Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        let deadline = Instant::now() + observation_window;
+        let api: Api<K> = Api::namespaced(self.client.clone(), &self.namespace);
+        let mut resource_version = None;
+
+        loop {
+            let version = match resource_version.take() {
+                Some(version) => version,
+                None => {
+                    let list = api
+                        .list(&self.list_defaults.list_params(label_selector))
+                        .await?;
+                    if let Some(resource) = list.items.first() {
+                        return Err(anyhow!(
+                            "Resource [{}] matching [{}] exists although it should not be created.",
+                            resource.name(),
+                            label_selector
+                        ));
+                    }
+                    list.metadata.resource_version.unwrap_or_default()
+                }
+            };
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining == Duration::ZERO {
+                return Ok(());
+            }
+
+            let lp = self
+                .list_defaults
+                .list_params(label_selector)
+                .timeout(secs_rounded_up(remaining) as u32);
+            let mut stream = api.watch(&lp, &version).await?.boxed();
+            let mut last_version = Some(version);
+
+            loop {
+                let next_event = tokio::time::timeout_at(deadline.into(), stream.try_next());
+                let event = match next_event.await {
+                    Ok(event) => event?,
+                    Err(_) => return Ok(()),
+                };
+                match event {
+                    Some(WatchEvent::Added(resource)) => {
+                        return Err(anyhow!(
+                            "Resource [{}] matching [{}] was created within {} seconds although it should not be created.",
+                            resource.name(),
+                            label_selector,
+                            observation_window.as_secs()
+                        ));
+                    }
+                    Some(WatchEvent::Modified(resource)) | Some(WatchEvent::Deleted(resource)) => {
+                        last_version = resource.resource_version();
+                    }
+                    Some(WatchEvent::Bookmark(bookmark)) => {
+                        last_version = Some(bookmark.metadata.resource_version);
+                    }
+                    Some(WatchEvent::Error(_)) => {
+                        // e.g. 410 Gone if the resource version is too old;
+                        // the resources are listed again
+                        last_version = None;
+                        break;
+                    }
+                    None => break,
+                }
+            }
+
+            resource_version = last_version;
+        }
+    }
+
+    /// Returns the events which involve the given resource.
+    pub async fn list_events<K>(&self, resource: &K) -> Result<Vec<Event>>
+    where
+        K: Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        let (api, lp) = self.event_api(resource);
+        Ok(api.list(&lp).await?.items)
+    }
+
+    /// Returns the Warning events in all namespaces.
+    pub async fn list_warning_events(&self) -> Result<Vec<Event>> {
+        let api: Api<Event> = Api::all(self.client.clone());
+        let lp = ListParams::default().fields("type=Warning");
+        Ok(api.list(&lp).await?.items)
+    }
+
+    /// Returns the reason why the given pod cannot be scheduled, e.g.
+    /// insufficient CPU, an unbound persistent volume claim, or
+    /// untolerated taints, or `None` if the pod is scheduled or the
+    /// reason is not known yet.
+    ///
+    /// The events of the pod are only retrieved if the pod is not
+    /// scheduled.
+    pub async fn get_scheduling_failure(&self, pod: &Pod) -> Result<Option<String>> {
+        if !is_unscheduled(pod) {
+            return Ok(None);
+        }
+        let events = self.list_events(pod).await?;
+        Ok(scheduling_failure(pod, &events))
+    }
+
+    /// Waits until an event which involves the given resource and has
+    /// the given reason occurs within the specified timeout and returns
+    /// it.
+    ///
+    /// Events which occurred before the call are also considered.
+    pub async fn wait_for_event<K>(&self, resource: &K, reason: &str) -> Result<Event>
+    where
+        K: Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        self.wait_for_event_matching(resource, |event| event.reason.as_deref() == Some(reason))
+            .await
+    }
+
+    /// Waits until an event which involves the given resource and
+    /// fulfills the given predicate occurs within the specified timeout
+    /// and returns it.
+    ///
+    /// Events which occurred before the call are also considered.
+    pub async fn wait_for_event_matching<K, P>(&self, resource: &K, predicate: P) -> Result<Event>
+    where
+        K: Resource,
+        <K as Resource>::DynamicType: Default,
+        P: Fn(&Event) -> bool,
+    {
+        let timeout_secs = self.timeouts.wait_for_event.as_secs() as u32;
+        let (api, lp) = self.event_api(resource);
+        let lp = lp.timeout(timeout_secs);
+
+        let mut stream = api
+            .watch(&lp, self.list_defaults.resource_version.as_str())
+            .await?
+            .boxed();
+
+        if let Some(event) = api.list(&lp).await?.items.into_iter().find(&predicate) {
+            return Ok(event);
+        }
+
+        while let Some(watch_event) = stream.try_next().await? {
+            if let WatchEvent::Added(event) | WatchEvent::Modified(event) = watch_event {
+                if predicate(&event) {
+                    return Ok(event);
+                }
+            }
+        }
+
+        Err(anyhow!(
+            "The expected event for [{}] did not occur within {} seconds.",
+            resource.name(),
+            timeout_secs
+        ))
+    }
+
+    /// Waits until an event which involves the given resource, has the
+    /// given reason, and contains the given substring in its message
+    /// occurs within the specified timeout and returns it.
+    ///
+    /// If the event does not occur then the error lists the events which
+    /// were published for the resource instead.
+    pub async fn wait_for_operator_event<K>(
+        &self,
+        resource: &K,
+        reason: &str,
+        message_substring: &str,
+    ) -> Result<Event>
+    where
+        K: Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        let result = self
+            .wait_for_event_matching(resource, |event| {
+                is_event_matching(event, reason, message_substring)
+            })
+            .await;
+
+        match result {
+            Ok(event) => Ok(event),
+            Err(error) => {
+                let events = match self.list_events(resource).await {
+                    Ok(events) => describe_events(&events),
+                    Err(list_error) => format!("unknown ({})", list_error),
+                };
+                Err(anyhow!(
+                    "{} An event with the reason [{}] and a message containing [{}] was \
+                    expected. The published events are: {}",
+                    error,
+                    reason,
+                    message_substring,
+                    events
+                ))
+            }
+        }
+    }
+
+    /// Returns the API and the list parameters for the events which
+    /// involve the given resource.
+    fn event_api<K>(&self, resource: &K) -> (Api<Event>, ListParams)
+    where
+        K: Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        let namespace = resource
+            .namespace()
+            .unwrap_or_else(|| self.namespace.clone());
+        let api = Api::namespaced(self.client.clone(), &namespace);
+
+        let mut fields = format!(
+            "involvedObject.kind={},involvedObject.name={}",
+            K::kind(&Default::default()),
+            resource.name()
+        );
+        if let Some(uid) = resource.uid() {
+            fields.push_str(&format!(",involvedObject.uid={}", uid));
+        }
+
+        (api, ListParams::default().fields(&fields))
+    }
+
+    /// Verifies that the given labels of the parent resource were
+    /// copied to all child resources matching the label selector.
+    pub async fn verify_labels_propagated<P, C>(
+        &self,
+        parent: &P,
+        child_selector: &str,
+        keys: &[&str],
+    ) -> Result<()>
+    where
+        P: Resource,
+        C: Clone + Debug + DeserializeOwned + Resource,
+        <C as Resource>::DynamicType: Default,
+    {
+        let children = self.list_labeled::<C>(child_selector).await?;
+        verify_propagated(
+            "Label",
+            parent,
+            &children.items,
+            |meta| meta.labels.as_ref(),
+            keys,
+        )
+    }
+
+    /// Verifies that the given annotations of the parent resource were
+    /// copied to all child resources matching the label selector.
+    pub async fn verify_annotations_propagated<P, C>(
+        &self,
+        parent: &P,
+        child_selector: &str,
+        keys: &[&str],
+    ) -> Result<()>
+    where
+        P: Resource,
+        C: Clone + Debug + DeserializeOwned + Resource,
+        <C as Resource>::DynamicType: Default,
+    {
+        let children = self.list_labeled::<C>(child_selector).await?;
+        verify_propagated(
+            "Annotation",
+            parent,
+            &children.items,
+            |meta| meta.annotations.as_ref(),
+            keys,
+        )
+    }
+
+    /// Sets the status of the given pod condition and returns the
+    /// updated pod.
+    ///
+    /// This allows to signal the readiness of a custom readiness gate
+    /// from a test case, see [`with_readiness_gate`].
+    pub async fn set_pod_condition(
+        &self,
+        pod: &Pod,
+        condition_type: &str,
+        status: bool,
+    ) -> Result<Pod> {
+        let pods: Api<Pod> = Api::namespaced(self.client.clone(), &self.namespace);
+
+        let patch = json!({
+            "status": {
+                "conditions": [{
+                    "type": condition_type,
+                    "status": if status { "True" } else { "False" },
+                    "lastTransitionTime": Utc::now(),
+                }]
+            }
+        });
+
+        Ok(pods
+            .patch_status(
+                &pod.name(),
+                &PatchParams::default(),
+                &Patch::Strategic(patch),
+            )
+            .await?)
+    }
+
+    /// Marks the given node as unschedulable and returns the updated
+    /// node.
+    ///
+    /// Pods which are already running on the node are not evicted.
+    pub async fn cordon(&self, node: &Node) -> Result<Node> {
+        self.set_unschedulable(node, true).await
+    }
+
+    /// Marks the given node as schedulable and returns the updated node.
+    pub async fn uncordon(&self, node: &Node) -> Result<Node> {
+        self.set_unschedulable(node, false).await
+    }
+
+    /// Cordons the given node, evicts all pods which are not managed by
+    /// a DaemonSet, and waits until the evicted pods of controllers are
+    /// rescheduled and ready on other nodes within the specified
+    /// timeout.
+    ///
+    /// Evictions which are rejected because of a PodDisruptionBudget are
+    /// retried until the timeout is reached. Pods without a controller
+    /// are evicted but not awaited.
+    pub async fn drain(&self, node: &Node) -> Result<Node> {
+        let timeout = self.timeouts.drain;
+        let start = Instant::now();
+
+        let node = self.cordon(node).await?;
+        let node_name = node.name();
+
+        let all_pods: Api<Pod> = Api::all(self.client.clone());
+        let pods_before = all_pods.list(&ListParams::default()).await?.items;
+        let evicted_pods = drainable_pods(&pods_before, &node_name);
+
+        for pod in &evicted_pods {
+            let namespace = pod.namespace().unwrap_or_default();
+            let pods: Api<Pod> = Api::namespaced(self.client.clone(), &namespace);
+            loop {
+                match pods.evict(&pod.name(), &EvictParams::default()).await {
+                    Ok(_) => break,
+                    Err(kube::Error::Api(error)) if error.code == 404 => break,
+                    Err(kube::Error::Api(error))
+                        if error.code == 429 && start.elapsed() < timeout =>
+                    {
+                        tokio::time::sleep(Duration::from_secs(2)).await
+                    }
+                    Err(error) => return Err(error.into()),
+                }
+            }
+        }
+
+        loop {
+            let pods_after = all_pods.list(&ListParams::default()).await?.items;
+            if is_rescheduled(&evicted_pods, &pods_before, &pods_after, &node_name) {
+                return Ok(node);
+            }
+            if start.elapsed() > timeout {
+                return Err(anyhow!(
+                    "Pods of node [{}] were not rescheduled within {} seconds.",
+                    node_name,
+                    timeout.as_secs()
+                ));
+            }
+            tokio::time::sleep(Duration::from_secs(2)).await;
+        }
+    }
+
+    async fn set_unschedulable(&self, node: &Node, unschedulable: bool) -> Result<Node> {
+        let patch = json!({ "spec": { "unschedulable": unschedulable } });
+        let node = self.patch_merge(node, patch).await?;
+
+        if is_cordoned(&node) == unschedulable {
+            Ok(node)
+        } else {
+            Err(anyhow!(
+                "Node [{}] was not marked as {}.",
+                node.name(),
+                if unschedulable {
+                    "unschedulable"
+                } else {
+                    "schedulable"
+                }
+            ))
+        }
+    }
+
+    /// Verifies that the given pod condition becomes true within the specified timeout.
+    pub async fn verify_pod_condition(&self, pod: &Pod, condition_type: &str) -> Result<Pod> {
+        let is_condition_true = |pod: &Pod| {
+            get_pod_conditions(pod)
+                .iter()
+                .any(|condition| condition.type_ == condition_type && condition.status == "True")
+        };
+        self.verify_status(pod, is_condition_true).await
+    }
+
+    /// Verifies that the status of a resource fulfills the given
+    /// predicate within the specified timeout.
+    pub async fn verify_status<K, P>(&self, resource: &K, predicate: P) -> Result<K>
+    where
+        P: Fn(&K) -> bool,
+        K: Clone + Debug + DeserializeOwned + Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        self.verify_status_within(resource, predicate, self.timeouts.verify_status)
+            .await
+    }
+
+    /// Waits until the condition of the given type is true and its
+    /// `lastTransitionTime` is not before `since` within the specified
+    /// timeout.
+    ///
+    /// The timestamps of conditions have a precision of seconds, so
+    /// `since` is truncated accordingly.
+    pub async fn wait_for_condition_transition<K>(
+        &self,
+        resource: &K,
+        type_: &str,
+        since: &Time,
+    ) -> Result<K>
+    where
+        K: Clone + Debug + DeserializeOwned + Resource + Serialize,
+        <K as Resource>::DynamicType: Default,
+    {
+        let has_transitioned = |resource: &K| {
+            serde_json::to_value(resource)
+                .is_ok_and(|value| has_condition_transitioned(&value, type_, since))
+        };
+        self.verify_status(resource, has_transitioned).await
+    }
+
+    /// Waits until the status field at the given JSON pointer fulfills
+    /// the given predicate within the specified timeout.
+    ///
+    /// The JSON pointer is relative to the status of the resource. The
+    /// predicate is not evaluated as long as the field is absent.
+    pub async fn wait_for_status_field<K, P>(
+        &self,
+        resource: &K,
+        json_pointer: &str,
+        predicate: P,
+    ) -> Result<K>
+    where
+        P: Fn(&Value) -> bool,
+        K: Clone + Debug + DeserializeOwned + Resource + Serialize,
+        <K as Resource>::DynamicType: Default,
+    {
+        let has_expected_value = |resource: &K| {
+            serde_json::to_value(resource)
+                .is_ok_and(|value| status_field(&value, json_pointer).is_some_and(&predicate))
+        };
+        self.verify_status(resource, has_expected_value).await
+    }
+
+    /// Waits until the given workload, i.e. a Deployment, StatefulSet,
+    /// or DaemonSet, is fully rolled out within the specified timeout
+    /// like `kubectl rollout status` does.
+    pub async fn wait_for_rollout<K>(&self, workload: &K) -> Result<K>
+    where
+        K: Clone + Debug + DeserializeOwned + Resource + Rollout,
+        <K as Resource>::DynamicType: Default,
+    {
+        self.verify_status_within(workload, K::is_rolled_out, self.timeouts.rollout)
+            .await
+    }
+
+    /// Verifies that the status of a resource fulfills the given
+    /// predicate within the given timeout.
+    ///
+    /// If the status is not reached then the error contains the last
+    /// observed state of the resource with the credentials redacted.
+    async fn verify_status_within<K, P>(
+        &self,
+        resource: &K,
+        predicate: P,
+        timeout: Duration,
+    ) -> Result<K>
+    where
+        P: Fn(&K) -> bool,
+        K: Clone + Debug + DeserializeOwned + Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        let predicate = &predicate;
+
+        self.retry_on_disconnect_within(timeout, |remaining| async move {
+            let api: Api<K> = Api::namespaced(self.client.clone(), &self.namespace);
+
+            let lp = ListParams::default()
+                .fields(&format!("metadata.name={}", resource.name()))
+                .timeout(remaining.as_secs().max(1) as u32);
+            let mut stream = api
+                .watch(&lp, self.list_defaults.resource_version.as_str())
+                .await?
+                .boxed();
+
+            let resource = api.get_status(&resource.name()).await?;
+
+            if predicate(&resource) {
+                return Ok(resource);
+            }
+
+            while let Some(status) = stream.try_next().await? {
+                if let WatchEvent::Modified(resource) = status {
+                    if predicate(&resource) {
+                        return Ok(resource);
+                    }
+                }
+            }
+
+            Err(anyhow!(
+                "Resource [{}] did not reach the expected status within {} seconds. \
+                Its current state is:\n{}",
+                resource.name(),
+                timeout.as_secs(),
+                self.redacted_state(&resource).await
+            ))
+        })
+        .await
+    }
+
+    /// Verifies that the status of a resource does not fulfill the given
+    /// predicate within the given observation window.
+    pub async fn verify_status_not_reached<K, P>(
+        &self,
+        resource: &K,
+        predicate: P,
+        observation_window: Duration,
+    ) -> Result<()>
+    where
+        P: Fn(&K) -> bool,
+        K: Clone + Debug + DeserializeOwned + Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        let window_secs = observation_window.as_secs() as u32;
+        let api: Api<K> = Api::namespaced(self.client.clone(), &self.namespace);
+
+        let lp = ListParams::default()
+            .fields(&format!("metadata.name={}", resource.name()))
+            .timeout(window_secs);
+        let mut stream = api
+            .watch(&lp, self.list_defaults.resource_version.as_str())
+            .await?
+            .boxed();
+
+        let resource = api.get_status(&resource.name()).await?;
+
+        if predicate(&resource) {
+            return Err(anyhow!(
+                "Resource [{}] has the unexpected status. Its current state is:\n{}",
+                resource.name(),
+                self.redacted_state(&resource).await
+            ));
+        }
+
+        while let Some(status) = stream.try_next().await? {
+            if let WatchEvent::Modified(resource) = status {
+                if predicate(&resource) {
+                    return Err(anyhow!(
+                        "Resource [{}] reached the unexpected status within {} seconds. \
+                        Its current state is:\n{}",
+                        resource.name(),
+                        window_secs,
+                        self.redacted_state(&resource).await
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the current state of the given resource as YAML for
+    /// diagnostics. Credentials are redacted because the diagnostics are
+    /// printed and archived.
+    async fn redacted_state<K>(&self, resource: &K) -> String
+    where
+        K: Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        let api_resource = ApiResource::erase::<K>(&Default::default());
+        let api: Api<DynamicObject> =
+            Api::namespaced_with(self.client.clone(), &self.namespace, &api_resource);
+        match api.get(&resource.name()).await {
+            Ok(resource) => to_redacted_yaml(&resource),
+            Err(error) => format!("unknown ({})", error),
+        }
+    }
+
+    /// Waits until the resource version of the given resource has not
+    /// changed for the given quiet period and returns the stable
+    /// resource.
+    ///
+    /// An error is returned if the resource does not become stable
+    /// within the `wait_until_stable` timeout.
+    pub async fn wait_until_stable<K>(&self, resource: &K, quiet_period: Duration) -> Result<K>
+    where
+        K: Clone + Debug + DeserializeOwned + Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        let timeout = self.timeouts.wait_until_stable;
+        let api: Api<K> = Api::namespaced(self.client.clone(), &self.namespace);
+
+        let lp = ListParams::default()
+            .fields(&format!("metadata.name={}", resource.name()))
+            .timeout(timeout.as_secs() as u32);
+        let mut stream = api
+            .watch(&lp, self.list_defaults.resource_version.as_str())
+            .await?
+            .boxed();
+
+        let start = Instant::now();
+        let mut resource = api.get(&resource.name()).await?;
+        let mut last_change = Instant::now();
+
+        while last_change + quiet_period <= start + timeout {
+            let remaining = quiet_period.saturating_sub(last_change.elapsed());
+            let event = match tokio::time::timeout(remaining, stream.try_next()).await {
+                Ok(event) => event?,
+                Err(_) => return Ok(resource),
+            };
+            match event {
+                Some(WatchEvent::Modified(modified)) => {
+                    if modified.resource_version() != resource.resource_version() {
+                        resource = modified;
+                        last_change = Instant::now();
+                    }
+                }
+                Some(WatchEvent::Deleted(_)) => {
+                    return Err(anyhow!(
+                        "Resource [{}] was deleted before it became stable.",
+                        resource.name()
+                    ))
+                }
+                Some(_) => {}
+                None => break,
+            }
+        }
+
+        Err(anyhow!(
+            "Resource [{}] did not become stable for {} seconds within {} seconds.",
+            resource.name(),
+            quiet_period.as_secs(),
+            timeout.as_secs()
+        ))
+    }
+
+    /// Returns the given resource with an updated status.
+    pub async fn get_status<K>(&self, resource: &K) -> Result<K>
+    where
+        K: DeserializeOwned + Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        self.retry_on_disconnect(|| async move {
+            let api: Api<K> = Api::namespaced(self.client.clone(), &self.namespace);
+            Ok(api.get_status(&resource.name()).await?)
+        })
+        .await
+    }
+
+    /// Verifies that the product binary in the image of the given pod
+    /// reports a version matching the given regular expression and
+    /// returns the output of the command.
+    ///
+    /// The command, e.g. `["bin/kafka-topics.sh", "--version"]`, is
+    /// executed in the first container of the pod, so it runs with the
+    /// environment, volumes, and service account of the product. This
+    /// reveals mismatches between the version label of the image and the
+    /// actually shipped binary. Standard output and standard error are
+    /// both matched because some binaries print their version to the
+    /// latter.
+    pub async fn verify_product_version_via_cli(
+        &self,
+        pod: &Pod,
+        command: &[&str],
+        expected_version_regex: &str,
+    ) -> Result<String> {
+        let regex = Regex::new(expected_version_regex)?;
+
+        let container = pod
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.containers.first())
+            .ok_or_else(|| anyhow!("Pod [{}] has no containers.", pod.name()))?;
+        let result = self
+            .exec_in_container(pod, &container.name, command)
+            .await?;
+        let output = format!("{}{}", result.stdout, result.stderr);
+
+        if !result.success() {
+            Err(anyhow!(
+                "The command {:?} failed in pod [{}]: {}",
+                command,
+                pod.name(),
+                output
+            ))
+        } else if regex.is_match(&output) {
+            Ok(output)
+        } else {
+            Err(anyhow!(
+                "The output of the command {:?} does not match [{}]: {}",
+                command,
+                expected_version_regex,
+                output
+            ))
+        }
+    }
+
+    /// Executes the given command in the default container of the given
+    /// pod and returns its output.
+    ///
+    /// A non-zero exit code is not treated as an error but is returned
+    /// in the output.
+    pub async fn exec(&self, pod: &Pod, command: &[&str]) -> Result<ExecOutput> {
+        self.exec_with_params(pod, command, AttachParams::default())
+            .await
+    }
+
+    /// Executes the given command in the given container of the pod and
+    /// returns its output.
+    ///
+    /// A non-zero exit code is not treated as an error but is returned
+    /// in the output.
+    pub async fn exec_in_container(
+        &self,
+        pod: &Pod,
+        container: &str,
+        command: &[&str],
+    ) -> Result<ExecOutput> {
+        self.exec_with_params(pod, command, AttachParams::default().container(container))
+            .await
+    }
+
+    /// Forwards the given port of the pod to a local address.
+    ///
+    /// Every connection to the local address opens a new connection to
+    /// the port of the pod. The forwarding is stopped when the returned
+    /// [`PortForward`] is dropped.
+    #[cfg(feature = "port-forward")]
+    pub async fn port_forward(&self, pod: &Pod, port: u16) -> Result<PortForward> {
+        let namespace = pod.namespace().unwrap_or_else(|| self.namespace.clone());
+        PortForward::start(self.client.clone(), &namespace, &pod.name(), port).await
+    }
+
+    /// Copies the given local file or directory to the given path in the
+    /// default container of the pod.
+    ///
+    /// The files are transferred as tar archive like `kubectl cp` does,
+    /// so `sh`, `head`, and `tar` must be available in the container.
+    #[cfg(feature = "copy")]
+    pub async fn copy_to_pod(&self, pod: &Pod, local_path: &Path, remote_path: &str) -> Result<()> {
+        let (remote_dir, remote_name) = split_remote_path(remote_path)?;
+        let archive = pack(local_path, remote_name)?;
+        let archive_len = archive.len().to_string();
+
+        let command = [
+            "sh",
+            "-c",
+            r#"head -c "$0" | tar xmf - -C "$1""#,
+            &archive_len,
+            remote_dir,
+        ];
+        let attach_params = AttachParams::default().stdin(true);
+        let (_, output) = self
+            .exec_raw(pod, &command, attach_params, Some(&archive))
+            .await?;
+
+        if output.success() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "[{}] could not be copied to [{}] in pod [{}]: {}",
+                local_path.display(),
+                remote_path,
+                pod.name(),
+                output.stderr
+            ))
+        }
+    }
+
+    /// Copies the given file or directory from the default container of
+    /// the pod to the given local path.
+    ///
+    /// The files are transferred as tar archive like `kubectl cp` does,
+    /// so `tar` must be available in the container.
+    #[cfg(feature = "copy")]
+    pub async fn copy_from_pod(
+        &self,
+        pod: &Pod,
+        remote_path: &str,
+        local_path: &Path,
+    ) -> Result<()> {
+        let (remote_dir, remote_name) = split_remote_path(remote_path)?;
+
+        let command = ["tar", "cf", "-", "-C", remote_dir, remote_name];
+        let (archive, output) = self
+            .exec_raw(pod, &command, AttachParams::default(), None)
+            .await?;
+
+        if output.success() {
+            unpack(&archive, remote_name, local_path)
+        } else {
+            Err(anyhow!(
+                "[{}] could not be copied from pod [{}]: {}",
+                remote_path,
+                pod.name(),
+                output.stderr
+            ))
+        }
+    }
+
+    async fn exec_with_params(
+        &self,
+        pod: &Pod,
+        command: &[&str],
+        attach_params: AttachParams,
+    ) -> Result<ExecOutput> {
+        let (stdout, mut output) = self.exec_raw(pod, command, attach_params, None).await?;
+        output.stdout = String::from_utf8_lossy(&stdout).into_owned();
+        Ok(output)
+    }
+
+    /// Executes the given command, writes the given input to its stdin,
+    /// and returns the raw stdout and the remaining output.
+    ///
+    /// The stdin is kept open until the command terminates because
+    /// closing it terminates the connection.
+    async fn exec_raw(
+        &self,
+        pod: &Pod,
+        command: &[&str],
+        attach_params: AttachParams,
+        input: Option<&[u8]>,
+    ) -> Result<(Vec<u8>, ExecOutput)> {
+        let namespace = pod.namespace().unwrap_or_else(|| self.namespace.clone());
+        let api: Api<Pod> = Api::namespaced(self.client.clone(), &namespace);
+
+        let mut process = api
+            .exec(&pod.name(), command.to_vec(), &attach_params)
+            .await?;
+
+        let mut stdin = process.stdin();
+        let mut stdout = process
+            .stdout()
+            .ok_or_else(|| anyhow!("The stdout of the command is not attached."))?;
+        let mut stderr = process
+            .stderr()
+            .ok_or_else(|| anyhow!("The stderr of the command is not attached."))?;
+
+        let write_input = async {
+            match (stdin.as_mut(), input) {
+                (Some(stdin), Some(input)) => stdin.write_all(input).await,
+                _ => Ok(()),
+            }
+        };
+
+        let mut stdout_buf = Vec::new();
+        let mut stderr_buf = Vec::new();
+        let (input_result, stdout_result, stderr_result, status) = futures::join!(
+            write_input,
+            stdout.read_to_end(&mut stdout_buf),
+            stderr.read_to_end(&mut stderr_buf),
+            process
+        );
+        input_result?;
+        stdout_result?;
+        stderr_result?;
+        drop(stdin);
+
+        Ok((
+            stdout_buf,
+            ExecOutput {
+                stdout: String::new(),
+                stderr: String::from_utf8_lossy(&stderr_buf).into_owned(),
+                exit_code: status.as_ref().and_then(exit_code),
+            },
+        ))
+    }
+
+    /// Acquires the lease with the given name for the given holder and
+    /// awaits the acquisition within the specified timeout.
+    ///
+    /// A lease can be acquired if it does not exist, if it is not held,
+    /// if it is already held by the given holder, or if it expired
+    /// because it was not renewed within its lease duration.
+    pub async fn acquire_lease(
+        &self,
+        name: &str,
+        holder_identity: &str,
+        lease_duration: Duration,
+    ) -> Result<Lease> {
+        let timeout = self.timeouts.acquire_lease;
+        let start = Instant::now();
+
+        while start.elapsed() < timeout {
+            if let Some(lease) = self
+                .try_acquire_lease(name, holder_identity, lease_duration)
+                .await?
+            {
+                return Ok(lease);
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+
+        Err(anyhow!(
+            "Lease [{}] could not be acquired within {} seconds.",
+            name,
+            timeout.as_secs()
+        ))
+    }
+
+    /// Tries to acquire the lease with the given name for the given
+    /// holder and returns `None` if the lease is held by someone else.
+    async fn try_acquire_lease(
+        &self,
+        name: &str,
+        holder_identity: &str,
+        lease_duration: Duration,
+    ) -> Result<Option<Lease>> {
+        let api: Api<Lease> = Api::namespaced(self.client.clone(), &self.namespace);
+
+        let now = MicroTime(Utc::now());
+        let spec = LeaseSpec {
+            holder_identity: Some(holder_identity.to_owned()),
+            lease_duration_seconds: Some(lease_duration.as_secs() as i32),
+            acquire_time: Some(now.clone()),
+            renew_time: Some(now.clone()),
+            ..Default::default()
+        };
+
+        let result = match api.get(name).await {
+            Ok(mut lease) => {
+                if !is_lease_available(&lease, holder_identity, &now) {
+                    return Ok(None);
+                }
+                let lease_transitions = lease
+                    .spec
+                    .as_ref()
+                    .and_then(|spec| spec.lease_transitions)
+                    .unwrap_or_default();
+                lease.spec = Some(LeaseSpec {
+                    lease_transitions: Some(lease_transitions + 1),
+                    ..spec
+                });
+                api.replace(name, &PostParams::default(), &lease).await
+            }
+            Err(kube::Error::Api(error)) if error.code == 404 => {
+                let lease = Lease {
+                    metadata: ObjectMeta {
+                        name: Some(name.to_owned()),
+                        ..Default::default()
+                    },
+                    spec: Some(spec),
+                };
+                api.create(&PostParams::default(), &lease).await
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        match result {
+            Ok(lease) => Ok(Some(lease)),
+            Err(kube::Error::Api(error)) if error.code == 409 => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Renews the lease with the given name if it is still held by the
+    /// given holder and returns false otherwise, e.g. if the lease
+    /// expired and was acquired by another holder in the meantime.
+    pub async fn renew_lease(
+        &self,
+        name: &str,
+        holder_identity: &str,
+        lease_duration: Duration,
+    ) -> Result<bool> {
+        let api: Api<Lease> = Api::namespaced(self.client.clone(), &self.namespace);
+
+        let mut lease = match api.get(name).await {
+            Ok(lease) => lease,
+            Err(kube::Error::Api(error)) if error.code == 404 => return Ok(false),
+            Err(error) => return Err(error.into()),
+        };
+
+        match lease.spec.as_mut() {
+            Some(spec) if spec.holder_identity.as_deref() == Some(holder_identity) => {
+                spec.renew_time = Some(MicroTime(Utc::now()));
+                spec.lease_duration_seconds = Some(lease_duration.as_secs() as i32);
+            }
+            _ => return Ok(false),
+        }
+
+        api.replace(name, &PostParams::default(), &lease).await?;
+        Ok(true)
+    }
+
+    /// Releases the lease with the given name if it is held by the
+    /// given holder.
+    pub async fn release_lease(&self, name: &str, holder_identity: &str) -> Result<()> {
+        let api: Api<Lease> = Api::namespaced(self.client.clone(), &self.namespace);
+
+        let mut lease = api.get(name).await?;
+
+        if let Some(spec) = lease.spec.as_mut() {
+            if spec.holder_identity.as_deref() == Some(holder_identity) {
+                spec.holder_identity = None;
+                spec.acquire_time = None;
+                spec.renew_time = None;
+                api.replace(name, &PostParams::default(), &lease).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sends a request to the given URL path of the API server and
+    /// returns the response.
+    ///
+    /// This is an escape hatch for API surfaces which are not modeled by
+    /// `k8s-openapi` or `kube`, e.g. aggregated APIs, `/version`, or
+    /// `/healthz`. The body is sent as JSON. A response which is not
+    /// valid JSON is returned as string value.
+    pub async fn raw_request(
+        &self,
+        method: Method,
+        url_path: &str,
+        body: Option<&Value>,
+    ) -> Result<Value> {
+        let body = match body {
+            Some(body) => serde_json::to_vec(body)?,
+            None => Vec::new(),
+        };
+
+        let request = http::Request::builder()
+            .method(method)
+            .uri(url_path)
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body(body)?;
+
+        let response = self.client.request_text(request).await?;
+
+        Ok(serde_json::from_str(&response).unwrap_or(Value::String(response)))
+    }
+
+    /// Sends a GET request to the given port and path of the pod or
+    /// service via the proxy of the API server and returns the response
+    /// body.
+    ///
+    /// This allows to call HTTP endpoints of products, e.g. health
+    /// checks or REST APIs, without port forwarding or additional pods.
+    pub async fn proxy_get<K>(&self, resource: &K, port: u16, path: &str) -> Result<String>
+    where
+        K: Resource,
+        <K as Resource>::DynamicType: Default,
+    {
+        let namespace = resource
+            .namespace()
+            .unwrap_or_else(|| self.namespace.clone());
+        let url_path = proxy_url_path(
+            &K::url_path(&Default::default(), Some(&namespace)),
+            &resource.name(),
+            port,
+            path,
+        );
+
+        let request = http::Request::get(url_path).body(Vec::new())?;
+        Ok(self.client.request_text(request).await?)
+    }
+
+    /// Returns the version of the Kubernetes API server, e.g.
+    /// `v1.22.2`.
+    pub async fn get_kubernetes_version(&self) -> Result<String> {
+        Ok(self.client.apiserver_version().await?.git_version)
+    }
+
+    /// Returns true if the given API service, e.g.
+    /// `v1beta1.metrics.k8s.io`, exists and is available.
+    pub async fn is_apiservice_available(&self, name: &str) -> Result<bool> {
+        let api_services: Api<APIService> = Api::all(self.client.clone());
+        match api_services.get(name).await {
+            Ok(api_service) => Ok(is_apiservice_available(&api_service)),
+            Err(kube::Error::Api(error)) if error.code == 404 => Ok(false),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Verifies that the given API service becomes available within the
+    /// specified timeout.
+    ///
+    /// Test suites which depend on aggregated APIs like the metrics
+    /// server can assert their availability upfront instead of failing
+    /// midway with confusing errors.
+    pub async fn verify_apiservice_available(&self, name: &str) -> Result<()> {
+        let timeout_secs = self.timeouts.verify_status.as_secs() as u32;
+        let api_services: Api<APIService> = Api::all(self.client.clone());
+
+        let lp = ListParams::default()
+            .fields(&format!("metadata.name={}", name))
+            .timeout(timeout_secs);
+        let mut stream = api_services
+            .watch(&lp, self.list_defaults.resource_version.as_str())
+            .await?
+            .boxed();
+
+        if self.is_apiservice_available(name).await? {
+            return Ok(());
+        }
+
+        while let Some(event) = stream.try_next().await? {
+            if let WatchEvent::Added(api_service) | WatchEvent::Modified(api_service) = event {
+                if is_apiservice_available(&api_service) {
+                    return Ok(());
+                }
+            }
+        }
+
+        Err(anyhow!(
+            "API service [{}] did not become available within {} seconds.",
+            name,
+            timeout_secs
+        ))
+    }
+
+    /// Returns the resource usage of the given pod reported by the
+    /// metrics-server.
+    ///
+    /// The metrics are only available if the API service
+    /// `v1beta1.metrics.k8s.io` is available and the pod ran at least
+    /// for one scrape interval.
+    pub async fn get_pod_metrics(&self, pod_name: &str) -> Result<PodMetrics> {
+        let request = Request::new(format!(
+            "{}/namespaces/{}/pods",
+            METRICS_API_PATH, self.namespace
+        ))
+        .get(pod_name)?;
+        Ok(self.client.request(request).await?)
+    }
+
+    /// Returns the resource usage of the pods matching the label selector
+    /// reported by the metrics-server.
+    pub async fn list_pod_metrics(&self, label_selector: &str) -> Result<Vec<PodMetrics>> {
+        let request = Request::new(format!(
+            "{}/namespaces/{}/pods",
+            METRICS_API_PATH, self.namespace
+        ))
+        .list(&self.list_defaults.list_params(label_selector))?;
+        let pod_metrics: ObjectList<PodMetrics> = self.client.request(request).await?;
+        Ok(pod_metrics.items)
+    }
+
+    /// Returns the resource usage of the given node reported by the
+    /// metrics-server.
+    pub async fn get_node_metrics(&self, node_name: &str) -> Result<NodeMetrics> {
+        let request = Request::new(format!("{}/nodes", METRICS_API_PATH)).get(node_name)?;
+        Ok(self.client.request(request).await?)
+    }
+
+    /// Returns the CPU usage of all containers of the given pod in
+    /// millicores.
+    pub async fn get_pod_cpu_usage(&self, pod_name: &str) -> Result<u64> {
+        self.get_pod_metrics(pod_name).await?.cpu_millis()
+    }
+
+    /// Returns the memory usage of all containers of the given pod in
+    /// bytes.
+    pub async fn get_pod_memory_usage(&self, pod_name: &str) -> Result<u64> {
+        self.get_pod_metrics(pod_name).await?.memory_bytes()
+    }
+
+    /// Returns the logs for the given pod.
+    pub async fn get_logs(&self, pod: &Pod, params: &LogParams) -> Result<Vec<String>> {
+        let namespace = pod.namespace().unwrap_or_else(|| self.namespace.clone());
+        let pods: Api<Pod> = Api::namespaced(self.client.clone(), &namespace);
+
+        let bytes = pods
+            .log_stream(&pod.name(), params)
             .await?
             .try_collect::<Vec<_>>()
             .await?
             .concat();
 
-        let lines = String::from_utf8_lossy(&bytes)
-            .lines()
-            .map(|line| line.to_owned())
-            .collect();
+        let lines = String::from_utf8_lossy(&bytes)
+            .lines()
+            .map(|line| line.to_owned())
+            .collect();
+
+        Ok(lines)
+    }
+
+    /// Returns the logs of the given container of the pod.
+    ///
+    /// In contrast to [`KubeClient::get_logs`] with default parameters,
+    /// this also works for pods with several containers, e.g. a product
+    /// container and a log agent.
+    pub async fn get_container_logs(&self, pod: &Pod, container: &str) -> Result<Vec<String>> {
+        let params = LogParams {
+            container: Some(container.to_owned()),
+            ..Default::default()
+        };
+        self.get_logs(pod, &params).await
+    }
+
+    /// Returns the logs of all init containers and containers of the
+    /// given pod by container name.
+    pub async fn get_all_container_logs(&self, pod: &Pod) -> Result<BTreeMap<String, Vec<String>>> {
+        let mut logs = BTreeMap::new();
+        for container in container_names(pod) {
+            let lines = self.get_container_logs(pod, &container).await?;
+            logs.insert(container, lines);
+        }
+        Ok(logs)
+    }
+
+    /// Returns the logs of the previous instance of the given container.
+    ///
+    /// If a container crash-loops then the reason is usually logged by
+    /// the previous instance.
+    pub async fn get_previous_logs(&self, pod: &Pod, container: &str) -> Result<Vec<String>> {
+        let params = LogParams {
+            container: Some(container.to_owned()),
+            previous: true,
+            ..Default::default()
+        };
+        self.get_logs(pod, &params).await
+    }
+
+    /// Returns the last lines of the logs of the given container.
+    ///
+    /// Products like NiFi or HBase log a lot, so retrieving only the end
+    /// of the logs keeps the memory usage of the test process low.
+    pub async fn get_tail_logs(
+        &self,
+        pod: &Pod,
+        container: &str,
+        tail_lines: i64,
+    ) -> Result<Vec<String>> {
+        let params = LogParams {
+            container: Some(container.to_owned()),
+            tail_lines: Some(tail_lines),
+            ..Default::default()
+        };
+        self.get_logs(pod, &params).await
+    }
+
+    /// Returns the lines which the given container logged within the
+    /// given duration before now.
+    ///
+    /// The duration is rounded up to whole seconds.
+    pub async fn get_recent_logs(
+        &self,
+        pod: &Pod,
+        container: &str,
+        since: Duration,
+    ) -> Result<Vec<String>> {
+        let params = LogParams {
+            container: Some(container.to_owned()),
+            since_seconds: Some(secs_rounded_up(since) as i64),
+            ..Default::default()
+        };
+        self.get_logs(pod, &params).await
+    }
+
+    /// Returns the logs of the given container up to the given number
+    /// of bytes.
+    ///
+    /// The last line can be incomplete because the API server cuts the
+    /// logs at the byte limit.
+    pub async fn get_limited_logs(
+        &self,
+        pod: &Pod,
+        container: &str,
+        limit_bytes: i64,
+    ) -> Result<Vec<String>> {
+        let params = LogParams {
+            container: Some(container.to_owned()),
+            limit_bytes: Some(limit_bytes),
+            ..Default::default()
+        };
+        self.get_logs(pod, &params).await
+    }
+
+    /// Streams the logs for the given pod into the given file and returns
+    /// the number of written bytes.
+    ///
+    /// In contrast to [`KubeClient::get_logs`], the logs are not kept in
+    /// memory, so that also the complete logs of chatty products can be
+    /// archived. An existing file is overwritten. The files are meant to
+    /// be archived, so credentials in the lines are redacted with
+    /// [`redact_text`].
+    pub async fn write_logs(&self, pod: &Pod, params: &LogParams, path: &Path) -> Result<u64> {
+        let namespace = pod.namespace().unwrap_or_else(|| self.namespace.clone());
+        let pods: Api<Pod> = Api::namespaced(self.client.clone(), &namespace);
+        let mut stream = pods.log_stream(&pod.name(), params).await?.boxed();
+
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        let mut file = BufWriter::new(File::create(path)?);
+        let mut buffer = Vec::new();
+        let mut written = 0;
+        while let Some(chunk) = stream.try_next().await? {
+            buffer.extend_from_slice(&chunk);
+            for line in take_lines(&mut buffer) {
+                let line = format!("{}\n", redact_text(&line));
+                file.write_all(line.as_bytes())?;
+                written += line.len() as u64;
+            }
+        }
+        if !buffer.is_empty() {
+            let last_line = redact_text(&String::from_utf8_lossy(&buffer));
+            file.write_all(last_line.as_bytes())?;
+            written += last_line.len() as u64;
+        }
+        file.flush()?;
+
+        Ok(written)
+    }
+
+    /// Follows the logs of the given pod until a line matches the given
+    /// regular expression within the specified timeout and returns this
+    /// line.
+    ///
+    /// Many products signal their readiness only with a log line like
+    /// `Started Admin server`. A plain substring can be matched by
+    /// escaping it with [`regex::escape`]. Lines which were logged
+    /// before the call are also considered.
+    pub async fn wait_for_log_line(&self, pod: &Pod, pattern: &str) -> Result<String> {
+        let regex = Regex::new(pattern)?;
+        let timeout = self.timeouts.wait_for_log_line;
+        let namespace = pod.namespace().unwrap_or_else(|| self.namespace.clone());
+        let pods: Api<Pod> = Api::namespaced(self.client.clone(), &namespace);
+
+        let params = LogParams {
+            follow: true,
+            ..Default::default()
+        };
+
+        let find_line = async {
+            let mut stream = pods.log_stream(&pod.name(), &params).await?.boxed();
+            let mut buffer = Vec::new();
+
+            while let Some(chunk) = stream.try_next().await? {
+                buffer.extend_from_slice(&chunk);
+                if let Some(line) = take_lines(&mut buffer)
+                    .into_iter()
+                    .find(|line| regex.is_match(line))
+                {
+                    return Ok(Some(line));
+                }
+            }
+
+            let last_line = String::from_utf8_lossy(&buffer).into_owned();
+            Ok::<_, anyhow::Error>(Some(last_line).filter(|line| regex.is_match(line)))
+        };
+
+        match tokio::time::timeout(timeout, find_line).await {
+            Ok(Ok(Some(line))) => Ok(line),
+            Ok(Ok(None)) => Err(anyhow!(
+                "The logs of pod [{}] ended without a line matching [{}].",
+                pod.name(),
+                pattern
+            )),
+            Ok(Err(error)) => Err(error),
+            Err(_) => Err(anyhow!(
+                "No line matching [{}] appeared in the logs of pod [{}] within {} seconds.",
+                pattern,
+                pod.name(),
+                timeout.as_secs()
+            )),
+        }
+    }
+}
+
+/// Verifies that the entries with the given keys of the parent
+/// resource are contained in all children.
+///
+/// `get_map` returns either the labels or the annotations of a resource
+/// and `kind` names them in error messages.
+fn verify_propagated<P, C>(
+    kind: &str,
+    parent: &P,
+    children: &[C],
+    get_map: fn(&ObjectMeta) -> Option<&BTreeMap<String, String>>,
+    keys: &[&str],
+) -> Result<()>
+where
+    P: Resource,
+    C: Resource,
+{
+    if children.is_empty() {
+        return Err(anyhow!(
+            "No child resources of [{}] found to verify the propagation.",
+            parent.name()
+        ));
+    }
+
+    for key in keys {
+        let expected_value = get_map(parent.meta())
+            .and_then(|map| map.get(*key))
+            .ok_or_else(|| anyhow!("{} [{}] is not set on [{}].", kind, key, parent.name()))?;
+
+        for child in children {
+            let actual_value = get_map(child.meta()).and_then(|map| map.get(*key));
+            if actual_value != Some(expected_value) {
+                return Err(anyhow!(
+                    "{} [{}] of [{}] has the value [{:?}] but [{}] was expected.",
+                    kind,
+                    key,
+                    child.name(),
+                    actual_value,
+                    expected_value
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns true if the given lease is not held, already held by the
+/// given holder, or expired.
+/// Returns the given duration in whole seconds rounded up, but at least
+/// one second, e.g. for the `timeoutSeconds` of a watch which would be
+/// unlimited if it was zero.
+fn secs_rounded_up(duration: Duration) -> u64 {
+    let secs = duration.as_secs() + u64::from(duration.subsec_nanos() > 0);
+    secs.max(1)
+}
+
+fn is_lease_available(lease: &Lease, holder_identity: &str, now: &MicroTime) -> bool {
+    let spec = match &lease.spec {
+        Some(spec) => spec,
+        None => return true,
+    };
+
+    match spec.holder_identity.as_deref() {
+        None | Some("") => true,
+        Some(holder) if holder == holder_identity => true,
+        Some(_) => match (&spec.renew_time, spec.lease_duration_seconds) {
+            (Some(renew_time), Some(lease_duration_seconds)) => {
+                renew_time.0 + chrono::Duration::seconds(lease_duration_seconds.into()) < now.0
+            }
+            _ => true,
+        },
+    }
+}
+
+/// Deserializes the given JSON value into the desired type.
+pub fn from_value<T>(value: Value) -> T
+where
+    T: DeserializeOwned,
+{
+    T::deserialize(value).expect("Deserialization failed")
+}
+
+/// Deserializes the given YAML text into the desired type.
+pub fn from_yaml<T>(yaml: &str) -> T
+where
+    T: DeserializeOwned,
+{
+    serde_yaml::from_str(yaml).expect("String is not a well-formed YAML")
+}
+
+/// Appends a UUID to `metadata/name`.
+pub fn with_unique_name(yaml: &str) -> String {
+    let mut spec: serde_yaml::Value = from_yaml(yaml);
+    let name = &mut spec["metadata"]["name"];
+    *name = format!(
+        "{}-{}",
+        name.as_str().expect("metadata/name is invalid"),
+        Uuid::new_v4()
+    )
+    .into();
+    serde_yaml::to_string(&spec).unwrap()
+}
+
+/// Output of a command executed in a container
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExecOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// Exit code of the command or `None` if the API server did not
+    /// report it
+    pub exit_code: Option<i32>,
+}
+
+impl ExecOutput {
+    /// Returns true if the command exited with the code 0.
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
+
+/// Returns the exit code contained in the status of an executed
+/// command.
+fn exit_code(status: &Status) -> Option<i32> {
+    if status.status.as_deref() == Some("Success") {
+        return Some(0);
+    }
+
+    status
+        .details
+        .as_ref()
+        .and_then(|details| details.causes.as_ref())
+        .and_then(|causes| {
+            causes
+                .iter()
+                .find(|cause| cause.reason.as_deref() == Some("ExitCode"))
+        })
+        .and_then(|cause| cause.message.as_ref())
+        .and_then(|message| message.parse().ok())
+}
+
+/// Returns the URL path of the proxy subresource for the given port and
+/// path of the resource with the given name.
+fn proxy_url_path(resource_url_path: &str, name: &str, port: u16, path: &str) -> String {
+    format!(
+        "{}/{}:{}/proxy/{}",
+        resource_url_path,
+        name,
+        port,
+        path.trim_start_matches('/')
+    )
+}
+
+/// Returns the names of the init containers and containers of the given
+/// pod.
+fn container_names(pod: &Pod) -> Vec<String> {
+    pod.spec
+        .iter()
+        .flat_map(|spec| {
+            spec.init_containers
+                .iter()
+                .flatten()
+                .chain(&spec.containers)
+        })
+        .map(|container| container.name.clone())
+        .collect()
+}
+
+/// Parses the given content in the format of Java properties files.
+///
+/// Keys and values are separated by `=` or `:`. Empty lines and
+/// comments starting with `#` or `!` are ignored. Line continuations
+/// and escape sequences are not supported.
+pub fn parse_properties(content: &str) -> BTreeMap<String, String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('!'))
+        .map(|line| match line.find(&['=', ':'][..]) {
+            Some(index) => (
+                line[..index].trim().to_string(),
+                line[index + 1..].trim().to_string(),
+            ),
+            None => (line.to_string(), String::new()),
+        })
+        .collect()
+}
+
+/// Returns the command, the arguments, and the values of the
+/// environment variables of the given container split into single
+/// arguments, or an empty list if the pod has no such container.
+///
+/// Scripts like `bash -c "java -Xmx2g ..."` are split as well, so that
+/// the arguments passed to a process inside the script can be checked.
+pub fn get_container_arguments(pod: &Pod, container_name: &str) -> Vec<String> {
+    pod.spec
+        .iter()
+        .flat_map(|spec| &spec.containers)
+        .filter(|container| container.name == container_name)
+        .flat_map(|container| {
+            let env_values = container
+                .env
+                .iter()
+                .flatten()
+                .filter_map(|env_var| env_var.value.as_deref());
+            container
+                .command
+                .iter()
+                .chain(container.args.iter())
+                .flatten()
+                .map(String::as_str)
+                .chain(env_values)
+        })
+        .flat_map(split_arguments)
+        .collect()
+}
+
+/// Splits the given command line at whitespace and removes the quotes
+/// around the arguments.
+pub fn split_arguments(command_line: &str) -> Vec<String> {
+    command_line
+        .split_whitespace()
+        .map(|argument| argument.trim_matches(|c| c == '"' || c == '\'').to_string())
+        .filter(|argument| !argument.is_empty())
+        .collect()
+}
+
+/// Parses the given output of the `env` command into the environment
+/// variables and their values.
+///
+/// Lines without `=`, e.g. continuations of values which contain line
+/// breaks, are ignored.
+pub fn parse_env(output: &str) -> BTreeMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Returns the data of the given secret with the values decoded as
+/// UTF-8.
+///
+/// Invalid UTF-8 sequences are replaced with the replacement
+/// character.
+fn decode_secret_data(secret: &Secret) -> BTreeMap<String, String> {
+    secret
+        .data
+        .iter()
+        .flatten()
+        .map(|(key, value)| (key.clone(), String::from_utf8_lossy(&value.0).into_owned()))
+        .collect()
+}
+
+/// Describes the given Warning events which involve one of the given
+/// resources and which were created or updated since the events
+/// `before` were listed.
+fn new_warning_events(
+    before: &[Event],
+    after: Vec<Event>,
+    resources: &BTreeSet<ResourceId>,
+) -> Vec<String> {
+    let known_versions = before
+        .iter()
+        .map(|event| (event.uid(), event.resource_version()))
+        .collect::<BTreeSet<_>>();
+
+    after
+        .into_iter()
+        .filter(|event| !known_versions.contains(&(event.uid(), event.resource_version())))
+        .filter_map(|event| {
+            let Event {
+                involved_object,
+                reason,
+                message,
+                ..
+            } = event;
+            let id = ResourceId {
+                kind: involved_object.kind.unwrap_or_default(),
+                namespace: involved_object.namespace,
+                name: involved_object.name.unwrap_or_default(),
+            };
+            resources.contains(&id).then(|| {
+                format!(
+                    "{}: {}: {}",
+                    id,
+                    reason.unwrap_or_default(),
+                    message.unwrap_or_default()
+                )
+            })
+        })
+        .collect()
+}
+
+/// Adds the pods which match the list parameters and their owners to
+/// the given set until the task is aborted.
+async fn observe_pods(
+    api: Api<Pod>,
+    list_params: ListParams,
+    observed: Arc<Mutex<BTreeSet<ResourceId>>>,
+) -> Result<()> {
+    loop {
+        let mut stream = api.watch(&list_params, "0").await?.boxed();
+        while let Some(event) = stream.try_next().await? {
+            if let WatchEvent::Added(pod) | WatchEvent::Modified(pod) | WatchEvent::Deleted(pod) =
+                event
+            {
+                let mut observed = observed.lock().unwrap();
+                observed.insert(ResourceId {
+                    kind: String::from("Pod"),
+                    namespace: pod.namespace(),
+                    name: pod.name(),
+                });
+                observed.extend(owner_ids(&pod));
+            }
+        }
+    }
+}
+
+/// Returns the IDs of the owners of the given resource.
+fn owner_ids<K>(resource: &K) -> Vec<ResourceId>
+where
+    K: Resource,
+{
+    resource
+        .owner_references()
+        .iter()
+        .map(|owner| ResourceId {
+            kind: owner.kind.clone(),
+            namespace: resource.namespace(),
+            name: owner.name.clone(),
+        })
+        .collect()
+}
+
+/// Loads the configuration from the kubeconfig file and context given by
+/// `KUBE_TEST_KUBECONFIG` and `KUBE_TEST_CONTEXT` or infers it from the
+/// environment if both are not set.
+async fn infer_config() -> Result<Config> {
+    let path = env::var(KUBECONFIG_ENV_VAR).ok();
+    let context = env::var(CONTEXT_ENV_VAR).ok();
+    if path.is_some() || context.is_some() {
+        load_kubeconfig(path.as_deref().map(Path::new), context.as_deref()).await
+    } else {
+        Ok(Config::infer().await?)
+    }
+}
+
+/// Loads the configuration from the given context of the given
+/// kubeconfig file which default to the current context and to the file
+/// given by `KUBECONFIG` or `~/.kube/config`.
+async fn load_kubeconfig(path: Option<&Path>, context: Option<&str>) -> Result<Config> {
+    let options = KubeConfigOptions {
+        context: context.map(String::from),
+        ..Default::default()
+    };
+    let config = match path {
+        Some(path) => {
+            Config::from_custom_kubeconfig(Kubeconfig::read_from(path)?, &options).await?
+        }
+        None => Config::from_kubeconfig(&options).await?,
+    };
+    Ok(config)
+}
+
+/// Returns the connector for the API server with the TLS backend of the
+/// enabled feature.
+#[cfg(feature = "native-tls")]
+fn https_connector(config: &Config) -> Result<impl Connect + Clone + Send + Sync + 'static> {
+    Ok(config.native_tls_https_connector()?)
+}
+
+/// Returns the connector for the API server with the TLS backend of the
+/// enabled feature.
+#[cfg(all(not(feature = "native-tls"), feature = "rustls-tls"))]
+fn https_connector(config: &Config) -> Result<impl Connect + Clone + Send + Sync + 'static> {
+    Ok(config.rustls_https_connector()?)
+}
+
+/// Builds a client for the given configuration which adds the given
+/// headers to every request.
+fn client_with_headers(config: &Config, headers: HeaderMap) -> Result<Client> {
+    let service = ServiceBuilder::new()
+        .layer(config.base_uri_layer())
+        .option_layer(config.auth_layer()?)
+        .map_request(move |mut request: http::Request<hyper::Body>| {
+            request.headers_mut().extend(headers.clone());
+            request
+        })
+        .service(hyper::Client::builder().build(https_connector(config)?));
+
+    Ok(Client::new(service, config.default_namespace.clone()))
+}
+
+/// Returns the cluster settings of the given configuration without the
+/// credentials, i.e. without the client certificate and the
+/// authentication info, which would otherwise take precedence over a
+/// bearer token.
+fn without_credentials(config: &Config) -> Config {
+    let mut cluster_config = Config::new(config.cluster_url.clone());
+    cluster_config.default_namespace = config.default_namespace.clone();
+    cluster_config.root_cert = config.root_cert.clone();
+    cluster_config.timeout = config.timeout;
+    cluster_config.accept_invalid_certs = config.accept_invalid_certs;
+    cluster_config.proxy_url = config.proxy_url.clone();
+    cluster_config
+}
+
+/// Returns the user name and the groups of the given ServiceAccount as
+/// they are authenticated by the API server, e.g. to review its access
+/// with [`KubeClient::can_i_as`].
+pub fn service_account_subject(namespace: &str, name: &str) -> (String, Vec<String>) {
+    (
+        format!("system:serviceaccount:{}:{}", namespace, name),
+        vec![
+            String::from("system:serviceaccounts"),
+            format!("system:serviceaccounts:{}", namespace),
+            String::from("system:authenticated"),
+        ],
+    )
+}
+
+/// Returns the attributes of an access review for the given verb and
+/// resource in the kubectl notation `resource[.group][/subresource]`.
+fn resource_attributes(verb: &str, resource: &str, namespace: Option<&str>) -> ResourceAttributes {
+    let (resource, subresource) = match resource.split_once('/') {
+        Some((resource, subresource)) => (resource, Some(subresource.to_string())),
+        None => (resource, None),
+    };
+    let (resource, group) = match resource.split_once('.') {
+        Some((resource, group)) => (resource, group),
+        None => (resource, ""),
+    };
+
+    ResourceAttributes {
+        verb: Some(verb.to_string()),
+        group: Some(group.to_string()),
+        resource: Some(resource.to_string()),
+        subresource,
+        namespace: namespace.map(String::from),
+        ..Default::default()
+    }
+}
+
+/// Returns true if the reviewed access is allowed.
+///
+/// An evaluation error, e.g. of an unavailable authorization webhook,
+/// is logged because the access is denied without a policy decision.
+fn is_access_allowed(status: Option<SubjectAccessReviewStatus>) -> Result<bool> {
+    let status = status.ok_or_else(|| anyhow!("The access review has no status."))?;
+    if let Some(error) = &status.evaluation_error {
+        if !status.allowed {
+            eprintln!("The access could not be fully reviewed: {}", error);
+        }
+    }
+    Ok(status.allowed)
+}
+
+/// Returns the headers which instruct the API server to impersonate the
+/// given user and groups.
+fn impersonation_headers(user: &str, groups: &[&str]) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    headers.insert("Impersonate-User", HeaderValue::from_str(user)?);
+    for group in groups {
+        headers.append("Impersonate-Group", HeaderValue::from_str(group)?);
+    }
+    Ok(headers)
+}
+
+/// Returns true if the given event has the given reason and contains
+/// the given substring in its message.
+fn is_event_matching(event: &Event, reason: &str, message_substring: &str) -> bool {
+    event.reason.as_deref() == Some(reason)
+        && event
+            .message
+            .as_deref()
+            .unwrap_or_default()
+            .contains(message_substring)
+}
+
+/// Describes the reasons and messages of the given events or returns
+/// `none` if there are no events.
+fn describe_events(events: &[Event]) -> String {
+    if events.is_empty() {
+        String::from("none")
+    } else {
+        events
+            .iter()
+            .map(|event| {
+                format!(
+                    "[{}: {}]",
+                    event.reason.as_deref().unwrap_or_default(),
+                    event.message.as_deref().unwrap_or_default()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Returns true if the given pod is pending and not scheduled yet.
+fn is_unscheduled(pod: &Pod) -> bool {
+    let is_pending = pod
+        .status
+        .as_ref()
+        .and_then(|status| status.phase.as_deref())
+        .map_or(true, |phase| phase == "Pending");
+    let is_scheduled = get_pod_conditions(pod)
+        .iter()
+        .any(|condition| condition.type_ == "PodScheduled" && condition.status == "True");
+    is_pending && !is_scheduled
+}
+
+/// Returns the message of the latest `FailedScheduling` event of the
+/// given pod or, if there is none, the message of its `PodScheduled`
+/// condition, or `None` if the pod is scheduled.
+fn scheduling_failure(pod: &Pod, events: &[Event]) -> Option<String> {
+    if !is_unscheduled(pod) {
+        return None;
+    }
+
+    events
+        .iter()
+        .filter(|event| event.reason.as_deref() == Some("FailedScheduling"))
+        .max_by_key(|event| last_observed_time(event))
+        .and_then(|event| event.message.clone())
+        .or_else(|| {
+            get_pod_conditions(pod)
+                .into_iter()
+                .find(|condition| condition.type_ == "PodScheduled")
+                .and_then(|condition| condition.message)
+        })
+}
+
+/// Returns the time when the given event occurred for the last time.
+///
+/// Events which were created with the `events.k8s.io` API, like the
+/// ones of the scheduler, have no `lastTimestamp` but an `eventTime`
+/// and, if they recurred, a `series.lastObservedTime`.
+fn last_observed_time(event: &Event) -> Option<DateTime<Utc>> {
+    event
+        .last_timestamp
+        .as_ref()
+        .map(|time| time.0)
+        .or_else(|| {
+            event
+                .series
+                .as_ref()
+                .and_then(|series| series.last_observed_time.as_ref())
+                .map(|time| time.0)
+        })
+        .or_else(|| event.event_time.as_ref().map(|time| time.0))
+}
+
+/// Removes all complete lines from the given buffer and returns them
+/// without line endings.
+fn take_lines(buffer: &mut Vec<u8>) -> Vec<String> {
+    match buffer.iter().rposition(|byte| *byte == b'\n') {
+        Some(position) => String::from_utf8_lossy(&buffer.drain(..=position).collect::<Vec<_>>())
+            .lines()
+            .map(|line| line.to_owned())
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Adds a readiness gate with the given condition type to the pod
+/// specification.
+///
+/// The YAML specification can either describe a pod or a resource with
+/// a pod template like a StatefulSet. The pod does not become ready
+/// until the condition is set, e.g. with
+/// [`TestKubeClient::set_pod_condition`].
+pub fn with_readiness_gate(yaml: &str, condition_type: &str) -> String {
+    let mut spec: serde_yaml::Value = from_yaml(yaml);
+    let pod_spec = pod_spec_mut(&mut spec);
+
+    let readiness_gate = serde_yaml::to_value(json!({ "conditionType": condition_type }))
+        .expect("Readiness gate could not be serialized");
 
-        Ok(lines)
+    match pod_spec["readinessGates"].as_sequence_mut() {
+        Some(readiness_gates) => readiness_gates.push(readiness_gate),
+        None => pod_spec["readinessGates"] = vec![readiness_gate].into(),
     }
-}
 
-/// Deserializes the given JSON value into the desired type.
-pub fn from_value<T>(value: Value) -> T
-where
-    T: DeserializeOwned,
-{
-    T::deserialize(value).expect("Deserialization failed")
+    serde_yaml::to_string(&spec).unwrap()
 }
 
-/// Deserializes the given YAML text into the desired type.
-pub fn from_yaml<T>(yaml: &str) -> T
-where
-    T: DeserializeOwned,
-{
-    serde_yaml::from_str(yaml).expect("String is not a well-formed YAML")
+/// Adds host aliases to the pod specification so that the given host
+/// names resolve to the given IP addresses inside the pod.
+///
+/// The YAML specification can either describe a pod or a resource with
+/// a pod template like a StatefulSet. Host names like the hosts of an
+/// external Kerberos realm or an S3 endpoint can be simulated this way
+/// without a DNS setup.
+pub fn with_host_aliases(yaml: &str, host_aliases: &[(&str, &[&str])]) -> String {
+    let mut spec: serde_yaml::Value = from_yaml(yaml);
+    let pod_spec = pod_spec_mut(&mut spec);
+
+    for (ip, hostnames) in host_aliases {
+        let host_alias = serde_yaml::to_value(json!({ "ip": ip, "hostnames": hostnames }))
+            .expect("Host alias could not be serialized");
+
+        match pod_spec["hostAliases"].as_sequence_mut() {
+            Some(aliases) => aliases.push(host_alias),
+            None => pod_spec["hostAliases"] = vec![host_alias].into(),
+        }
+    }
+
+    serde_yaml::to_string(&spec).unwrap()
 }
 
-/// Appends a UUID to `metadata/name`.
-pub fn with_unique_name(yaml: &str) -> String {
+/// Adds annotations to the pod metadata which disable the sidecar
+/// injection of common service meshes.
+///
+/// The YAML specification can either describe a pod or a resource with
+/// a pod template like a StatefulSet. Fixture pods which must terminate
+/// should not get a sidecar which keeps them running.
+pub fn without_sidecar_injection(yaml: &str) -> String {
     let mut spec: serde_yaml::Value = from_yaml(yaml);
-    let name = &mut spec["metadata"]["name"];
-    *name = format!(
-        "{}-{}",
-        name.as_str().expect("metadata/name is invalid"),
-        Uuid::new_v4()
-    )
-    .into();
+
+    let pod_metadata = if spec["spec"]["template"].is_mapping() {
+        &mut spec["spec"]["template"]["metadata"]
+    } else {
+        &mut spec["metadata"]
+    };
+
+    for (key, value) in SIDECAR_INJECTION_DISABLED {
+        pod_metadata["annotations"][*key] = (*value).into();
+    }
+
     serde_yaml::to_string(&spec).unwrap()
 }
 
+/// Returns the pod specification of a pod or of the pod template of a
+/// resource like a StatefulSet.
+fn pod_spec_mut(spec: &mut serde_yaml::Value) -> &mut serde_yaml::Value {
+    if spec["spec"]["template"].is_mapping() {
+        &mut spec["spec"]["template"]["spec"]
+    } else {
+        &mut spec["spec"]
+    }
+}
+
 /// Returns the conditions of the given node.
 pub fn get_node_conditions(node: &Node) -> Vec<NodeCondition> {
     if let Some(status) = &node.status {
@@ -585,6 +4664,95 @@ pub fn get_crd_conditions(
     }
 }
 
+/// Returns true if all given conditions of the custom resource
+/// definition are true.
+fn has_crd_conditions(crd: &CustomResourceDefinition, condition_types: &[String]) -> bool {
+    let conditions = get_crd_conditions(crd);
+    condition_types.iter().all(|condition_type| {
+        conditions
+            .iter()
+            .any(|condition| &condition.type_ == condition_type && condition.status == "True")
+    })
+}
+
+/// Returns the API resource of the custom resources of the given
+/// definition in their storage version.
+fn crd_api_resource(crd: &CustomResourceDefinition) -> ApiResource {
+    let group = crd.spec.group.clone();
+    let version = crd
+        .spec
+        .versions
+        .iter()
+        .find(|version| version.storage)
+        .or_else(|| crd.spec.versions.first())
+        .map(|version| version.name.clone())
+        .unwrap_or_default();
+    ApiResource {
+        api_version: format!("{}/{}", group, version),
+        group,
+        version,
+        kind: crd.spec.names.kind.clone(),
+        plural: crd.spec.names.plural.clone(),
+    }
+}
+
+/// Returns the conditions of the given API service.
+pub fn get_apiservice_conditions(api_service: &APIService) -> Vec<APIServiceCondition> {
+    if let Some(status) = &api_service.status {
+        status.conditions.clone().unwrap_or_default()
+    } else {
+        vec![]
+    }
+}
+
+/// Returns true if the given resource has a true condition of the given
+/// type which transitioned not before `since`.
+fn has_condition_transitioned(resource: &Value, type_: &str, since: &Time) -> bool {
+    let since = since.0.trunc_subsecs(0);
+
+    resource
+        .pointer("/status/conditions")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter(|condition| condition["type"] == type_ && condition["status"] == "True")
+        .filter_map(|condition| condition["lastTransitionTime"].as_str())
+        .filter_map(|time| DateTime::parse_from_rfc3339(time).ok())
+        .any(|time| time >= since)
+}
+
+/// Returns true if the resource type with the given plural name is
+/// namespaced according to the given API discovery.
+fn is_namespaced(api_resources: &APIResourceList, plural: &str) -> Result<bool> {
+    api_resources
+        .resources
+        .iter()
+        .find(|api_resource| api_resource.name == plural)
+        .map(|api_resource| api_resource.namespaced)
+        .ok_or_else(|| {
+            anyhow!(
+                "Resource type [{}] is not served by the API server in [{}].",
+                plural,
+                api_resources.group_version
+            )
+        })
+}
+
+/// Returns the field at the given JSON pointer relative to the status of
+/// the given resource.
+fn status_field<'a>(resource: &'a Value, json_pointer: &str) -> Option<&'a Value> {
+    resource
+        .get("status")
+        .and_then(|status| status.pointer(json_pointer))
+}
+
+/// Returns true if the given API service has the condition `Available`.
+fn is_apiservice_available(api_service: &APIService) -> bool {
+    get_apiservice_conditions(api_service)
+        .iter()
+        .any(|condition| condition.type_ == "Available" && condition.status == "True")
+}
+
 /// Returns the taints of the given node.
 pub fn get_node_taints(node: &Node) -> Vec<Taint> {
     if let Some(spec) = &node.spec {
@@ -594,6 +4762,104 @@ pub fn get_node_taints(node: &Node) -> Vec<Taint> {
     }
 }
 
+/// Returns the pods on the given node which are evicted by a drain,
+/// i.e. all pods which are not managed by a DaemonSet, are not mirror
+/// pods, and are not terminated.
+fn drainable_pods(pods: &[Pod], node_name: &str) -> Vec<Pod> {
+    pods.iter()
+        .filter(|pod| {
+            pod.spec.as_ref().and_then(|spec| spec.node_name.as_deref()) == Some(node_name)
+        })
+        .filter(|pod| {
+            !pod.owner_references()
+                .iter()
+                .any(|owner| owner.kind == "DaemonSet")
+        })
+        .filter(|pod| {
+            !pod.annotations()
+                .contains_key("kubernetes.io/config.mirror")
+        })
+        .filter(|pod| {
+            let phase = pod
+                .status
+                .as_ref()
+                .and_then(|status| status.phase.as_deref());
+            phase != Some("Succeeded") && phase != Some("Failed")
+        })
+        .cloned()
+        .collect()
+}
+
+/// Returns true if the evicted pods are gone and each controller of an
+/// evicted pod has at least as many ready pods on other nodes as it had
+/// pods before the drain.
+fn is_rescheduled(
+    evicted_pods: &[Pod],
+    pods_before: &[Pod],
+    pods_after: &[Pod],
+    node_name: &str,
+) -> bool {
+    let controller_uid = |pod: &Pod| {
+        pod.owner_references()
+            .iter()
+            .find(|owner| owner.controller == Some(true))
+            .map(|owner| owner.uid.clone())
+    };
+    let is_ready_elsewhere = |pod: &Pod| {
+        pod.spec.as_ref().and_then(|spec| spec.node_name.as_deref()) != Some(node_name)
+            && get_pod_conditions(pod)
+                .iter()
+                .any(|condition| condition.type_ == "Ready" && condition.status == "True")
+    };
+
+    let evicted_uids = evicted_pods
+        .iter()
+        .filter_map(|pod| pod.uid())
+        .collect::<BTreeSet<_>>();
+    if pods_after
+        .iter()
+        .filter_map(|pod| pod.uid())
+        .any(|uid| evicted_uids.contains(&uid))
+    {
+        return false;
+    }
+
+    evicted_pods
+        .iter()
+        .filter_map(controller_uid)
+        .collect::<BTreeSet<_>>()
+        .iter()
+        .all(|controller| {
+            let is_owned = |pod: &&Pod| controller_uid(pod).as_ref() == Some(controller);
+            let expected = pods_before.iter().filter(is_owned).count();
+            let rescheduled = pods_after
+                .iter()
+                .filter(is_owned)
+                .filter(|pod| is_ready_elsewhere(pod))
+                .count();
+            rescheduled >= expected
+        })
+}
+
+/// Returns true if the given map contains all entries with a value and
+/// none of the entries without a value.
+fn has_entries(
+    map: Option<&BTreeMap<String, String>>,
+    entries: &BTreeMap<String, Option<String>>,
+) -> bool {
+    entries
+        .iter()
+        .all(|(key, value)| map.and_then(|map| map.get(key)) == value.as_ref())
+}
+
+/// Returns true if the given node is marked as unschedulable.
+pub fn is_cordoned(node: &Node) -> bool {
+    node.spec
+        .as_ref()
+        .and_then(|spec| spec.unschedulable)
+        .unwrap_or_default()
+}
+
 /// Returns the number of allocatable pods of the given node.
 pub fn get_allocatable_pods(node: &Node) -> u32 {
     node.status
@@ -603,3 +4869,929 @@ pub fn get_allocatable_pods(node: &Node) -> u32 {
         .and_then(|quantity| quantity.0.parse().ok())
         .unwrap_or_default()
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use indoc::indoc;
+    use k8s_openapi::api::core::v1::{ObjectReference, Service};
+    use spectral::prelude::*;
+
+    #[test]
+    fn list_params_should_combine_the_default_and_the_given_label_selector() {
+        let list_defaults = ListDefaults {
+            label_selector: Some(String::from("suite=integration")),
+            timeout: Some(Duration::from_secs(5)),
+            ..Default::default()
+        };
+
+        let list_params = list_defaults.list_params("app=test");
+
+        assert_that(&list_params.label_selector)
+            .contains_value(String::from("suite=integration,app=test"));
+        assert_that(&list_params.timeout).contains_value(5);
+    }
+
+    fn config_map(name: &str, labels: &[(&str, &str)]) -> ConfigMap {
+        ConfigMap {
+            metadata: ObjectMeta {
+                name: Some(String::from(name)),
+                labels: Some(
+                    labels
+                        .iter()
+                        .map(|(key, value)| (key.to_string(), value.to_string()))
+                        .collect(),
+                ),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn verify_propagated_should_succeed_if_all_children_contain_the_labels() {
+        let parent = config_map("parent", &[("a", "1"), ("b", "2")]);
+        let children = vec![
+            config_map("child-1", &[("a", "1"), ("b", "2"), ("c", "3")]),
+            config_map("child-2", &[("a", "1"), ("b", "2")]),
+        ];
+
+        let result = verify_propagated(
+            "Label",
+            &parent,
+            &children,
+            |meta| meta.labels.as_ref(),
+            &["a", "b"],
+        );
+
+        assert_that(&result).is_ok();
+    }
+
+    #[test]
+    fn verify_propagated_should_fail_if_a_child_misses_a_label() {
+        let parent = config_map("parent", &[("a", "1"), ("b", "2")]);
+        let children = vec![
+            config_map("child-1", &[("a", "1"), ("b", "2")]),
+            config_map("child-2", &[("a", "1"), ("b", "3")]),
+        ];
+
+        let result = verify_propagated(
+            "Label",
+            &parent,
+            &children,
+            |meta| meta.labels.as_ref(),
+            &["a", "b"],
+        );
+
+        assert_that(&result).is_err();
+    }
+
+    #[test]
+    fn verify_propagated_should_fail_if_there_are_no_children() {
+        let parent = config_map("parent", &[("a", "1")]);
+
+        let result = verify_propagated::<_, ConfigMap>(
+            "Label",
+            &parent,
+            &[],
+            |meta| meta.labels.as_ref(),
+            &["a"],
+        );
+
+        assert_that(&result).is_err();
+    }
+
+    #[test]
+    fn secs_rounded_up_should_be_at_least_one() {
+        assert_that(&secs_rounded_up(Duration::ZERO)).is_equal_to(1);
+        assert_that(&secs_rounded_up(Duration::from_millis(300))).is_equal_to(1);
+        assert_that(&secs_rounded_up(Duration::from_secs(2))).is_equal_to(2);
+        assert_that(&secs_rounded_up(Duration::from_millis(2001))).is_equal_to(3);
+    }
+
+    fn lease(holder_identity: Option<&str>, renewed_secs_ago: i64) -> Lease {
+        Lease {
+            spec: Some(LeaseSpec {
+                holder_identity: holder_identity.map(String::from),
+                lease_duration_seconds: Some(60),
+                renew_time: Some(MicroTime(
+                    Utc::now() - chrono::Duration::seconds(renewed_secs_ago),
+                )),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn lease_should_be_available_if_it_is_not_held_or_held_by_the_same_holder() {
+        let now = MicroTime(Utc::now());
+
+        assert_that(&is_lease_available(&lease(None, 0), "me", &now)).is_true();
+        assert_that(&is_lease_available(&lease(Some(""), 0), "me", &now)).is_true();
+        assert_that(&is_lease_available(&lease(Some("me"), 0), "me", &now)).is_true();
+    }
+
+    #[test]
+    fn lease_should_only_be_available_if_the_lease_of_another_holder_expired() {
+        let now = MicroTime(Utc::now());
+
+        assert_that(&is_lease_available(&lease(Some("other"), 10), "me", &now)).is_false();
+        assert_that(&is_lease_available(&lease(Some("other"), 120), "me", &now)).is_true();
+    }
+
+    #[test]
+    fn has_condition_transitioned_should_only_accept_true_conditions_after_since() {
+        let resource = json!({
+            "status": {
+                "conditions": [
+                    {
+                        "type": "Ready",
+                        "status": "True",
+                        "lastTransitionTime": "2021-10-01T12:00:00Z"
+                    },
+                    {
+                        "type": "Degraded",
+                        "status": "False",
+                        "lastTransitionTime": "2021-10-01T12:05:00Z"
+                    }
+                ]
+            }
+        });
+        let time = |time: &str| Time(time.parse().unwrap());
+
+        assert_that(&has_condition_transitioned(
+            &resource,
+            "Ready",
+            &time("2021-10-01T11:59:59Z"),
+        ))
+        .is_true();
+        assert_that(&has_condition_transitioned(
+            &resource,
+            "Ready",
+            &time("2021-10-01T12:00:00.500Z"),
+        ))
+        .is_true();
+        assert_that(&has_condition_transitioned(
+            &resource,
+            "Ready",
+            &time("2021-10-01T12:00:01Z"),
+        ))
+        .is_false();
+        assert_that(&has_condition_transitioned(
+            &resource,
+            "Degraded",
+            &time("2021-10-01T11:59:59Z"),
+        ))
+        .is_false();
+        assert_that(&has_condition_transitioned(
+            &json!({}),
+            "Ready",
+            &time("2021-10-01T11:59:59Z"),
+        ))
+        .is_false();
+    }
+
+    #[test]
+    fn has_crd_conditions_should_require_all_conditions() {
+        let crd: CustomResourceDefinition = from_yaml(indoc! {"
+            metadata:
+              name: zookeeperclusters.zookeeper.stackable.tech
+            spec:
+              group: zookeeper.stackable.tech
+              names:
+                kind: ZookeeperCluster
+                plural: zookeeperclusters
+              scope: Namespaced
+              versions: []
+            status:
+              acceptedNames:
+                kind: ZookeeperCluster
+                plural: zookeeperclusters
+              conditions:
+                - type: NamesAccepted
+                  status: 'True'
+                - type: Established
+                  status: 'False'
+              storedVersions: []
+        "});
+        let conditions = |types: &[&str]| {
+            types
+                .iter()
+                .map(|type_| type_.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_that(&has_crd_conditions(&crd, &conditions(&["NamesAccepted"]))).is_true();
+        assert_that(&has_crd_conditions(
+            &crd,
+            &conditions(&["NamesAccepted", "Established"]),
+        ))
+        .is_false();
+        assert_that(&has_crd_conditions(&crd, &[])).is_true();
+    }
+
+    #[test]
+    fn crd_api_resource_should_use_the_storage_version() {
+        let crd: CustomResourceDefinition = from_yaml(indoc! {"
+            metadata:
+              name: zookeeperclusters.zookeeper.stackable.tech
+            spec:
+              group: zookeeper.stackable.tech
+              names:
+                kind: ZookeeperCluster
+                plural: zookeeperclusters
+              scope: Namespaced
+              versions:
+                - name: v1alpha1
+                  served: true
+                  storage: false
+                - name: v1beta1
+                  served: true
+                  storage: true
+        "});
+
+        let api_resource = crd_api_resource(&crd);
+
+        assert_that(&api_resource.api_version)
+            .is_equal_to(String::from("zookeeper.stackable.tech/v1beta1"));
+        assert_that(&api_resource.kind).is_equal_to(String::from("ZookeeperCluster"));
+        assert_that(&api_resource.plural).is_equal_to(String::from("zookeeperclusters"));
+    }
+
+    #[test]
+    fn is_namespaced_should_return_the_scope_of_the_resource_type() {
+        let api_resources: APIResourceList = serde_json::from_value(json!({
+            "groupVersion": "scheduling.k8s.io/v1",
+            "resources": [
+                {
+                    "name": "priorityclasses",
+                    "singularName": "",
+                    "namespaced": false,
+                    "kind": "PriorityClass",
+                    "verbs": ["create", "delete", "get"]
+                },
+                {
+                    "name": "widgets",
+                    "singularName": "",
+                    "namespaced": true,
+                    "kind": "Widget",
+                    "verbs": ["create", "delete", "get"]
+                }
+            ]
+        }))
+        .unwrap();
+
+        assert_that(&is_namespaced(&api_resources, "priorityclasses")).is_ok_containing(false);
+        assert_that(&is_namespaced(&api_resources, "widgets")).is_ok_containing(true);
+        assert_that(&is_namespaced(&api_resources, "gadgets")).is_err();
+    }
+
+    #[test]
+    fn status_field_should_resolve_the_json_pointer_relative_to_the_status() {
+        let resource = json!({
+            "spec": { "replicas": 3 },
+            "status": { "replicas": 2, "nodes": [{ "name": "a" }] }
+        });
+
+        assert_that(&status_field(&resource, "/replicas")).contains_value(&json!(2));
+        assert_that(&status_field(&resource, "/nodes/0/name")).contains_value(&json!("a"));
+        assert_that(&status_field(&resource, "/spec/replicas")).is_none();
+        assert_that(&status_field(&json!({}), "/replicas")).is_none();
+    }
+
+    #[test]
+    fn with_readiness_gate_should_add_the_readiness_gate_to_the_pod_template() {
+        let spec = with_readiness_gate(
+            "
+            apiVersion: apps/v1
+            kind: StatefulSet
+            metadata:
+              name: test
+            spec:
+              template:
+                spec:
+                  readinessGates:
+                    - conditionType: existing
+                  containers: []
+            ",
+            "example.com/ready",
+        );
+
+        let stateful_set: serde_yaml::Value = from_yaml(&spec);
+        let readiness_gates = &stateful_set["spec"]["template"]["spec"]["readinessGates"];
+        assert_that(&readiness_gates[0]["conditionType"].as_str()).contains_value("existing");
+        assert_that(&readiness_gates[1]["conditionType"].as_str())
+            .contains_value("example.com/ready");
+    }
+
+    #[test]
+    fn with_readiness_gate_should_add_the_readiness_gate_to_the_pod() {
+        let spec = with_readiness_gate(
+            "
+            apiVersion: v1
+            kind: Pod
+            metadata:
+              name: test
+            spec:
+              containers: []
+            ",
+            "example.com/ready",
+        );
+
+        let pod: Pod = from_yaml(&spec);
+        let readiness_gates = pod.spec.unwrap().readiness_gates.unwrap();
+        assert_that(&readiness_gates[0].condition_type.as_str()).is_equal_to("example.com/ready");
+    }
+
+    #[test]
+    fn with_host_aliases_should_add_the_host_aliases_to_the_pod() {
+        let spec = with_host_aliases(
+            "
+            apiVersion: v1
+            kind: Pod
+            metadata:
+              name: test
+            spec:
+              containers: []
+            ",
+            &[
+                ("10.0.0.1", &["kdc.example.com"]),
+                ("10.0.0.2", &["s3.example.com", "bucket.s3.example.com"]),
+            ],
+        );
+
+        let pod: Pod = from_yaml(&spec);
+        let host_aliases = pod.spec.unwrap().host_aliases.unwrap();
+        assert_that(&host_aliases).has_length(2);
+        assert_that(&host_aliases[0].ip).contains_value(String::from("10.0.0.1"));
+        assert_that(&host_aliases[1].hostnames).contains_value(vec![
+            String::from("s3.example.com"),
+            String::from("bucket.s3.example.com"),
+        ]);
+    }
+
+    #[test]
+    fn exit_code_should_be_extracted_from_the_status() {
+        let success: Status =
+            serde_json::from_value(json!({ "metadata": {}, "status": "Success" })).unwrap();
+        let failure: Status = serde_json::from_value(json!({
+            "metadata": {},
+            "status": "Failure",
+            "reason": "NonZeroExitCode",
+            "details": {
+                "causes": [{ "reason": "ExitCode", "message": "2" }]
+            }
+        }))
+        .unwrap();
+
+        assert_that(&exit_code(&success)).contains_value(0);
+        assert_that(&exit_code(&failure)).contains_value(2);
+        assert_that(&exit_code(&Status::default())).is_none();
+    }
+
+    #[test]
+    fn proxy_url_path_should_address_the_port_of_the_resource() {
+        let url_path = Service::url_path(&(), Some("default"));
+
+        assert_that(&proxy_url_path(
+            &url_path,
+            "simple",
+            8080,
+            "/health?ready=true",
+        ))
+        .is_equal_to(String::from(
+            "/api/v1/namespaces/default/services/simple:8080/proxy/health?ready=true",
+        ));
+        assert_that(&proxy_url_path(&url_path, "simple", 8080, "")).is_equal_to(String::from(
+            "/api/v1/namespaces/default/services/simple:8080/proxy/",
+        ));
+    }
+
+    #[test]
+    fn container_names_should_include_init_containers() {
+        let pod: Pod = from_yaml(
+            "
+            apiVersion: v1
+            kind: Pod
+            metadata:
+              name: test
+            spec:
+              initContainers:
+                - name: prepare
+              containers:
+                - name: product
+                - name: vector
+            ",
+        );
+
+        assert_that(&container_names(&pod)).is_equal_to(vec![
+            String::from("prepare"),
+            String::from("product"),
+            String::from("vector"),
+        ]);
+        assert_that(&container_names(&Pod::default())).is_empty();
+    }
+
+    #[test]
+    fn parse_properties_should_ignore_comments_and_empty_lines() {
+        let properties = parse_properties(indoc! {"
+            # ZooKeeper configuration
+            tickTime=2000
+            dataDir = /stackable/data
+
+            ! legacy comment
+            server.1:zk-0.zk:2888:3888
+            standaloneEnabled
+        "});
+
+        assert_that(&properties).is_equal_to(
+            vec![
+                ("dataDir", "/stackable/data"),
+                ("server.1", "zk-0.zk:2888:3888"),
+                ("standaloneEnabled", ""),
+                ("tickTime", "2000"),
+            ]
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect::<BTreeMap<_, _>>(),
+        );
+    }
+
+    #[test]
+    fn get_container_arguments_should_split_scripts_and_env_values() {
+        let pod: Pod = from_yaml(indoc! {r#"
+            metadata:
+              name: zookeeper-0
+            spec:
+              containers:
+                - name: zookeeper
+                  command: [bash, -c]
+                  args:
+                    - "java \"-Xmx2g\" -jar zookeeper.jar"
+                  env:
+                    - name: JVM_FLAGS
+                      value: -XX:+UseG1GC -Xms2g
+                - name: metrics
+                  args: [-Xmx64m]
+        "#});
+
+        assert_that(&get_container_arguments(&pod, "zookeeper")).is_equal_to(vec![
+            String::from("bash"),
+            String::from("-c"),
+            String::from("java"),
+            String::from("-Xmx2g"),
+            String::from("-jar"),
+            String::from("zookeeper.jar"),
+            String::from("-XX:+UseG1GC"),
+            String::from("-Xms2g"),
+        ]);
+        assert_that(&get_container_arguments(&pod, "kafka")).is_empty();
+    }
+
+    #[test]
+    fn parse_env_should_split_at_the_first_equals_sign() {
+        let env = parse_env(indoc! {"
+            HOME=/stackable
+            JVM_ARGS=-Dzookeeper.admin.enableServer=false
+            MULTILINE=first
+            second
+        "});
+
+        assert_that(&env).is_equal_to(
+            vec![
+                ("HOME", "/stackable"),
+                ("JVM_ARGS", "-Dzookeeper.admin.enableServer=false"),
+                ("MULTILINE", "first"),
+            ]
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<BTreeMap<_, _>>(),
+        );
+    }
+
+    fn pod(uid: &str, node_name: &str, owner_kind: &str, ready: bool) -> Pod {
+        from_yaml(&format!(
+            "
+            metadata:
+              name: pod-{uid}
+              uid: '{uid}'
+              ownerReferences:
+                - apiVersion: apps/v1
+                  kind: {owner_kind}
+                  name: owner
+                  uid: owner-{owner_kind}
+                  controller: true
+            spec:
+              nodeName: {node_name}
+              containers: []
+            status:
+              phase: Running
+              conditions:
+                - type: Ready
+                  status: '{ready}'
+            ",
+            uid = uid,
+            node_name = node_name,
+            owner_kind = owner_kind,
+            ready = if ready { "True" } else { "False" },
+        ))
+    }
+
+    #[test]
+    fn drainable_pods_should_exclude_daemon_set_pods() {
+        let pods = vec![
+            pod("1", "node-1", "StatefulSet", true),
+            pod("2", "node-1", "DaemonSet", true),
+            pod("3", "node-2", "StatefulSet", true),
+        ];
+
+        let uids = drainable_pods(&pods, "node-1")
+            .iter()
+            .filter_map(|pod| pod.uid())
+            .collect::<Vec<_>>();
+
+        assert_that(&uids).is_equal_to(vec![String::from("1")]);
+    }
+
+    #[test]
+    fn is_rescheduled_should_await_ready_pods_on_other_nodes() {
+        let pods_before = vec![
+            pod("1", "node-1", "StatefulSet", true),
+            pod("2", "node-2", "StatefulSet", true),
+        ];
+        let evicted_pods = drainable_pods(&pods_before, "node-1");
+
+        let is_rescheduled_to = |pods_after: Vec<Pod>| {
+            is_rescheduled(&evicted_pods, &pods_before, &pods_after, "node-1")
+        };
+
+        assert_that(&is_rescheduled_to(pods_before.clone())).is_false();
+        assert_that(&is_rescheduled_to(vec![
+            pod("2", "node-2", "StatefulSet", true),
+            pod("3", "node-2", "StatefulSet", false),
+        ]))
+        .is_false();
+        assert_that(&is_rescheduled_to(vec![
+            pod("2", "node-2", "StatefulSet", true),
+            pod("3", "node-3", "StatefulSet", true),
+        ]))
+        .is_true();
+    }
+
+    #[test]
+    fn has_entries_should_check_added_and_removed_entries() {
+        let map = vec![(String::from("zone"), String::from("a"))]
+            .into_iter()
+            .collect::<BTreeMap<_, _>>();
+        let entries = |entries: &[(&str, Option<&str>)]| {
+            entries
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.map(String::from)))
+                .collect::<BTreeMap<_, _>>()
+        };
+
+        assert_that(&has_entries(Some(&map), &entries(&[("zone", Some("a"))]))).is_true();
+        assert_that(&has_entries(Some(&map), &entries(&[("zone", Some("b"))]))).is_false();
+        assert_that(&has_entries(Some(&map), &entries(&[("rack", None)]))).is_true();
+        assert_that(&has_entries(Some(&map), &entries(&[("zone", None)]))).is_false();
+        assert_that(&has_entries(None, &entries(&[("zone", None)]))).is_true();
+    }
+
+    #[test]
+    fn decode_secret_data_should_decode_the_values() {
+        let secret: Secret = from_yaml(
+            "
+            metadata:
+              name: credentials
+            data:
+              username: YWRtaW4=
+              password: c2VjcmV0
+            ",
+        );
+
+        assert_that(&decode_secret_data(&secret)).is_equal_to(
+            vec![
+                (String::from("password"), String::from("secret")),
+                (String::from("username"), String::from("admin")),
+            ]
+            .into_iter()
+            .collect::<BTreeMap<_, _>>(),
+        );
+        assert_that(&decode_secret_data(&Secret::default()).is_empty()).is_true();
+    }
+
+    #[test]
+    fn resource_attributes_should_split_group_and_subresource() {
+        assert_that(&resource_attributes("get", "pods", None)).is_equal_to(ResourceAttributes {
+            verb: Some(String::from("get")),
+            group: Some(String::new()),
+            resource: Some(String::from("pods")),
+            ..Default::default()
+        });
+        assert_that(&resource_attributes(
+            "patch",
+            "statefulsets.apps/scale",
+            Some("default"),
+        ))
+        .is_equal_to(ResourceAttributes {
+            verb: Some(String::from("patch")),
+            group: Some(String::from("apps")),
+            resource: Some(String::from("statefulsets")),
+            subresource: Some(String::from("scale")),
+            namespace: Some(String::from("default")),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn is_access_allowed_should_deny_on_evaluation_errors() {
+        let status = |allowed: bool, evaluation_error: Option<&str>| {
+            Some(SubjectAccessReviewStatus {
+                allowed,
+                evaluation_error: evaluation_error.map(String::from),
+                ..Default::default()
+            })
+        };
+
+        assert_that(&is_access_allowed(status(true, None)).ok()).contains_value(true);
+        assert_that(&is_access_allowed(status(false, None)).ok()).contains_value(false);
+        assert_that(&is_access_allowed(status(true, Some("webhook unavailable"))).ok())
+            .contains_value(true);
+        assert_that(&is_access_allowed(status(false, Some("webhook unavailable"))).ok())
+            .contains_value(false);
+        assert_that(&is_access_allowed(None).is_err()).is_true();
+    }
+
+    #[test]
+    fn impersonation_headers_should_contain_the_user_and_all_groups() {
+        let headers = impersonation_headers(
+            "system:serviceaccount:default:zookeeper",
+            &["system:serviceaccounts", "system:authenticated"],
+        )
+        .unwrap();
+
+        assert_that(&headers["Impersonate-User"]).is_equal_to(HeaderValue::from_static(
+            "system:serviceaccount:default:zookeeper",
+        ));
+        assert_that(
+            &headers
+                .get_all("Impersonate-Group")
+                .iter()
+                .collect::<Vec<_>>(),
+        )
+        .is_equal_to(vec![
+            &HeaderValue::from_static("system:serviceaccounts"),
+            &HeaderValue::from_static("system:authenticated"),
+        ]);
+        assert_that(&impersonation_headers("invalid\nuser", &[])).is_err();
+    }
+
+    #[test]
+    fn operator_events_should_match_the_reason_and_the_message_substring() {
+        let event: Event = from_yaml(indoc! {"
+            metadata:
+              name: simple.event
+            involvedObject:
+              kind: ZookeeperCluster
+              name: simple
+            reason: Upgrading
+            message: Upgrading the cluster from 3.5.8 to 3.6.3
+        "});
+
+        assert_that(&is_event_matching(
+            &event,
+            "Upgrading",
+            "from 3.5.8 to 3.6.3",
+        ))
+        .is_true();
+        assert_that(&is_event_matching(&event, "Upgrading", "to 3.7.0")).is_false();
+        assert_that(&is_event_matching(&event, "Upgraded", "")).is_false();
+        assert_that(&describe_events(&[event])).is_equal_to(String::from(
+            "[Upgrading: Upgrading the cluster from 3.5.8 to 3.6.3]",
+        ));
+        assert_that(&describe_events(&[])).is_equal_to(String::from("none"));
+    }
+
+    #[test]
+    fn scheduling_failure_should_return_the_latest_failed_scheduling_event() {
+        let pending_pod: Pod = from_yaml(indoc! {"
+            metadata:
+              name: zookeeper-0
+            status:
+              phase: Pending
+              conditions:
+                - type: PodScheduled
+                  status: 'False'
+                  reason: Unschedulable
+                  message: '0/3 nodes are available: 3 Insufficient cpu.'
+        "});
+        let event = |message: &str, last_timestamp: &str| -> Event {
+            from_yaml(&format!(
+                "
+                metadata:
+                  name: zookeeper-0.event
+                involvedObject:
+                  kind: Pod
+                  name: zookeeper-0
+                reason: FailedScheduling
+                message: '{}'
+                lastTimestamp: {}
+                ",
+                message, last_timestamp
+            ))
+        };
+        let events = [
+            event(
+                "0/3 nodes are available: 3 Insufficient cpu.",
+                "2021-10-01T10:00:00Z",
+            ),
+            event(
+                "0/3 nodes are available: 3 pod has unbound immediate PersistentVolumeClaims.",
+                "2021-10-01T10:01:00Z",
+            ),
+        ];
+
+        assert_that(&scheduling_failure(&pending_pod, &events)).contains_value(String::from(
+            "0/3 nodes are available: 3 pod has unbound immediate PersistentVolumeClaims.",
+        ));
+
+        let new_api_event = |message: &str, event_time: &str, series: &str| -> Event {
+            from_yaml(&format!(
+                "
+                metadata:
+                  name: zookeeper-0.event
+                involvedObject:
+                  kind: Pod
+                  name: zookeeper-0
+                reason: FailedScheduling
+                message: '{}'
+                eventTime: {}
+                {}
+                ",
+                message, event_time, series
+            ))
+        };
+        let new_api_events = [
+            new_api_event(
+                "0/3 nodes are available: 3 Insufficient memory.",
+                "2021-10-01T10:00:00.000000Z",
+                "series: { count: 2, lastObservedTime: '2021-10-01T10:05:00.000000Z' }",
+            ),
+            new_api_event(
+                "0/3 nodes are available: 3 Insufficient cpu.",
+                "2021-10-01T10:02:00.000000Z",
+                "",
+            ),
+        ];
+
+        assert_that(&scheduling_failure(&pending_pod, &new_api_events)).contains_value(
+            String::from("0/3 nodes are available: 3 Insufficient memory."),
+        );
+        assert_that(&scheduling_failure(&pending_pod, &[]))
+            .contains_value(String::from("0/3 nodes are available: 3 Insufficient cpu."));
+        assert_that(&scheduling_failure(
+            &pod("1", "node-1", "StatefulSet", true),
+            &events,
+        ))
+        .is_none();
+    }
+
+    #[test]
+    fn new_warning_events_should_only_report_new_events_of_the_given_resources() {
+        let warning = |uid: &str, resource_version: &str, name: &str| Event {
+            metadata: ObjectMeta {
+                uid: Some(uid.to_string()),
+                resource_version: Some(resource_version.to_string()),
+                ..Default::default()
+            },
+            involved_object: ObjectReference {
+                kind: Some(String::from("Pod")),
+                namespace: Some(String::from("default")),
+                name: Some(name.to_string()),
+                ..Default::default()
+            },
+            reason: Some(String::from("BackOff")),
+            message: Some(String::from("Back-off restarting failed container")),
+            type_: Some(String::from("Warning")),
+            ..Default::default()
+        };
+        let resources = vec![ResourceId {
+            kind: String::from("Pod"),
+            namespace: Some(String::from("default")),
+            name: String::from("test-0"),
+        }]
+        .into_iter()
+        .collect();
+
+        let before = vec![warning("1", "1", "test-0")];
+        let after = vec![
+            warning("1", "1", "test-0"),
+            warning("2", "2", "test-0"),
+            warning("3", "3", "other-0"),
+        ];
+
+        assert_that(&new_warning_events(&before, after, &resources)).is_equal_to(vec![
+            String::from("Pod/default/test-0: BackOff: Back-off restarting failed container"),
+        ]);
+    }
+
+    #[test]
+    fn owner_ids_should_be_in_the_namespace_of_the_resource() {
+        let statefulset: StatefulSet = from_yaml(
+            "
+            metadata:
+              name: simple-server-default
+              namespace: default
+              ownerReferences:
+                - apiVersion: zookeeper.stackable.tech/v1alpha1
+                  kind: ZookeeperCluster
+                  name: simple
+                  uid: 2d4b7d2c-1b46-4d1d-8c1f-5d0e5c4a1f7a
+            ",
+        );
+
+        assert_that(&owner_ids(&statefulset)).is_equal_to(vec![ResourceId {
+            kind: String::from("ZookeeperCluster"),
+            namespace: Some(String::from("default")),
+            name: String::from("simple"),
+        }]);
+    }
+
+    #[test]
+    fn take_lines_should_only_take_complete_lines() {
+        let mut buffer = b"first\r\nsecond\nthi".to_vec();
+
+        assert_that(&take_lines(&mut buffer))
+            .is_equal_to(vec![String::from("first"), String::from("second")]);
+        assert_that(&buffer).is_equal_to(b"thi".to_vec());
+
+        buffer.extend_from_slice(b"rd");
+
+        assert_that(&take_lines(&mut buffer)).is_empty();
+        assert_that(&buffer).is_equal_to(b"third".to_vec());
+    }
+
+    #[test]
+    fn without_sidecar_injection_should_annotate_the_pod_template() {
+        let spec = without_sidecar_injection(
+            "
+            apiVersion: apps/v1
+            kind: Deployment
+            metadata:
+              name: test
+            spec:
+              template:
+                metadata:
+                  labels:
+                    app: test
+                spec:
+                  containers: []
+            ",
+        );
+
+        let deployment: serde_yaml::Value = from_yaml(&spec);
+        let pod_metadata = &deployment["spec"]["template"]["metadata"];
+        assert_that(&pod_metadata["labels"]["app"].as_str()).contains_value("test");
+        assert_that(&pod_metadata["annotations"]["sidecar.istio.io/inject"].as_str())
+            .contains_value("false");
+        assert_that(&pod_metadata["annotations"]["linkerd.io/inject"].as_str())
+            .contains_value("disabled");
+    }
+
+    #[test]
+    fn list_params_should_omit_empty_label_selectors() {
+        let list_defaults = ListDefaults::default();
+
+        let list_params = list_defaults.list_params("app=test");
+
+        assert_that(&list_params.label_selector).contains_value(String::from("app=test"));
+        assert_that(&list_params.timeout).is_none();
+    }
+
+    #[test]
+    fn list_params_without_label_defaults_should_only_contain_the_given_label_selector() {
+        let list_defaults = ListDefaults {
+            label_selector: Some(String::from("suite=integration")),
+            timeout: Some(Duration::from_secs(5)),
+            ..Default::default()
+        };
+
+        let list_params =
+            list_defaults.list_params_without_label_defaults("kubernetes.io/os=linux");
+
+        assert_that(&list_params.label_selector)
+            .contains_value(String::from("kubernetes.io/os=linux"));
+        assert_that(&list_params.timeout).contains_value(5);
+    }
+
+    #[test]
+    fn list_query_should_request_resource_versions_not_older_than_any() {
+        assert_that(&ResourceVersion::NotOlderThanAny.list_query())
+            .contains_value("resourceVersion=0&resourceVersionMatch=NotOlderThan");
+        assert_that(&ResourceVersion::MostRecent.list_query()).is_none();
+        assert_that(&ListDefaults::default().list_resource_version)
+            .is_equal_to(ResourceVersion::MostRecent);
+    }
+}
@@ -0,0 +1,251 @@
+//! Linter for common mistakes in generated pods
+
+use super::sidecar::is_injected_sidecar;
+use k8s_openapi::api::core::v1::{Container, Pod};
+use std::fmt;
+
+/// A common mistake found in a pod
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Warning {
+    /// The image of the container has no tag or the tag `latest`.
+    LatestTag { container: String, image: String },
+    /// The container has neither resource requests nor limits.
+    MissingResources { container: String },
+    /// The container has no readiness probe.
+    MissingReadinessProbe { container: String },
+    /// The container has no liveness probe.
+    MissingLivenessProbe { container: String },
+    /// The volume mounts a path of the host.
+    HostPathMount { volume: String, path: String },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::LatestTag { container, image } => write!(
+                f,
+                "Container [{}] uses the image [{}] without a fixed tag",
+                container, image
+            ),
+            Warning::MissingResources { container } => write!(
+                f,
+                "Container [{}] has no resource requests or limits",
+                container
+            ),
+            Warning::MissingReadinessProbe { container } => {
+                write!(f, "Container [{}] has no readiness probe", container)
+            }
+            Warning::MissingLivenessProbe { container } => {
+                write!(f, "Container [{}] has no liveness probe", container)
+            }
+            Warning::HostPathMount { volume, path } => {
+                write!(f, "Volume [{}] mounts the host path [{}]", volume, path)
+            }
+        }
+    }
+}
+
+/// Checks the given pod for common mistakes.
+///
+/// Sidecars injected by service meshes are not checked.
+///
+/// Not every warning is a mistake for every product, so test cases can
+/// turn the warnings into assertions selectively:
+///
+/// ```rust
+/// use integration_test_commons::test::lint::{lint_pod, Warning};
+/// use integration_test_commons::test::prelude::*;
+///
+/// # let pod = Pod::default();
+/// let warnings = lint_pod(&pod)
+///     .into_iter()
+///     .filter(|warning| !matches!(warning, Warning::MissingLivenessProbe { .. }))
+///     .collect::<Vec<_>>();
+///
+/// assert_that(&warnings).is_empty();
+/// ```
+pub fn lint_pod(pod: &Pod) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+
+    let spec = match &pod.spec {
+        Some(spec) => spec,
+        None => return warnings,
+    };
+
+    let containers = spec
+        .containers
+        .iter()
+        .filter(|container| !is_injected_sidecar(&container.name))
+        .collect::<Vec<_>>();
+    let init_containers = spec
+        .init_containers
+        .iter()
+        .flatten()
+        .filter(|container| !is_injected_sidecar(&container.name));
+
+    for container in containers.iter().copied().chain(init_containers) {
+        warnings.extend(lint_image(container));
+        let has_resources = container.resources.as_ref().is_some_and(|resources| {
+            resources
+                .requests
+                .iter()
+                .chain(&resources.limits)
+                .any(|r| !r.is_empty())
+        });
+        if !has_resources {
+            warnings.push(Warning::MissingResources {
+                container: container.name.clone(),
+            });
+        }
+    }
+
+    for container in containers {
+        if container.readiness_probe.is_none() {
+            warnings.push(Warning::MissingReadinessProbe {
+                container: container.name.clone(),
+            });
+        }
+        if container.liveness_probe.is_none() {
+            warnings.push(Warning::MissingLivenessProbe {
+                container: container.name.clone(),
+            });
+        }
+    }
+
+    for volume in spec.volumes.iter().flatten() {
+        if let Some(host_path) = &volume.host_path {
+            warnings.push(Warning::HostPathMount {
+                volume: volume.name.clone(),
+                path: host_path.path.clone(),
+            });
+        }
+    }
+
+    warnings
+}
+
+/// Returns a warning if the image of the given container is not pinned.
+fn lint_image(container: &Container) -> Option<Warning> {
+    let image = container.image.as_deref().unwrap_or_default();
+
+    let is_pinned = if image.contains('@') {
+        true
+    } else {
+        let name = image.rsplit('/').next().unwrap_or_default();
+        match name.split_once(':') {
+            Some((_, tag)) => tag != "latest",
+            None => false,
+        }
+    };
+
+    if is_pinned {
+        None
+    } else {
+        Some(Warning::LatestTag {
+            container: container.name.clone(),
+            image: image.to_owned(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn lint_pod_should_not_warn_about_a_well_defined_pod() {
+        let pod: Pod = serde_yaml::from_str(
+            "
+            metadata:
+              name: test
+            spec:
+              containers:
+                - name: zookeeper
+                  image: docker.stackable.tech:5000/zookeeper:3.7.0
+                  resources:
+                    limits:
+                      memory: 512Mi
+                  readinessProbe:
+                    tcpSocket:
+                      port: 2181
+                  livenessProbe:
+                    tcpSocket:
+                      port: 2181
+            ",
+        )
+        .unwrap();
+
+        assert_that(&lint_pod(&pod)).is_empty();
+    }
+
+    #[test]
+    fn lint_pod_should_ignore_injected_sidecars() {
+        let pod: Pod = serde_yaml::from_str(
+            "
+            metadata:
+              name: test
+            spec:
+              initContainers:
+                - name: istio-init
+                  image: istio/proxyv2
+              containers:
+                - name: istio-proxy
+                  image: istio/proxyv2
+            ",
+        )
+        .unwrap();
+
+        assert_that(&lint_pod(&pod)).is_empty();
+    }
+
+    #[test]
+    fn lint_pod_should_warn_about_common_mistakes() {
+        let pod: Pod = serde_yaml::from_str(
+            "
+            metadata:
+              name: test
+            spec:
+              initContainers:
+                - name: init
+                  image: docker.stackable.tech:5000/tools:latest
+                  resources:
+                    requests:
+                      cpu: 100m
+              containers:
+                - name: zookeeper
+                  image: docker.stackable.tech:5000/zookeeper
+              volumes:
+                - name: data
+                  hostPath:
+                    path: /data
+            ",
+        )
+        .unwrap();
+
+        assert_that(&lint_pod(&pod)).is_equal_to(vec![
+            Warning::LatestTag {
+                container: String::from("zookeeper"),
+                image: String::from("docker.stackable.tech:5000/zookeeper"),
+            },
+            Warning::MissingResources {
+                container: String::from("zookeeper"),
+            },
+            Warning::LatestTag {
+                container: String::from("init"),
+                image: String::from("docker.stackable.tech:5000/tools:latest"),
+            },
+            Warning::MissingReadinessProbe {
+                container: String::from("zookeeper"),
+            },
+            Warning::MissingLivenessProbe {
+                container: String::from("zookeeper"),
+            },
+            Warning::HostPathMount {
+                volume: String::from("data"),
+                path: String::from("/data"),
+            },
+        ]);
+    }
+}
@@ -0,0 +1,150 @@
+//! Data generation for load tests
+
+use super::prelude::{Pod, TestKubeClient};
+use super::progress::Progress;
+use kube::ResourceExt;
+
+/// A generator which creates data in a product, e.g. znodes in
+/// ZooKeeper, messages in Kafka, or files in HDFS
+///
+/// The data is generated by executing a shell command repeatedly in a
+/// container of the given product pod, so the command line tools, the
+/// configuration, and the credentials of the product are available.
+/// The iterations are split into batches and the progress is reported
+/// after each batch to the
+/// [`ProgressReporter`](super::progress::ProgressReporter).
+///
+/// ```rust,no_run
+/// use integration_test_commons::test::load::{payload, DataGenerator};
+/// use integration_test_commons::test::prelude::*;
+///
+/// let client = TestKubeClient::new();
+/// # let pod = Pod::default();
+///
+/// DataGenerator::new(&client, &pod).generate(
+///     "znodes",
+///     1000,
+///     &format!(
+///         "bin/zkCli.sh -server localhost:2181 create /load-$i {}",
+///         payload(512)
+///     ),
+/// );
+/// ```
+pub struct DataGenerator<'a> {
+    client: &'a TestKubeClient,
+    pod: Pod,
+    container: Option<String>,
+    batch_size: usize,
+}
+
+impl<'a> DataGenerator<'a> {
+    /// Creates a generator which executes the command in the first
+    /// container of the given pod.
+    pub fn new(client: &'a TestKubeClient, pod: &Pod) -> Self {
+        DataGenerator {
+            client,
+            pod: pod.clone(),
+            container: None,
+            batch_size: 100,
+        }
+    }
+
+    /// Sets the container of the pod in which the command is executed.
+    pub fn container(mut self, container: &str) -> Self {
+        self.container = Some(container.to_string());
+        self
+    }
+
+    /// Sets the number of iterations which are executed with one
+    /// command.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Runs the given shell command `count` times.
+    ///
+    /// The iteration number starting at 1 is available in the shell
+    /// variable `$i`. The generation stops with a panic as soon as the
+    /// command fails.
+    pub fn generate(&self, description: &str, count: usize, command: &str) {
+        let container = self.container.clone().unwrap_or_else(|| {
+            self.pod
+                .spec
+                .as_ref()
+                .and_then(|spec| spec.containers.first())
+                .map(|container| container.name.clone())
+                .unwrap_or_else(|| panic!("Pod [{}] has no containers", self.pod.name()))
+        });
+        let progress = Progress::start(&format!(
+            "[{}] Generating {} {}",
+            self.pod.name(),
+            count,
+            description
+        ));
+        let mut generated = 0;
+
+        while generated < count {
+            let batch_end = (generated + self.batch_size).min(count);
+            let script = batch_script(generated + 1, batch_end, command);
+
+            let output =
+                self.client
+                    .exec_in_container(&self.pod, &container, &["sh", "-c", &script]);
+            assert!(
+                output.success(),
+                "[{}] Generating {} failed in the iterations {} to {}: {}{}",
+                self.pod.name(),
+                description,
+                generated + 1,
+                batch_end,
+                output.stdout,
+                output.stderr
+            );
+
+            generated = batch_end;
+            progress.update(&format!(
+                "[{}] Generated [{}/{}] {}",
+                self.pod.name(),
+                generated,
+                count,
+                description
+            ));
+        }
+        progress.finish(&format!(
+            "[{}] Generated {} {}",
+            self.pod.name(),
+            count,
+            description
+        ));
+    }
+}
+
+/// Returns a shell expression which expands to a payload of the given
+/// size in bytes.
+pub fn payload(size: usize) -> String {
+    format!("\"$(head -c {} /dev/zero | tr '\\0' x)\"", size)
+}
+
+/// Returns a shell script which runs the command for all iterations
+/// from `start` to `end` and fails on the first failing iteration.
+fn batch_script(start: usize, end: usize, command: &str) -> String {
+    format!(
+        "for i in $(seq {} {}); do {} || exit 1; done",
+        start, end, command
+    )
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn batch_script_should_loop_over_the_iterations() {
+        assert_that(&batch_script(101, 200, "echo $i")).is_equal_to(String::from(
+            "for i in $(seq 101 200); do echo $i || exit 1; done",
+        ));
+    }
+}
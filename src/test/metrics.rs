@@ -0,0 +1,125 @@
+//! Resource usage of pods and nodes reported by the metrics-server
+//!
+//! The types of the `metrics.k8s.io` API group are not contained in
+//! `k8s-openapi`, so they are defined here. The metrics are retrieved
+//! with [`KubeClient::get_pod_metrics`] and
+//! [`KubeClient::get_node_metrics`].
+//!
+//! [`KubeClient::get_pod_metrics`]: super::kube::KubeClient::get_pod_metrics
+//! [`KubeClient::get_node_metrics`]: super::kube::KubeClient::get_node_metrics
+
+use super::quantity::{parse_cpu_millis, parse_memory_bytes};
+use anyhow::{anyhow, Result};
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, Time};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// URL path of the `metrics.k8s.io` API
+pub(crate) const METRICS_API_PATH: &str = "/apis/metrics.k8s.io/v1beta1";
+
+/// Resource usage of the containers of a pod
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PodMetrics {
+    pub metadata: ObjectMeta,
+    pub timestamp: Time,
+    pub window: String,
+    pub containers: Vec<ContainerMetrics>,
+}
+
+/// Resource usage of a container
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ContainerMetrics {
+    pub name: String,
+    pub usage: BTreeMap<String, Quantity>,
+}
+
+/// Resource usage of a node
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NodeMetrics {
+    pub metadata: ObjectMeta,
+    pub timestamp: Time,
+    pub window: String,
+    pub usage: BTreeMap<String, Quantity>,
+}
+
+impl PodMetrics {
+    /// Returns the CPU usage of all containers in millicores.
+    pub fn cpu_millis(&self) -> Result<u64> {
+        self.containers
+            .iter()
+            .map(ContainerMetrics::cpu_millis)
+            .sum()
+    }
+
+    /// Returns the memory usage of all containers in bytes.
+    pub fn memory_bytes(&self) -> Result<u64> {
+        self.containers
+            .iter()
+            .map(ContainerMetrics::memory_bytes)
+            .sum()
+    }
+}
+
+impl ContainerMetrics {
+    /// Returns the CPU usage in millicores.
+    pub fn cpu_millis(&self) -> Result<u64> {
+        parse_cpu_millis(usage(&self.usage, "cpu")?)
+    }
+
+    /// Returns the memory usage in bytes.
+    pub fn memory_bytes(&self) -> Result<u64> {
+        parse_memory_bytes(usage(&self.usage, "memory")?)
+    }
+}
+
+impl NodeMetrics {
+    /// Returns the CPU usage in millicores.
+    pub fn cpu_millis(&self) -> Result<u64> {
+        parse_cpu_millis(usage(&self.usage, "cpu")?)
+    }
+
+    /// Returns the memory usage in bytes.
+    pub fn memory_bytes(&self) -> Result<u64> {
+        parse_memory_bytes(usage(&self.usage, "memory")?)
+    }
+}
+
+fn usage<'a>(usage: &'a BTreeMap<String, Quantity>, resource: &str) -> Result<&'a str> {
+    usage
+        .get(resource)
+        .map(|quantity| quantity.0.as_str())
+        .ok_or_else(|| anyhow!("The usage of [{}] is not reported.", resource))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test::kube::from_yaml;
+    use spectral::prelude::*;
+
+    #[test]
+    fn pod_metrics_should_sum_up_the_usage_of_the_containers() {
+        let metrics: PodMetrics = from_yaml(
+            "
+            metadata:
+              name: test-0
+            timestamp: '2021-10-01T12:00:00Z'
+            window: 30s
+            containers:
+              - name: main
+                usage:
+                  cpu: 250000000n
+                  memory: 200Mi
+              - name: sidecar
+                usage:
+                  cpu: 5m
+                  memory: 56Mi
+            ",
+        );
+
+        assert_that(&metrics.cpu_millis().unwrap()).is_equal_to(255);
+        assert_that(&metrics.memory_bytes().unwrap()).is_equal_to(256 << 20);
+    }
+}
@@ -1,7 +1,44 @@
 //! Test module extracted from the integration tests
 
+pub mod api_discovery;
 pub mod assertions;
+pub mod bootstrap;
+pub mod capacity;
+pub mod cluster_lock;
+pub mod connectivity;
+#[cfg(feature = "copy")]
+mod copy;
+pub mod deadline;
+pub mod debug;
+pub mod environment;
+#[cfg(feature = "grpc-health")]
+pub mod grpc_health;
+#[cfg(feature = "http-probe")]
+pub mod http_probe;
+pub mod ip_family;
 pub mod kube;
+pub mod lint;
+pub mod load;
+pub mod metrics;
+#[cfg(feature = "port-forward")]
+pub mod port_forward;
+pub mod preemption;
 pub mod prelude;
+pub mod pressure;
+pub mod printer_columns;
+pub mod progress;
+pub mod quantity;
+pub mod redaction;
 pub mod repository;
+pub mod rng;
+pub mod rollout;
+pub mod scale_down;
+pub mod shared_fixture;
+pub mod sidecar;
+pub mod skip;
+pub mod snapshot;
+pub mod table;
 pub mod temporary_resource;
+pub mod timeline;
+#[cfg(feature = "port-forward")]
+pub mod ws;
@@ -0,0 +1,166 @@
+//! Port forwarding to pods
+//!
+//! `kube` 0.60 does not support port forwarding, so the WebSocket
+//! protocol of the API server is implemented here. Every frame starts
+//! with a channel byte. The data of a forwarded port is sent on channel
+//! 0 and errors are reported on channel 1. The first frame of each
+//! channel sent by the API server is prefixed with the port number.
+
+use anyhow::{anyhow, Result};
+use futures::{SinkExt, StreamExt, TryStreamExt};
+use kube::Client;
+use std::net::SocketAddr;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+
+const DATA_CHANNEL: u8 = 0;
+const ERROR_CHANNEL: u8 = 1;
+
+/// A port of a pod which is forwarded to a local address
+///
+/// Every connection to the local address is forwarded to the port of
+/// the pod. The forwarding is stopped when this value is dropped.
+pub struct PortForward {
+    local_address: SocketAddr,
+    listener: JoinHandle<()>,
+}
+
+impl PortForward {
+    /// Starts to forward connections to the local address to the given
+    /// port of the pod.
+    pub(crate) async fn start(
+        client: Client,
+        namespace: &str,
+        pod_name: &str,
+        port: u16,
+    ) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let local_address = listener.local_addr()?;
+        let path = format!(
+            "/api/v1/namespaces/{}/pods/{}/portforward?ports={}",
+            namespace, pod_name, port
+        );
+
+        let listener = tokio::spawn(async move {
+            while let Ok((connection, _)) = listener.accept().await {
+                let client = client.clone();
+                let path = path.clone();
+                tokio::spawn(async move {
+                    if let Err(error) = forward(client, &path, connection).await {
+                        eprintln!("Port forwarding to [{}] failed: {}", path, error);
+                    }
+                });
+            }
+        });
+
+        Ok(PortForward {
+            local_address,
+            listener,
+        })
+    }
+
+    /// Returns the local address which is forwarded to the pod.
+    pub fn local_address(&self) -> SocketAddr {
+        self.local_address
+    }
+}
+
+impl Drop for PortForward {
+    fn drop(&mut self) {
+        self.listener.abort();
+    }
+}
+
+/// Forwards the given connection via a new WebSocket connection to the
+/// API server.
+async fn forward(client: Client, path: &str, mut connection: TcpStream) -> Result<()> {
+    let request = http::Request::get(path).body(Vec::new())?;
+    let (mut sink, mut stream) = client.connect(request).await?.split();
+    let (mut reader, mut writer) = connection.split();
+
+    let upstream = async {
+        let mut buffer = vec![0; 4096];
+        loop {
+            let read = reader.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            let mut frame = Vec::with_capacity(read + 1);
+            frame.push(DATA_CHANNEL);
+            frame.extend_from_slice(&buffer[..read]);
+            sink.send(Message::Binary(frame)).await?;
+        }
+        sink.close().await?;
+        Ok::<_, anyhow::Error>(())
+    };
+
+    let downstream = async {
+        let mut decoder = FrameDecoder::default();
+        while let Some(message) = stream.try_next().await? {
+            if let Message::Binary(frame) = message {
+                match decoder.decode(&frame) {
+                    Some((DATA_CHANNEL, data)) => writer.write_all(data).await?,
+                    Some((ERROR_CHANNEL, error)) if !error.is_empty() => {
+                        return Err(anyhow!("{}", String::from_utf8_lossy(error)))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        writer.shutdown().await?;
+        Ok(())
+    };
+
+    futures::try_join!(upstream, downstream)?;
+    Ok(())
+}
+
+/// Decoder which strips the channel byte and the port prefix from the
+/// frames sent by the API server
+#[derive(Debug, Default)]
+struct FrameDecoder {
+    data_port_received: bool,
+    error_port_received: bool,
+}
+
+impl FrameDecoder {
+    /// Returns the channel and the payload of the given frame.
+    fn decode<'a>(&mut self, frame: &'a [u8]) -> Option<(u8, &'a [u8])> {
+        let (&channel, payload) = frame.split_first()?;
+        let port_received = match channel {
+            DATA_CHANNEL => &mut self.data_port_received,
+            ERROR_CHANNEL => &mut self.error_port_received,
+            _ => return None,
+        };
+
+        if *port_received {
+            Some((channel, payload))
+        } else {
+            *port_received = true;
+            Some((channel, payload.get(2..).unwrap_or_default()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn decoder_should_strip_the_port_prefix_of_the_first_frame_per_channel() {
+        let mut decoder = FrameDecoder::default();
+
+        assert_that(&decoder.decode(&[DATA_CHANNEL, 0x90, 0x1f]))
+            .contains_value((DATA_CHANNEL, &[][..]));
+        assert_that(&decoder.decode(&[ERROR_CHANNEL, 0x90, 0x1f]))
+            .contains_value((ERROR_CHANNEL, &[][..]));
+        assert_that(&decoder.decode(&[DATA_CHANNEL, b'o', b'k']))
+            .contains_value((DATA_CHANNEL, &b"ok"[..]));
+        assert_that(&decoder.decode(&[])).is_none();
+        assert_that(&decoder.decode(&[2, 0])).is_none();
+    }
+}
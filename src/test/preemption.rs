@@ -0,0 +1,154 @@
+//! Scenario which verifies that product pods preempt filler pods
+
+use super::prelude::TestKubeClient;
+use super::pressure::BalloonPod;
+use super::temporary_resource::TemporaryResource;
+use indoc::formatdoc;
+use k8s_openapi::api::core::v1::{Node, Pod};
+use k8s_openapi::api::scheduling::v1::PriorityClass;
+use kube::ResourceExt;
+use std::thread;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Priority of the filler pods which is lower than the default
+/// priority 0 of pods without a priority class
+pub const FILLER_PRIORITY: i32 = -100;
+
+/// A scenario which verifies that product pods preempt low-priority
+/// filler pods
+///
+/// The given nodes are filled with balloon pods of a temporary
+/// priority class with the priority [`FILLER_PRIORITY`]. Then the
+/// product is deployed. Its pods can only be scheduled if they preempt
+/// the filler pods, which requires a higher priority.
+///
+/// ```rust,no_run
+/// use integration_test_commons::test::prelude::*;
+/// use integration_test_commons::test::preemption::PreemptionScenario;
+///
+/// let client = TestKubeClient::new();
+/// let nodes = client.list_labeled::<Node>("kubernetes.io/os=linux").items;
+///
+/// PreemptionScenario::new(&client, nodes).run("app.kubernetes.io/name=zookeeper", || {
+///     // deploy the product
+/// });
+/// ```
+pub struct PreemptionScenario<'a> {
+    client: &'a TestKubeClient,
+    nodes: Vec<Node>,
+    fraction: f64,
+}
+
+impl<'a> PreemptionScenario<'a> {
+    /// Creates a scenario which fills the given nodes.
+    ///
+    /// The filler pods request 80 % of the allocatable resources of each
+    /// node by default.
+    pub fn new(client: &'a TestKubeClient, nodes: Vec<Node>) -> Self {
+        PreemptionScenario {
+            client,
+            nodes,
+            fraction: 0.8,
+        }
+    }
+
+    /// Sets the fraction of the allocatable resources of each node which
+    /// is requested by the filler pods.
+    pub fn fraction(mut self, fraction: f64) -> Self {
+        self.fraction = fraction;
+        self
+    }
+
+    /// Fills the nodes, deploys the product with the given function, and
+    /// verifies that the product pods matching the label selector become
+    /// ready and that filler pods were preempted.
+    pub fn run<F>(self, product_pod_selector: &str, deploy: F)
+    where
+        F: FnOnce(),
+    {
+        let priority_class = filler_priority_class(self.client);
+
+        let fillers = self
+            .nodes
+            .iter()
+            .map(|node| {
+                BalloonPod::balloon_with_priority_class(
+                    self.client,
+                    node,
+                    self.fraction,
+                    &priority_class.name(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        deploy();
+
+        let product_pods = self.wait_for_product_pods(product_pod_selector);
+        for pod in &product_pods {
+            self.client.verify_pod_condition(pod, "Ready");
+        }
+
+        let preempted_fillers = fillers
+            .iter()
+            .filter(|filler| is_preempted(self.client, filler))
+            .count();
+        assert!(
+            preempted_fillers > 0,
+            "The product pods did not preempt any of the {} filler pods",
+            fillers.len()
+        );
+    }
+
+    /// Waits until pods match the given label selector within the
+    /// rollout timeout of the client and returns them.
+    ///
+    /// The product pods are created asynchronously by the operator and
+    /// the workload controllers after the product was deployed.
+    fn wait_for_product_pods(&self, product_pod_selector: &str) -> Vec<Pod> {
+        let timeout = self.client.kube_client().timeouts.rollout;
+        let start = Instant::now();
+        loop {
+            let product_pods = self.client.list_labeled::<Pod>(product_pod_selector).items;
+            if !product_pods.is_empty() {
+                return product_pods;
+            }
+            assert!(
+                start.elapsed() < timeout,
+                "No product pods match the selector [{}] within {} seconds",
+                product_pod_selector,
+                timeout.as_secs()
+            );
+            thread::sleep(Duration::from_secs(2));
+        }
+    }
+}
+
+/// Returns true if the given filler pod was deleted or is being deleted.
+fn is_preempted(client: &TestKubeClient, filler: &TemporaryResource<Pod>) -> bool {
+    let name = filler.metadata.name.as_deref().unwrap_or_default();
+    client
+        .find_namespaced::<Pod>(name)
+        .map_or(true, |pod| pod.metadata.deletion_timestamp.is_some())
+}
+
+/// Creates a priority class for filler pods which is deleted when it
+/// goes out of scope.
+fn filler_priority_class(client: &TestKubeClient) -> TemporaryResource<'_, PriorityClass> {
+    TemporaryResource::new(
+        client,
+        &formatdoc!(
+            "
+                apiVersion: scheduling.k8s.io/v1
+                kind: PriorityClass
+                metadata:
+                  name: filler-{uuid}
+                value: {value}
+                preemptionPolicy: Never
+                description: Filler pods of integration tests which can be preempted
+            ",
+            uuid = Uuid::new_v4(),
+            value = FILLER_PRIORITY
+        ),
+    )
+}
@@ -1,11 +1,56 @@
 //! Prelude for integration tests
+//!
+//! The prelude re-exports the clients, assertions, fixtures, and the
+//! commonly needed Kubernetes types. Test files which only need the
+//! basics can use [`minimal`] instead.
 
+pub use super::api_discovery::{DiscoveredGroup, ServedResource};
 pub use super::assertions::*;
+pub use super::bootstrap::*;
+pub use super::capacity::{ClusterCapacity, StorageCapacity};
+pub use super::cluster_lock::ClusterLock;
+pub use super::connectivity::{ClusterUnreachable, ReconnectPolicy};
+pub use super::deadline::TestDeadline;
+pub use super::environment::{report_environment_once, EnvironmentReport};
+pub use super::ip_family::{endpoint_address, IpFamilies};
 pub use super::kube::*;
+pub use super::load::DataGenerator;
+pub use super::metrics::{ContainerMetrics, NodeMetrics, PodMetrics};
+#[cfg(feature = "port-forward")]
+pub use super::port_forward::PortForward;
+pub use super::pressure::BalloonPod;
+pub use super::printer_columns::{render_printer_columns, verify_printer_columns};
+pub use super::quantity::Resources;
 pub use super::repository::*;
-pub use super::temporary_resource::TemporaryResource;
+pub use super::rng::TestRng;
+pub use super::rollout::Rollout;
+pub use super::shared_fixture::SharedFixture;
+pub use super::skip::{skip, skippable};
+pub use super::snapshot::{ClusterSnapshot, SnapshotDiff};
+pub use super::table::Table;
+pub use super::temporary_resource::{TemporaryConfigMap, TemporaryResource, TemporarySecret};
+pub use crate::operator::setup::{
+    TestCluster, TestClusterLabels, TestClusterOptions, TestClusterTimeouts,
+};
 
 pub use indoc::{formatdoc, indoc};
+pub use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, ReplicaSet, StatefulSet};
 pub use k8s_openapi::api::core::v1::*;
+pub use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, Time};
+pub use kube::core::{DynamicObject, GroupVersionKind};
 pub use serde_json::json;
 pub use spectral::prelude::*;
+pub use std::time::Duration;
+
+/// Minimal prelude which only contains the client, the temporary
+/// resources, the assertions, and the most common Kubernetes types
+pub mod minimal {
+    pub use super::super::assertions::{ExtendedVecAssertions, PodAssertions, ValueAssertions};
+    pub use super::super::kube::{from_yaml, with_unique_name, TestKubeClient};
+    pub use super::super::temporary_resource::TemporaryResource;
+
+    pub use indoc::indoc;
+    pub use k8s_openapi::api::apps::v1::StatefulSet;
+    pub use k8s_openapi::api::core::v1::{ConfigMap, Event, Pod, Secret, Service};
+    pub use spectral::prelude::*;
+}
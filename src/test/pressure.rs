@@ -0,0 +1,162 @@
+//! Simulation of resource pressure on nodes
+//!
+//! Balloon pods request a fraction of the allocatable CPU and memory of
+//! a node without using it. Further pods which do not fit on the node
+//! stay pending, so the behavior of operators under resource pressure
+//! can be tested.
+
+use super::prelude::TestKubeClient;
+pub use super::quantity::{parse_cpu_millis, parse_memory_bytes};
+use super::temporary_resource::TemporaryResource;
+use anyhow::{anyhow, Result};
+use k8s_openapi::api::core::v1::{Node, Pod};
+use serde_json::json;
+use uuid::Uuid;
+
+/// Image of the balloon pods which does nothing
+pub const BALLOON_IMAGE: &str = "k8s.gcr.io/pause:3.5";
+
+/// A pod which requests resources of a node and is deleted when it goes
+/// out of scope
+pub type BalloonPod<'a> = TemporaryResource<'a, Pod>;
+
+impl<'a> TemporaryResource<'a, Pod> {
+    /// Creates a balloon pod on the given node which requests the given
+    /// fraction of the allocatable CPU and memory of the node and waits
+    /// until it is ready.
+    ///
+    /// Resources which are already requested by other pods are not
+    /// taken into account, so the balloon pod stays pending if the
+    /// fraction exceeds the free resources of the node.
+    pub fn balloon(client: &'a TestKubeClient, node: &Node, fraction: f64) -> Self {
+        TemporaryResource::create_balloon(client, node, fraction, None)
+    }
+
+    /// Creates a balloon pod like [`TemporaryResource::balloon`] with the
+    /// given priority class.
+    ///
+    /// Balloon pods with a low priority are preempted by pods with a
+    /// higher priority.
+    pub fn balloon_with_priority_class(
+        client: &'a TestKubeClient,
+        node: &Node,
+        fraction: f64,
+        priority_class: &str,
+    ) -> Self {
+        TemporaryResource::create_balloon(client, node, fraction, Some(priority_class))
+    }
+
+    fn create_balloon(
+        client: &'a TestKubeClient,
+        node: &Node,
+        fraction: f64,
+        priority_class: Option<&str>,
+    ) -> Self {
+        let spec = balloon_spec(node, fraction, priority_class)
+            .expect("Balloon pod could not be specified");
+        let balloon = TemporaryResource::new(client, &spec);
+        client.verify_pod_condition(&balloon, "Ready");
+        balloon
+    }
+}
+
+/// Returns the specification of a balloon pod which requests the given
+/// fraction of the allocatable resources of the node.
+fn balloon_spec(node: &Node, fraction: f64, priority_class: Option<&str>) -> Result<String> {
+    if !(0.0..=1.0).contains(&fraction) {
+        return Err(anyhow!("Fraction [{}] is not between 0 and 1.", fraction));
+    }
+
+    let node_name = node
+        .metadata
+        .name
+        .as_deref()
+        .ok_or_else(|| anyhow!("Node has no name."))?;
+    let allocatable = node
+        .status
+        .as_ref()
+        .and_then(|status| status.allocatable.as_ref())
+        .ok_or_else(|| anyhow!("Node [{}] has no allocatable resources.", node_name))?;
+    let allocatable_resource = |resource: &str| {
+        allocatable
+            .get(resource)
+            .map(|quantity| quantity.0.as_str())
+            .ok_or_else(|| anyhow!("Node [{}] has no allocatable {}.", node_name, resource))
+    };
+
+    let cpu_millis = parse_cpu_millis(allocatable_resource("cpu")?)? as f64 * fraction;
+    let memory_bytes = parse_memory_bytes(allocatable_resource("memory")?)? as f64 * fraction;
+    let resources = json!({
+        "cpu": format!("{}m", cpu_millis as u64),
+        "memory": (memory_bytes as u64).to_string(),
+    });
+
+    let mut pod = json!({
+        "apiVersion": "v1",
+        "kind": "Pod",
+        "metadata": { "name": format!("balloon-{}", Uuid::new_v4()) },
+        "spec": {
+            "affinity": {
+                "nodeAffinity": {
+                    "requiredDuringSchedulingIgnoredDuringExecution": {
+                        "nodeSelectorTerms": [{
+                            "matchFields": [{
+                                "key": "metadata.name",
+                                "operator": "In",
+                                "values": [node_name],
+                            }],
+                        }],
+                    },
+                },
+            },
+            "tolerations": [{ "operator": "Exists" }],
+            "containers": [{
+                "name": "balloon",
+                "image": BALLOON_IMAGE,
+                "resources": { "requests": resources, "limits": resources },
+            }],
+        },
+    });
+
+    if let Some(priority_class) = priority_class {
+        pod["spec"]["priorityClassName"] = json!(priority_class);
+    }
+
+    Ok(serde_yaml::to_string(&pod)?)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test::kube::from_yaml;
+    use spectral::prelude::*;
+
+    #[test]
+    fn balloon_spec_should_request_the_fraction_of_the_allocatable_resources() {
+        let node: Node = from_yaml(
+            "
+            metadata:
+              name: node-1
+            status:
+              allocatable:
+                cpu: '4'
+                memory: 8Gi
+            ",
+        );
+
+        let pod: Pod = from_yaml(&balloon_spec(&node, 0.5, Some("filler")).unwrap());
+
+        let spec = pod.spec.unwrap();
+        assert_that(&serde_json::to_value(&spec.affinity).unwrap().to_string()).contains("node-1");
+        let requests = spec.containers[0]
+            .resources
+            .as_ref()
+            .and_then(|resources| resources.requests.clone())
+            .unwrap();
+        assert_that(&requests["cpu"].0).is_equal_to(String::from("2000m"));
+        assert_that(&requests["memory"].0).is_equal_to((4u64 << 30).to_string());
+        assert_that(&spec.priority_class_name).contains_value(String::from("filler"));
+        assert_that(&balloon_spec(&node, 1.5, None)).is_err();
+    }
+}
@@ -0,0 +1,212 @@
+//! Verification of the additional printer columns of custom resources
+//!
+//! The additional printer columns of a custom resource definition
+//! determine what users see in `kubectl get`. Their JSONPaths are
+//! evaluated against a custom resource like kubectl does. A subset of
+//! the JSONPath syntax is supported which covers the usual printer
+//! columns: fields (`.status.phase`), array indices
+//! (`.spec.servers[0]`), and equality filters
+//! (`.status.conditions[?(@.type=="Ready")].status`).
+
+use anyhow::{anyhow, Result};
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use kube::Resource;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Renders the additional printer columns of the given custom resource
+/// definition for the given custom resource.
+///
+/// The columns are taken from the version of the custom resource. The
+/// values are rendered as raw values, i.e. dates are not converted to
+/// ages. Missing values are rendered as empty strings.
+pub fn render_printer_columns<K>(
+    crd: &CustomResourceDefinition,
+    resource: &K,
+) -> Result<BTreeMap<String, String>>
+where
+    K: Resource<DynamicType = ()> + Serialize,
+{
+    let version = K::version(&());
+    let crd_version = crd
+        .spec
+        .versions
+        .iter()
+        .find(|crd_version| crd_version.name == version)
+        .ok_or_else(|| {
+            anyhow!(
+                "Custom resource definition [{}] has no version [{}].",
+                crd.spec.names.kind,
+                version
+            )
+        })?;
+
+    let resource = serde_json::to_value(resource)?;
+
+    crd_version
+        .additional_printer_columns
+        .iter()
+        .flatten()
+        .map(|column| {
+            let values = evaluate_json_path(&resource, &column.json_path)?;
+            Ok((column.name.clone(), render(&values)))
+        })
+        .collect()
+}
+
+/// Verifies that the additional printer columns of the given custom
+/// resource definition render the expected values for the given custom
+/// resource.
+///
+/// Columns which are not expected are ignored.
+pub fn verify_printer_columns<K>(
+    crd: &CustomResourceDefinition,
+    resource: &K,
+    expected_columns: &[(&str, &str)],
+) where
+    K: Resource<DynamicType = ()> + Serialize,
+{
+    let columns =
+        render_printer_columns(crd, resource).expect("Printer columns could not be rendered");
+
+    for (name, expected_value) in expected_columns {
+        match columns.get(*name) {
+            Some(value) => assert_eq!(
+                value, expected_value,
+                "Printer column [{}] has an unexpected value",
+                name
+            ),
+            None => panic!(
+                "Printer column [{}] is not defined; defined columns are {:?}",
+                name,
+                columns.keys().collect::<Vec<_>>()
+            ),
+        }
+    }
+}
+
+/// Renders the values like kubectl does, i.e. strings without quotes
+/// and multiple values separated by commas.
+fn render(values: &[&Value]) -> String {
+    values
+        .iter()
+        .map(|value| match value {
+            Value::String(string) => string.clone(),
+            Value::Null => String::new(),
+            value => value.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Evaluates the given JSONPath against the value and returns all
+/// matching values.
+fn evaluate_json_path<'a>(value: &'a Value, json_path: &str) -> Result<Vec<&'a Value>> {
+    let mut values = vec![value];
+    let mut rest = json_path.trim();
+
+    while !rest.is_empty() {
+        if let Some(field_path) = rest.strip_prefix('.') {
+            let end = field_path
+                .find(['.', '['].as_ref())
+                .unwrap_or(field_path.len());
+            let field = &field_path[..end];
+            values = values
+                .into_iter()
+                .filter_map(|value| value.get(field))
+                .collect();
+            rest = &field_path[end..];
+        } else if let Some(index_path) = rest.strip_prefix('[') {
+            let end = index_path
+                .find(']')
+                .ok_or_else(|| anyhow!("JSONPath [{}] has an unclosed bracket.", json_path))?;
+            let selector = &index_path[..end];
+            values = select(values, selector)
+                .ok_or_else(|| anyhow!("JSONPath [{}] is not supported.", json_path))?;
+            rest = &index_path[end + 1..];
+        } else {
+            return Err(anyhow!("JSONPath [{}] is not supported.", json_path));
+        }
+    }
+
+    Ok(values)
+}
+
+/// Applies the given array selector, i.e. an index, a wildcard, or an
+/// equality filter, to the values.
+fn select<'a>(values: Vec<&'a Value>, selector: &str) -> Option<Vec<&'a Value>> {
+    let elements = values.into_iter().filter_map(Value::as_array).flatten();
+
+    if selector == "*" {
+        Some(elements.collect())
+    } else if let Ok(index) = selector.parse::<usize>() {
+        let mut elements = elements;
+        Some(elements.nth(index).into_iter().collect())
+    } else {
+        let filter = selector.strip_prefix("?(@.")?.strip_suffix(')')?;
+        let (field, expected) = filter.split_once("==")?;
+        let expected = expected.trim().trim_matches(|c| c == '"' || c == '\'');
+        Some(
+            elements
+                .filter(|element| {
+                    element.get(field.trim()).and_then(Value::as_str) == Some(expected)
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json::json;
+    use spectral::prelude::*;
+
+    fn cluster() -> Value {
+        json!({
+            "spec": { "replicas": 3, "servers": ["a", "b"] },
+            "status": {
+                "conditions": [
+                    { "type": "Available", "status": "False" },
+                    { "type": "Ready", "status": "True" }
+                ]
+            }
+        })
+    }
+
+    #[test]
+    fn evaluate_json_path_should_support_fields_indices_and_filters() {
+        let cluster = cluster();
+
+        assert_that(&render(
+            &evaluate_json_path(&cluster, ".spec.replicas").unwrap(),
+        ))
+        .is_equal_to(String::from("3"));
+        assert_that(&render(
+            &evaluate_json_path(&cluster, ".spec.servers[1]").unwrap(),
+        ))
+        .is_equal_to(String::from("b"));
+        assert_that(&render(
+            &evaluate_json_path(&cluster, ".spec.servers[*]").unwrap(),
+        ))
+        .is_equal_to(String::from("a,b"));
+        assert_that(&render(
+            &evaluate_json_path(&cluster, r#".status.conditions[?(@.type=="Ready")].status"#)
+                .unwrap(),
+        ))
+        .is_equal_to(String::from("True"));
+        assert_that(&render(
+            &evaluate_json_path(&cluster, ".status.phase").unwrap(),
+        ))
+        .is_equal_to(String::new());
+    }
+
+    #[test]
+    fn evaluate_json_path_should_reject_unsupported_expressions() {
+        assert_that(&evaluate_json_path(&cluster(), "{.spec}")).is_err();
+        assert_that(&evaluate_json_path(&cluster(), ".spec.servers[0")).is_err();
+        assert_that(&evaluate_json_path(&cluster(), ".spec.servers[-1:]")).is_err();
+    }
+}
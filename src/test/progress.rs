@@ -0,0 +1,297 @@
+//! Progress of long-running waits
+//!
+//! Wait loops, e.g. until the pods of a cluster are ready, report their
+//! progress to a [`ProgressReporter`] instead of printing it directly.
+//! The reporter is selected with the environment variable
+//! `KUBE_TEST_PROGRESS`:
+//!
+//! * `console` prints the progress to stdout. This is the default.
+//! * `github` prints the progress of every wait in a collapsible group
+//!   of the GitHub Actions log. This is the default if the variable
+//!   `GITHUB_ACTIONS` is `true`.
+//! * `tracing` emits the progress as tracing events if the feature
+//!   `tracing-progress` is enabled.
+//! * `quiet` suppresses the progress.
+//!
+//! Test suites can also install their own reporter:
+//!
+//! ```rust
+//! use integration_test_commons::test::progress::{set_progress_reporter, QuietReporter};
+//!
+//! set_progress_reporter(QuietReporter);
+//! ```
+
+use once_cell::sync::Lazy;
+use std::cell::Cell;
+use std::env;
+use std::sync::{Arc, RwLock};
+
+/// Name of the environment variable which selects the progress reporter
+pub const PROGRESS_ENV_VAR: &str = "KUBE_TEST_PROGRESS";
+
+/// Name of the environment variable which GitHub Actions sets to `true`
+const GITHUB_ACTIONS_ENV_VAR: &str = "GITHUB_ACTIONS";
+
+static REPORTER: Lazy<RwLock<Arc<dyn ProgressReporter>>> = Lazy::new(|| {
+    RwLock::new(parse_reporter(
+        env::var(PROGRESS_ENV_VAR).ok().as_deref(),
+        env::var(GITHUB_ACTIONS_ENV_VAR).as_deref() == Ok("true"),
+    ))
+});
+
+/// Receiver of the progress of waits
+///
+/// Waits can be nested, e.g. the steps of a scenario wait for the pods
+/// of a cluster, and waits of different test cases run concurrently in
+/// different threads.
+pub trait ProgressReporter: Send + Sync {
+    /// Reports that a wait with the given title started.
+    fn start(&self, title: &str);
+
+    /// Reports the progress of the current wait.
+    fn update(&self, message: &str);
+
+    /// Reports that the current wait finished with the given message or
+    /// was aborted if it is `None`.
+    fn finish(&self, message: Option<&str>);
+}
+
+/// Reporter which prints the progress to stdout
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ConsoleReporter;
+
+impl ProgressReporter for ConsoleReporter {
+    fn start(&self, title: &str) {
+        println!("{}", title);
+    }
+
+    fn update(&self, message: &str) {
+        println!("{}", message);
+    }
+
+    fn finish(&self, message: Option<&str>) {
+        if let Some(message) = message {
+            println!("{}", message);
+        }
+    }
+}
+
+/// Reporter which prints the progress of the outermost wait of a thread
+/// in a collapsible group of the GitHub Actions log
+///
+/// GitHub Actions does not support nested groups, so the titles of
+/// inner waits are printed as plain lines into the outer group.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GitHubActionsReporter;
+
+thread_local! {
+    static GROUP_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+impl ProgressReporter for GitHubActionsReporter {
+    fn start(&self, title: &str) {
+        let depth = GROUP_DEPTH.with(|depth| depth.replace(depth.get() + 1));
+        if depth == 0 {
+            println!("::group::{}", title);
+        } else {
+            println!("{}", title);
+        }
+    }
+
+    fn update(&self, message: &str) {
+        println!("{}", message);
+    }
+
+    fn finish(&self, message: Option<&str>) {
+        let depth = GROUP_DEPTH.with(|depth| {
+            depth.set(depth.get().saturating_sub(1));
+            depth.get()
+        });
+        if depth == 0 {
+            println!("::endgroup::");
+        }
+        if let Some(message) = message {
+            println!("{}", message);
+        }
+    }
+}
+
+/// Reporter which emits the progress as tracing events of the target
+/// `integration_test_commons::progress`
+#[cfg(feature = "tracing-progress")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TracingReporter;
+
+#[cfg(feature = "tracing-progress")]
+impl ProgressReporter for TracingReporter {
+    fn start(&self, title: &str) {
+        tracing::info!(target: "integration_test_commons::progress", "{}", title);
+    }
+
+    fn update(&self, message: &str) {
+        tracing::debug!(target: "integration_test_commons::progress", "{}", message);
+    }
+
+    fn finish(&self, message: Option<&str>) {
+        if let Some(message) = message {
+            tracing::info!(target: "integration_test_commons::progress", "{}", message);
+        }
+    }
+}
+
+/// Reporter which suppresses the progress
+#[derive(Clone, Copy, Debug, Default)]
+pub struct QuietReporter;
+
+impl ProgressReporter for QuietReporter {
+    fn start(&self, _title: &str) {}
+
+    fn update(&self, _message: &str) {}
+
+    fn finish(&self, _message: Option<&str>) {}
+}
+
+/// Replaces the progress reporter for all subsequent waits of all
+/// threads.
+pub fn set_progress_reporter<R>(reporter: R)
+where
+    R: ProgressReporter + 'static,
+{
+    *REPORTER.write().unwrap() = Arc::new(reporter);
+}
+
+/// Returns the current progress reporter.
+pub fn progress_reporter() -> Arc<dyn ProgressReporter> {
+    REPORTER.read().unwrap().clone()
+}
+
+/// A wait in progress
+///
+/// The wait is reported as aborted if it is dropped before it was
+/// finished, e.g. because of a timeout, so that the reporter can close
+/// the group of the wait.
+pub struct Progress {
+    reporter: Arc<dyn ProgressReporter>,
+    finished: bool,
+}
+
+impl Progress {
+    /// Reports the start of a wait with the given title to the current
+    /// progress reporter.
+    pub fn start(title: &str) -> Progress {
+        let reporter = progress_reporter();
+        reporter.start(title);
+        Progress {
+            reporter,
+            finished: false,
+        }
+    }
+
+    /// Reports the progress of the wait.
+    pub fn update(&self, message: &str) {
+        self.reporter.update(message);
+    }
+
+    /// Reports that the wait finished with the given message.
+    pub fn finish(mut self, message: &str) {
+        self.finished = true;
+        self.reporter.finish(Some(message));
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.reporter.finish(None);
+        }
+    }
+}
+
+/// Returns the reporter with the given name or the default one for the
+/// environment if no name is given.
+fn parse_reporter(name: Option<&str>, github_actions: bool) -> Arc<dyn ProgressReporter> {
+    match name {
+        Some("console") => Arc::new(ConsoleReporter),
+        Some("github") => Arc::new(GitHubActionsReporter),
+        #[cfg(feature = "tracing-progress")]
+        Some("tracing") => Arc::new(TracingReporter),
+        Some("quiet") => Arc::new(QuietReporter),
+        Some(name) => panic!(
+            "{} must be one of console, github, {}or quiet but is [{}]",
+            PROGRESS_ENV_VAR,
+            if cfg!(feature = "tracing-progress") {
+                "tracing, "
+            } else {
+                ""
+            },
+            name
+        ),
+        None if github_actions => Arc::new(GitHubActionsReporter),
+        None => Arc::new(ConsoleReporter),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use spectral::prelude::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordingReporter {
+        records: Mutex<Vec<String>>,
+    }
+
+    impl ProgressReporter for RecordingReporter {
+        fn start(&self, title: &str) {
+            self.records
+                .lock()
+                .unwrap()
+                .push(format!("start {}", title));
+        }
+
+        fn update(&self, message: &str) {
+            self.records
+                .lock()
+                .unwrap()
+                .push(format!("update {}", message));
+        }
+
+        fn finish(&self, message: Option<&str>) {
+            self.records
+                .lock()
+                .unwrap()
+                .push(format!("finish {}", message.unwrap_or("aborted")));
+        }
+    }
+
+    #[test]
+    fn progress_should_report_aborted_waits_when_dropped() {
+        let recorder = Arc::new(RecordingReporter::default());
+        let reporter: Arc<dyn ProgressReporter> = recorder.clone();
+
+        let progress = Progress {
+            reporter: reporter.clone(),
+            finished: false,
+        };
+        progress.update("waiting");
+        progress.finish("done");
+        drop(Progress {
+            reporter,
+            finished: false,
+        });
+
+        assert_that(&*recorder.records.lock().unwrap()).is_equal_to(vec![
+            String::from("update waiting"),
+            String::from("finish done"),
+            String::from("finish aborted"),
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "KUBE_TEST_PROGRESS must be one of console, github, ")]
+    fn parse_reporter_should_reject_unknown_reporters() {
+        parse_reporter(Some("verbose"), false);
+    }
+}
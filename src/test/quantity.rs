@@ -0,0 +1,215 @@
+//! Parsing of resource quantities
+
+use anyhow::{anyhow, Result};
+use k8s_openapi::api::core::v1::{Container, ResourceRequirements};
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+/// CPU and memory requests and limits of a container
+///
+/// CPU quantities are given in millicores and memory quantities in
+/// bytes, so that equal quantities in different notations, e.g. `1` and
+/// `1000m` or `1Gi` and `1024Mi`, are equal.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Resources {
+    pub cpu_request_millis: Option<u64>,
+    pub cpu_limit_millis: Option<u64>,
+    pub memory_request_bytes: Option<u64>,
+    pub memory_limit_bytes: Option<u64>,
+}
+
+impl Resources {
+    /// Parses the requests and limits of the given container.
+    pub fn from_container(container: &Container) -> Result<Self> {
+        Resources::from_requirements(&container.resources.clone().unwrap_or_default())
+    }
+
+    /// Parses the given resource requirements.
+    pub fn from_requirements(requirements: &ResourceRequirements) -> Result<Self> {
+        let quantity = |quantities: &Option<BTreeMap<String, Quantity>>,
+                        resource: &str,
+                        parse: fn(&str) -> Result<u64>| {
+            quantities
+                .as_ref()
+                .and_then(|quantities| quantities.get(resource))
+                .map(|quantity| parse(&quantity.0))
+                .transpose()
+        };
+
+        Ok(Resources {
+            cpu_request_millis: quantity(&requirements.requests, "cpu", parse_cpu_millis)?,
+            cpu_limit_millis: quantity(&requirements.limits, "cpu", parse_cpu_millis)?,
+            memory_request_bytes: quantity(&requirements.requests, "memory", parse_memory_bytes)?,
+            memory_limit_bytes: quantity(&requirements.limits, "memory", parse_memory_bytes)?,
+        })
+    }
+}
+
+/// Parses a CPU quantity like `4`, `0.5`, `3800m`, or `250000n` into
+/// millicores.
+///
+/// The metrics-server reports the CPU usage in nanocores or
+/// microcores. These values are rounded down to whole millicores.
+/// Negative quantities are rejected.
+pub fn parse_cpu_millis(quantity: &str) -> Result<u64> {
+    // suffix, numerator, and denominator of the factor to millicores
+    const SUFFIXES: &[(&str, u128, u128)] = &[("n", 1, 1_000_000), ("u", 1, 1_000), ("m", 1, 1)];
+
+    if quantity.starts_with('-') {
+        return Err(anyhow!("[{}] is a negative CPU quantity.", quantity));
+    }
+
+    let (number, numerator, denominator) = SUFFIXES
+        .iter()
+        .find_map(|(suffix, numerator, denominator)| {
+            quantity
+                .strip_suffix(suffix)
+                .map(|number| (number, *numerator, *denominator))
+        })
+        .unwrap_or((quantity, 1000, 1));
+
+    parse_decimal(number, numerator, denominator)
+        .ok_or_else(|| anyhow!("[{}] is not a valid CPU quantity.", quantity))
+}
+
+/// Parses a memory quantity like `16Gi`, `16393216Ki`, or `1G` into
+/// bytes.
+///
+/// Fractions of bytes are rounded down. Negative quantities are
+/// rejected.
+pub fn parse_memory_bytes(quantity: &str) -> Result<u64> {
+    const SUFFIXES: &[(&str, u128)] = &[
+        ("Ki", 1 << 10),
+        ("Mi", 1 << 20),
+        ("Gi", 1 << 30),
+        ("Ti", 1 << 40),
+        ("Pi", 1 << 50),
+        ("Ei", 1 << 60),
+        ("k", 1_000),
+        ("M", 1_000_000),
+        ("G", 1_000_000_000),
+        ("T", 1_000_000_000_000),
+        ("P", 1_000_000_000_000_000),
+        ("E", 1_000_000_000_000_000_000),
+    ];
+
+    if quantity.starts_with('-') {
+        return Err(anyhow!("[{}] is a negative memory quantity.", quantity));
+    }
+
+    let (number, multiplier) = SUFFIXES
+        .iter()
+        .find_map(|(suffix, multiplier)| {
+            quantity
+                .strip_suffix(suffix)
+                .map(|number| (number, *multiplier))
+        })
+        .unwrap_or((quantity, 1));
+
+    parse_decimal(number, multiplier, 1)
+        .ok_or_else(|| anyhow!("[{}] is not a valid memory quantity.", quantity))
+}
+
+/// Parses a non-negative decimal number like `2.01`, `.5`, or `1e3`
+/// exactly and returns it multiplied by `numerator / denominator` and
+/// rounded down.
+///
+/// Floating-point arithmetic is avoided because e.g. `2.01 * 1000`
+/// results in `2009.9999999999998`.
+fn parse_decimal(number: &str, numerator: u128, denominator: u128) -> Option<u64> {
+    let number = number.strip_prefix('+').unwrap_or(number);
+    let (mantissa, exponent) = match number.find(['e', 'E']) {
+        Some(index) => (&number[..index], number[index + 1..].parse::<i32>().ok()?),
+        None => (number, 0),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+    let digits = format!("{}{}", integer, fraction);
+    if digits.is_empty() || digits.len() > 30 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let value = digits.parse::<u128>().ok()?.checked_mul(numerator)?;
+    let scale = exponent - fraction.len() as i32;
+    let power = 10u128.checked_pow(scale.unsigned_abs())?;
+    let result = if scale >= 0 {
+        value.checked_mul(power)? / denominator
+    } else {
+        value / power.checked_mul(denominator)?
+    };
+
+    u64::try_from(result).ok()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test::kube::from_yaml;
+    use spectral::prelude::*;
+
+    #[test]
+    fn quantities_should_be_parsed() {
+        assert_that(&parse_cpu_millis("4").unwrap()).is_equal_to(4000);
+        assert_that(&parse_cpu_millis("0.5").unwrap()).is_equal_to(500);
+        assert_that(&parse_cpu_millis("3800m").unwrap()).is_equal_to(3800);
+        assert_that(&parse_cpu_millis("250000u").unwrap()).is_equal_to(250);
+        assert_that(&parse_cpu_millis("1500000000n").unwrap()).is_equal_to(1500);
+        assert_that(&parse_cpu_millis("many")).is_err();
+        assert_that(&parse_cpu_millis("")).is_err();
+        assert_that(&parse_cpu_millis("1.2.3")).is_err();
+
+        assert_that(&parse_memory_bytes("16Gi").unwrap()).is_equal_to(16 << 30);
+        assert_that(&parse_memory_bytes("2048Ki").unwrap()).is_equal_to(2 << 20);
+        assert_that(&parse_memory_bytes("1G").unwrap()).is_equal_to(1_000_000_000);
+        assert_that(&parse_memory_bytes("1024").unwrap()).is_equal_to(1024);
+        assert_that(&parse_memory_bytes("lots")).is_err();
+    }
+
+    #[test]
+    fn decimal_quantities_should_be_parsed_exactly() {
+        assert_that(&parse_cpu_millis("2.01").unwrap()).is_equal_to(2010);
+        assert_that(&parse_cpu_millis("0.1").unwrap()).is_equal_to(100);
+        assert_that(&parse_cpu_millis(".5").unwrap()).is_equal_to(500);
+        assert_that(&parse_cpu_millis("1e-1").unwrap()).is_equal_to(100);
+        assert_that(&parse_cpu_millis("0.0005").unwrap()).is_equal_to(0);
+        assert_that(&parse_cpu_millis("1999999n").unwrap()).is_equal_to(1);
+
+        assert_that(&parse_memory_bytes("1.1Gi").unwrap()).is_equal_to(1_181_116_006);
+        assert_that(&parse_memory_bytes("0.5Ki").unwrap()).is_equal_to(512);
+        assert_that(&parse_memory_bytes("1e3").unwrap()).is_equal_to(1000);
+        assert_that(&parse_memory_bytes("1.5E").unwrap()).is_equal_to(1_500_000_000_000_000_000);
+        assert_that(&parse_memory_bytes("100E")).is_err();
+    }
+
+    #[test]
+    fn negative_quantities_should_be_rejected() {
+        assert_that(&parse_cpu_millis("-500m")).is_err();
+        assert_that(&parse_cpu_millis("-1")).is_err();
+        assert_that(&parse_memory_bytes("-1Gi")).is_err();
+        assert_that(&parse_memory_bytes("-0.5")).is_err();
+    }
+
+    #[test]
+    fn resources_should_be_parsed_from_the_container() {
+        let container: Container = from_yaml(
+            "
+            name: zookeeper
+            resources:
+              requests:
+                cpu: 500m
+                memory: 1024Mi
+              limits:
+                memory: 1Gi
+            ",
+        );
+
+        assert_that(&Resources::from_container(&container).unwrap()).is_equal_to(Resources {
+            cpu_request_millis: Some(500),
+            cpu_limit_millis: None,
+            memory_request_bytes: Some(1 << 30),
+            memory_limit_bytes: Some(1 << 30),
+        });
+    }
+}
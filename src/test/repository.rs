@@ -1,10 +1,10 @@
 //! Stackable repository
 
+use super::bootstrap::{bootstrap_once_in_cluster, bootstrap_once_in_cluster_async};
 use super::prelude::{KubeClient, TestKubeClient};
 use anyhow::Result;
 use kube::CustomResourceExt;
 use kube_derive::CustomResource;
-use once_cell::sync::OnceCell;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -42,20 +42,22 @@ pub enum RepoType {
 // Setting up the repository multiple times can cause issues with K3s,
 // so we ensure that the code is only executed once, regardless of how
 // many test cases try to create the repository.
-static REPO_CREATED: OnceCell<bool> = OnceCell::new();
+const REPO_BOOTSTRAP_KEY: &str = "stackable-repository";
 
-#[allow(unused_must_use)]
 pub fn setup_repository(client: &TestKubeClient) {
-    if REPO_CREATED.set(true).is_ok() {
+    bootstrap_once_in_cluster(client, REPO_BOOTSTRAP_KEY, || {
         client.apply_crd(&Repository::crd());
         client.apply::<Repository>(REPO_SPEC);
-    };
+        Ok(())
+    })
+    .expect("Repository could not be set up")
 }
 
 pub async fn setup_repository_async(client: &KubeClient) -> Result<()> {
-    if REPO_CREATED.set(true).is_ok() {
+    bootstrap_once_in_cluster_async(client, REPO_BOOTSTRAP_KEY, async {
         client.apply_crd(&Repository::crd()).await?;
         client.apply::<Repository>(REPO_SPEC).await?;
-    };
-    Ok(())
+        Ok(())
+    })
+    .await
 }
@@ -0,0 +1,185 @@
+//! Deterministic randomness for reproducible test runs
+//!
+//! Random values like generated passwords are derived from a seed. The
+//! seed is taken from the environment variable `INTEGRATION_TEST_SEED`
+//! or chosen randomly. It is printed if a test case which used seeded
+//! values fails so that a flaky test run can be reproduced exactly by
+//! setting the environment variable.
+//!
+//! Every thread, i.e. every test case run by `cargo test`, has its own
+//! random number generator which is seeded with the seed, the name of
+//! the thread, and the number of previous threads with the same name.
+//! Therefore the random values of a test case do not depend on the
+//! other test cases running in parallel.
+//!
+//! Names of resources are not derived from the seed but are random
+//! UUIDs, so that a reproduced test run does not collide with the
+//! leftovers of the original run.
+
+use crate::test::skip::skip_reason;
+use once_cell::sync::Lazy;
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::{env, panic, thread};
+use uuid::{Builder, Uuid, Variant, Version};
+
+/// Name of the environment variable which contains the seed
+pub const SEED_ENV_VAR: &str = "INTEGRATION_TEST_SEED";
+
+static SEED: Lazy<u64> = Lazy::new(|| {
+    let seed = env::var(SEED_ENV_VAR)
+        .ok()
+        .map(|seed| {
+            seed.parse()
+                .unwrap_or_else(|_| panic!("{} must be an unsigned integer", SEED_ENV_VAR))
+        })
+        .unwrap_or_else(|| rand::thread_rng().gen());
+
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        let is_used = SEED_USED.try_with(Cell::get).unwrap_or_default();
+        if is_used && skip_reason(info.payload()).is_none() {
+            eprintln!(
+                "Random values were generated with the seed {seed}. \
+                Set {env_var}={seed} to reproduce them.",
+                seed = seed,
+                env_var = SEED_ENV_VAR
+            );
+        }
+    }));
+
+    seed
+});
+
+/// Number of threads which were started so far per thread name
+static THREAD_INSTANCES: Lazy<Mutex<HashMap<String, u64>>> = Lazy::new(Default::default);
+
+thread_local! {
+    static TEST_RNG: RefCell<TestRng> = RefCell::new(TestRng::for_current_thread());
+    static SEED_USED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Returns the seed of all random number generators.
+pub fn seed() -> u64 {
+    *SEED
+}
+
+/// A random number generator which is seeded deterministically
+pub struct TestRng(StdRng);
+
+impl TestRng {
+    /// Creates a random number generator seeded with the seed, the name
+    /// of the current thread, and the number of previous threads with
+    /// this name.
+    fn for_current_thread() -> Self {
+        let thread_name = thread::current().name().unwrap_or_default().to_owned();
+        let instance = {
+            let mut instances = THREAD_INSTANCES.lock().unwrap();
+            let instance = instances.entry(thread_name.clone()).or_default();
+            *instance += 1;
+            *instance - 1
+        };
+        TestRng::for_thread(&thread_name, instance)
+    }
+
+    /// Creates a random number generator seeded with the seed and the
+    /// given thread name and instance.
+    fn for_thread(thread_name: &str, instance: u64) -> Self {
+        let key = format!("{}#{}", thread_name, instance);
+        TestRng(StdRng::seed_from_u64(seed() ^ fnv1a(&key)))
+    }
+
+    /// Calls the given function with the random number generator of the
+    /// current thread.
+    ///
+    /// The seed is printed if the current thread panics afterwards.
+    pub fn with<F, R>(f: F) -> R
+    where
+        F: FnOnce(&mut TestRng) -> R,
+    {
+        SEED_USED.with(|used| used.set(true));
+        TEST_RNG.with(|rng| f(&mut rng.borrow_mut()))
+    }
+
+    /// Returns a random UUID of version 4 generated by the random
+    /// number generator of the current thread.
+    ///
+    /// The UUID is reproduced with the seed, so use [`Uuid::new_v4`] for
+    /// names of resources.
+    pub fn uuid() -> Uuid {
+        let bytes = TestRng::with(|rng| rng.gen());
+        Builder::from_bytes(bytes)
+            .set_variant(Variant::RFC4122)
+            .set_version(Version::Random)
+            .build()
+    }
+}
+
+impl RngCore for TestRng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.0.try_fill_bytes(dest)
+    }
+}
+
+/// Hashes the given text with FNV-1a which is, in contrast to the
+/// default hasher of the standard library, stable across Rust
+/// versions.
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn random_number_generators_should_be_reproducible_per_thread_instance() {
+        let generate = |instance| {
+            let mut rng = TestRng::for_thread("test-thread", instance);
+            (rng.next_u64(), rng.next_u64())
+        };
+
+        let (first_value, second_value) = generate(0);
+
+        assert_that(&generate(0)).is_equal_to((first_value, second_value));
+        assert_that(&first_value).is_not_equal_to(second_value);
+        assert_that(&generate(1)).is_not_equal_to((first_value, second_value));
+    }
+
+    #[test]
+    fn threads_with_the_same_name_should_have_different_random_values() {
+        let generate = || {
+            thread::Builder::new()
+                .name(String::from("same-name"))
+                .spawn(TestRng::uuid)
+                .unwrap()
+                .join()
+                .unwrap()
+        };
+
+        let uuid = generate();
+
+        assert_that(&generate()).is_not_equal_to(uuid);
+        assert_that(&uuid.get_version()).contains_value(Version::Random);
+    }
+}
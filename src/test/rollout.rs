@@ -0,0 +1,324 @@
+//! Rollout status of workloads
+//!
+//! Changes of a custom resource must or must not trigger a rollout of
+//! the workloads of the operator. The revisions of the pods, which are
+//! stored in the `controller-revision-hash` and `pod-template-hash`
+//! labels, reveal whether a change produced a new pod template:
+//!
+//! ```rust,no_run
+//! use integration_test_commons::test::prelude::*;
+//! use integration_test_commons::test::rollout::verify_new_revision;
+//!
+//! let client = TestKubeClient::new();
+//! let stateful_set: StatefulSet = client.find("simple-server").unwrap();
+//! verify_new_revision(&client, &stateful_set, "app.kubernetes.io/instance=simple", || {
+//!     // change the configuration of the cluster
+//! });
+//! ```
+
+use super::prelude::TestKubeClient;
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, StatefulSet};
+use k8s_openapi::api::core::v1::Pod;
+use kube::{Resource, ResourceExt};
+use serde::de::DeserializeOwned;
+use std::collections::BTreeSet;
+use std::fmt::Debug;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Label with the revision of the pods of StatefulSets and DaemonSets
+pub const CONTROLLER_REVISION_HASH_LABEL: &str = "controller-revision-hash";
+
+/// Label with the revision of the pods of Deployments
+pub const POD_TEMPLATE_HASH_LABEL: &str = "pod-template-hash";
+
+/// A workload whose rollout status can be determined like
+/// `kubectl rollout status` does
+pub trait Rollout {
+    /// Returns true if the controller observed the current generation
+    /// and all replicas are updated and available.
+    fn is_rolled_out(&self) -> bool;
+}
+
+impl Rollout for Deployment {
+    fn is_rolled_out(&self) -> bool {
+        let desired = self
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.replicas)
+            .unwrap_or(1);
+        let status = self.status.clone().unwrap_or_default();
+
+        is_generation_observed(self.metadata.generation, status.observed_generation)
+            && status.updated_replicas.unwrap_or_default() == desired
+            && status.replicas.unwrap_or_default() == desired
+            && status.available_replicas.unwrap_or_default() == desired
+    }
+}
+
+impl Rollout for StatefulSet {
+    fn is_rolled_out(&self) -> bool {
+        let spec = self.spec.clone().unwrap_or_default();
+        let desired = spec.replicas.unwrap_or(1);
+        let partition = spec
+            .update_strategy
+            .and_then(|strategy| strategy.rolling_update)
+            .and_then(|rolling_update| rolling_update.partition)
+            .unwrap_or_default();
+        let status = self.status.clone().unwrap_or_default();
+
+        let is_updated = if partition > 0 {
+            status.updated_replicas.unwrap_or_default() >= desired - partition
+        } else {
+            status.update_revision.is_some() && status.update_revision == status.current_revision
+        };
+
+        is_generation_observed(self.metadata.generation, status.observed_generation)
+            && is_updated
+            && status.ready_replicas.unwrap_or_default() == desired
+    }
+}
+
+impl Rollout for DaemonSet {
+    fn is_rolled_out(&self) -> bool {
+        let status = self.status.clone().unwrap_or_default();
+        let desired = status.desired_number_scheduled;
+
+        is_generation_observed(self.metadata.generation, status.observed_generation)
+            && status.updated_number_scheduled.unwrap_or_default() == desired
+            && status.number_available.unwrap_or_default() == desired
+    }
+}
+
+fn is_generation_observed(generation: Option<i64>, observed_generation: Option<i64>) -> bool {
+    observed_generation >= generation
+}
+
+/// Returns the revision of the pod template from which the given pod
+/// was created.
+pub fn get_revision_hash(pod: &Pod) -> Option<&str> {
+    let labels = pod.metadata.labels.as_ref()?;
+    labels
+        .get(CONTROLLER_REVISION_HASH_LABEL)
+        .or_else(|| labels.get(POD_TEMPLATE_HASH_LABEL))
+        .map(String::as_str)
+}
+
+/// Returns the distinct revisions of the given pods ignoring pods which
+/// are terminating.
+pub fn get_revision_hashes(pods: &[Pod]) -> BTreeSet<String> {
+    pods.iter()
+        .filter(|pod| pod.metadata.deletion_timestamp.is_none())
+        .filter_map(get_revision_hash)
+        .map(String::from)
+        .collect()
+}
+
+/// Executes the given change and verifies that it produced a new
+/// revision of the given workload, i.e. that all pods matching the label
+/// selector were replaced with pods of a new revision after the rollout.
+pub fn verify_new_revision<K, F>(
+    client: &TestKubeClient,
+    workload: &K,
+    label_selector: &str,
+    change: F,
+) where
+    K: Clone + Debug + DeserializeOwned + Resource + Rollout,
+    <K as Resource>::DynamicType: Default,
+    F: FnOnce(),
+{
+    let (before, after) = revisions_around(client, workload, label_selector, change);
+    if after.is_empty() || !after.is_disjoint(&before) {
+        panic!(
+            "The change of [{}] did not produce a new revision; the revisions are {:?} before and {:?} after the change.",
+            workload.name(),
+            before,
+            after
+        );
+    }
+}
+
+/// Executes the given change and verifies that it did not produce a new
+/// revision of the given workload, i.e. that the pods matching the label
+/// selector have the same revisions after the change as before.
+///
+/// The change must be observed by the controller of the workload before
+/// this function returns, e.g. because it changed the specification of
+/// the workload. Otherwise a rollout which starts later is not
+/// detected.
+pub fn verify_same_revision<K, F>(
+    client: &TestKubeClient,
+    workload: &K,
+    label_selector: &str,
+    change: F,
+) where
+    K: Clone + Debug + DeserializeOwned + Resource + Rollout,
+    <K as Resource>::DynamicType: Default,
+    F: FnOnce(),
+{
+    let (before, after) = revisions_around(client, workload, label_selector, change);
+    if after != before {
+        panic!(
+            "The change of [{}] produced a new revision; the revisions are {:?} before and {:?} after the change.",
+            workload.name(),
+            before,
+            after
+        );
+    }
+}
+
+/// Returns the revisions of the pods before the given change and after
+/// the subsequent rollout of the workload.
+///
+/// The change is often applied asynchronously, e.g. by an operator
+/// which updates the workload after its custom resource was changed.
+/// Therefore the rollout is only awaited after the generation of the
+/// workload increased or the revisions of the pods changed, or after the
+/// `verify_status` timeout of the client expired.
+fn revisions_around<K, F>(
+    client: &TestKubeClient,
+    workload: &K,
+    label_selector: &str,
+    change: F,
+) -> (BTreeSet<String>, BTreeSet<String>)
+where
+    K: Clone + Debug + DeserializeOwned + Resource + Rollout,
+    <K as Resource>::DynamicType: Default,
+    F: FnOnce(),
+{
+    let get_generation = || {
+        client
+            .find_namespaced::<K>(&workload.name())
+            .and_then(|workload| workload.meta().generation)
+    };
+
+    let generation_before = get_generation();
+    let before = get_revision_hashes(&client.list_labeled::<Pod>(label_selector).items);
+    change();
+
+    let timeout = client.kube_client().timeouts.verify_status;
+    let start = Instant::now();
+    while start.elapsed() < timeout
+        && get_generation() <= generation_before
+        && get_revision_hashes(&client.list_labeled::<Pod>(label_selector).items) == before
+    {
+        thread::sleep(Duration::from_secs(1));
+    }
+
+    client.wait_for_rollout(workload);
+    let after = get_revision_hashes(&client.list_labeled::<Pod>(label_selector).items);
+    (before, after)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test::kube::from_yaml;
+    use spectral::prelude::*;
+
+    #[test]
+    fn deployment_should_be_rolled_out_if_all_replicas_are_updated_and_available() {
+        let mut deployment: Deployment = from_yaml(
+            "
+            metadata:
+              name: test
+              generation: 2
+            spec:
+              replicas: 2
+              selector: {}
+              template: {}
+            status:
+              observedGeneration: 2
+              replicas: 2
+              updatedReplicas: 2
+              availableReplicas: 2
+            ",
+        );
+        assert_that(&deployment.is_rolled_out()).is_true();
+
+        deployment.status.as_mut().unwrap().replicas = Some(3);
+        assert_that(&deployment.is_rolled_out()).is_false();
+
+        deployment.metadata.generation = Some(3);
+        deployment.status.as_mut().unwrap().replicas = Some(2);
+        assert_that(&deployment.is_rolled_out()).is_false();
+    }
+
+    #[test]
+    fn stateful_set_should_be_rolled_out_if_the_update_revision_is_current() {
+        let mut stateful_set: StatefulSet = from_yaml(
+            "
+            metadata:
+              name: test
+              generation: 1
+            spec:
+              replicas: 3
+              selector: {}
+              serviceName: test
+              template: {}
+            status:
+              observedGeneration: 1
+              replicas: 3
+              readyReplicas: 3
+              updatedReplicas: 1
+              currentRevision: test-1
+              updateRevision: test-2
+            ",
+        );
+        assert_that(&stateful_set.is_rolled_out()).is_false();
+
+        stateful_set.spec.as_mut().unwrap().update_strategy =
+            Some(from_yaml("rollingUpdate: { partition: 2 }"));
+        assert_that(&stateful_set.is_rolled_out()).is_true();
+    }
+
+    #[test]
+    fn revision_hashes_should_be_taken_from_the_labels_of_running_pods() {
+        let pod = |labels: &str, terminating: bool| -> Pod {
+            let mut pod: Pod = from_yaml(&format!(
+                "
+                metadata:
+                  name: test
+                  labels: {}
+                ",
+                labels
+            ));
+            if terminating {
+                pod.metadata.deletion_timestamp = Some(from_yaml("2021-10-01T10:00:00Z"));
+            }
+            pod
+        };
+        let pods = [
+            pod("{ controller-revision-hash: test-5d8f7c }", false),
+            pod("{ pod-template-hash: 7b9c6d }", false),
+            pod("{ controller-revision-hash: test-4c7e6b }", true),
+            pod("{}", false),
+        ];
+
+        assert_that(&get_revision_hash(&pods[0])).contains_value("test-5d8f7c");
+        assert_that(&get_revision_hashes(&pods)).is_equal_to(
+            vec![String::from("7b9c6d"), String::from("test-5d8f7c")]
+                .into_iter()
+                .collect::<BTreeSet<_>>(),
+        );
+    }
+
+    #[test]
+    fn daemon_set_should_be_rolled_out_if_all_pods_are_updated_and_available() {
+        let daemon_set: DaemonSet = from_yaml(
+            "
+            metadata:
+              name: test
+            status:
+              currentNumberScheduled: 2
+              desiredNumberScheduled: 2
+              numberMisscheduled: 0
+              numberReady: 2
+              updatedNumberScheduled: 2
+              numberAvailable: 1
+            ",
+        );
+        assert_that(&daemon_set.is_rolled_out()).is_false();
+    }
+}
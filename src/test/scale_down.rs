@@ -0,0 +1,386 @@
+//! Verification of controlled scale-downs
+//!
+//! Controllers remove pods in a defined order when a workload is scaled
+//! down. StatefulSets remove the pods with the highest ordinals first.
+//! ReplicaSets prefer pods which are not ready and then pods with a
+//! lower `controller.kubernetes.io/pod-deletion-cost`, which operators
+//! set to protect e.g. the leader of a cluster.
+//!
+//! ```rust,no_run
+//! use integration_test_commons::test::prelude::*;
+//! use integration_test_commons::test::scale_down::verify_scale_down;
+//!
+//! let client = TestKubeClient::new();
+//! verify_scale_down(&client, "app.kubernetes.io/instance=simple", 2, || {
+//!     // reduce the replicas of the cluster to 2
+//! });
+//! ```
+
+use super::kube::get_pod_conditions;
+use super::prelude::TestKubeClient;
+use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Annotation which ranks the pods of a ReplicaSet for the scale-down
+pub const POD_DELETION_COST_ANNOTATION: &str = "controller.kubernetes.io/pod-deletion-cost";
+
+/// Executes the given scale-down of the pods matching the label
+/// selector and verifies that the controller removed the expected pods
+/// until only the given number of replicas is left.
+///
+/// The removal order of the pods of every controller is determined with
+/// [`removal_order`] before the scale-down. For every controller, the
+/// removed pods must be the first ones in its removal order. Pods with
+/// the same rank, e.g. pods of a ReplicaSet which were created in the
+/// same second with the same readiness and deletion cost, are
+/// interchangeable because the controller picks any of them. Pods which
+/// are terminating count as removed. The removal is awaited within the
+/// rollout timeout of the client.
+pub fn verify_scale_down<F>(
+    client: &TestKubeClient,
+    label_selector: &str,
+    replicas: usize,
+    scale_down: F,
+) where
+    F: FnOnce(),
+{
+    let pods = client.list_labeled::<Pod>(label_selector).items;
+    let removal_orders = removal_order(&pods);
+
+    scale_down();
+
+    let timeout = client.kube_client().timeouts.rollout;
+    let start = Instant::now();
+    let removed = loop {
+        let remaining = client
+            .list_labeled::<Pod>(label_selector)
+            .items
+            .into_iter()
+            .filter(|pod| pod.metadata.deletion_timestamp.is_none())
+            .collect::<Vec<_>>();
+        if remaining.len() <= replicas {
+            let remaining = pod_names(&remaining);
+            break pod_names(&pods)
+                .into_iter()
+                .filter(|name| !remaining.contains(name))
+                .collect::<Vec<_>>();
+        }
+        if start.elapsed() > timeout {
+            panic!(
+                "[{}] was not scaled down to {} replica(s) within {} seconds; {} pod(s) remain.",
+                label_selector,
+                replicas,
+                timeout.as_secs(),
+                remaining.len()
+            );
+        }
+        thread::sleep(Duration::from_secs(1));
+    };
+
+    for (controller, order) in &removal_orders {
+        if !is_expected_removal(order, &removed) {
+            let names = pod_names(order);
+            let removal_count = names.iter().filter(|name| removed.contains(name)).count();
+            let mut expected_removed = names[..removal_count].to_vec();
+            expected_removed.sort_unstable();
+            let mut actually_removed = names
+                .into_iter()
+                .filter(|name| removed.contains(name))
+                .collect::<Vec<_>>();
+            actually_removed.sort_unstable();
+
+            panic!(
+                "The scale-down of [{}] removed the pods {:?} of the controller [{}] but the pods {:?} or pods with the same rank were expected to be removed.",
+                label_selector, actually_removed, controller, expected_removed
+            );
+        }
+    }
+}
+
+/// Returns true if the removed pods are the first ones in the given
+/// removal order of a controller, whereby pods with the same rank as
+/// the last expected pod are interchangeable.
+fn is_expected_removal(order: &[Pod], removed: &[String]) -> bool {
+    let is_removed = |pod: &Pod| {
+        pod.metadata
+            .name
+            .as_ref()
+            .is_some_and(|name| removed.contains(name))
+    };
+    let removal_count = order.iter().filter(|pod| is_removed(pod)).count();
+    let last_expected = match removal_count.checked_sub(1) {
+        Some(index) => &order[index],
+        None => return true,
+    };
+
+    let is_stateful_set = order.iter().all(is_stateful_set_pod);
+    let threshold = removal_rank(last_expected, is_stateful_set);
+    order.iter().all(
+        |pod| match removal_rank(pod, is_stateful_set).cmp(&threshold) {
+            Ordering::Less => is_removed(pod),
+            Ordering::Equal => true,
+            Ordering::Greater => !is_removed(pod),
+        },
+    )
+}
+
+/// Returns the given pods grouped by the UID of their controller in the
+/// order in which the controller removes them on a scale-down.
+///
+/// Pods of StatefulSets are ordered by descending ordinal. Other pods
+/// are ordered like ReplicaSets do for scheduled pods: pods which are
+/// not ready first, then by ascending deletion cost, and then the
+/// newest pods first. Pods without a controller are grouped under an
+/// empty UID.
+pub fn removal_order(pods: &[Pod]) -> BTreeMap<String, Vec<Pod>> {
+    let mut pods_by_controller = BTreeMap::<String, Vec<Pod>>::new();
+    for pod in pods {
+        pods_by_controller
+            .entry(controller_uid(pod).unwrap_or_default())
+            .or_default()
+            .push(pod.clone());
+    }
+    for pods in pods_by_controller.values_mut() {
+        sort_by_removal_order(pods);
+    }
+    pods_by_controller
+}
+
+/// Sorts the pods of one controller in the order in which the
+/// controller removes them.
+fn sort_by_removal_order(pods: &mut [Pod]) {
+    let is_stateful_set = pods.iter().all(is_stateful_set_pod);
+    pods.sort_by_key(|pod| removal_rank(pod, is_stateful_set));
+}
+
+/// Rank of a pod in the removal order of its controller
+///
+/// Pods with a lower rank are removed first. Pods with the same rank
+/// are removed in an arbitrary order.
+#[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum RemovalRank {
+    StatefulSet {
+        ordinal: Reverse<Option<u32>>,
+    },
+    ReplicaSet {
+        ready: bool,
+        deletion_cost: i32,
+        creation_timestamp: Reverse<Option<Time>>,
+    },
+}
+
+fn removal_rank(pod: &Pod, is_stateful_set: bool) -> RemovalRank {
+    if is_stateful_set {
+        RemovalRank::StatefulSet {
+            ordinal: Reverse(ordinal(pod)),
+        }
+    } else {
+        RemovalRank::ReplicaSet {
+            ready: is_ready(pod),
+            deletion_cost: deletion_cost(pod),
+            creation_timestamp: Reverse(pod.metadata.creation_timestamp.clone()),
+        }
+    }
+}
+
+fn pod_names(pods: &[Pod]) -> Vec<String> {
+    pods.iter()
+        .map(|pod| pod.metadata.name.clone().unwrap_or_default())
+        .collect()
+}
+
+fn controller_uid(pod: &Pod) -> Option<String> {
+    pod.metadata
+        .owner_references
+        .iter()
+        .flatten()
+        .find(|owner| owner.controller == Some(true))
+        .map(|owner| owner.uid.clone())
+}
+
+fn is_stateful_set_pod(pod: &Pod) -> bool {
+    pod.metadata
+        .owner_references
+        .iter()
+        .flatten()
+        .any(|owner| owner.kind == "StatefulSet")
+}
+
+/// Returns the ordinal of a pod of a StatefulSet, i.e. the number after
+/// the last hyphen of its name.
+fn ordinal(pod: &Pod) -> Option<u32> {
+    pod.metadata
+        .name
+        .as_deref()
+        .and_then(|name| name.rsplit('-').next())
+        .and_then(|ordinal| ordinal.parse().ok())
+}
+
+fn deletion_cost(pod: &Pod) -> i32 {
+    pod.metadata
+        .annotations
+        .as_ref()
+        .and_then(|annotations| annotations.get(POD_DELETION_COST_ANNOTATION))
+        .and_then(|cost| cost.parse().ok())
+        .unwrap_or_default()
+}
+
+fn is_ready(pod: &Pod) -> bool {
+    get_pod_conditions(pod)
+        .iter()
+        .any(|condition| condition.type_ == "Ready" && condition.status == "True")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test::kube::from_yaml;
+    use spectral::prelude::*;
+
+    fn pod(name: &str, owner_kind: &str, deletion_cost: i32, ready: bool) -> Pod {
+        owned_pod(name, owner_kind, "1", deletion_cost, ready)
+    }
+
+    fn created_pod(name: &str, creation_timestamp: &str) -> Pod {
+        let mut pod = pod(name, "ReplicaSet", 0, true);
+        pod.metadata.creation_timestamp = Some(from_yaml(&format!("'{}'", creation_timestamp)));
+        pod
+    }
+
+    fn owned_pod(
+        name: &str,
+        owner_kind: &str,
+        owner_uid: &str,
+        deletion_cost: i32,
+        ready: bool,
+    ) -> Pod {
+        from_yaml(&format!(
+            "
+            metadata:
+              name: {name}
+              annotations:
+                controller.kubernetes.io/pod-deletion-cost: '{deletion_cost}'
+              ownerReferences:
+                - apiVersion: apps/v1
+                  kind: {owner_kind}
+                  name: owner
+                  uid: '{owner_uid}'
+                  controller: true
+            status:
+              conditions:
+                - type: Ready
+                  status: '{ready}'
+            ",
+            name = name,
+            owner_kind = owner_kind,
+            owner_uid = owner_uid,
+            deletion_cost = deletion_cost,
+            ready = if ready { "True" } else { "False" }
+        ))
+    }
+
+    #[test]
+    fn removal_order_should_start_with_the_highest_ordinal_of_stateful_sets() {
+        let pods = [
+            pod("zk-2", "StatefulSet", 0, true),
+            pod("zk-10", "StatefulSet", 0, true),
+            pod("zk-1", "StatefulSet", 100, true),
+        ];
+
+        assert_that(&pod_names(&removal_order(&pods)["1"])).is_equal_to(vec![
+            String::from("zk-10"),
+            String::from("zk-2"),
+            String::from("zk-1"),
+        ]);
+    }
+
+    #[test]
+    fn removal_order_should_consider_readiness_and_deletion_cost_of_replica_sets() {
+        let pods = [
+            pod("leader", "ReplicaSet", 100, true),
+            pod("follower", "ReplicaSet", 0, true),
+            pod("starting", "ReplicaSet", 100, false),
+        ];
+
+        assert_that(&pod_names(&removal_order(&pods)["1"])).is_equal_to(vec![
+            String::from("starting"),
+            String::from("follower"),
+            String::from("leader"),
+        ]);
+    }
+
+    #[test]
+    fn removal_order_should_be_determined_per_controller() {
+        let pods = [
+            owned_pod("server-0", "StatefulSet", "1", 0, true),
+            owned_pod("server-1", "StatefulSet", "1", 0, true),
+            owned_pod("gateway", "ReplicaSet", "2", 0, true),
+            owned_pod("gateway-new", "ReplicaSet", "2", 0, false),
+        ];
+
+        let orders = removal_order(&pods)
+            .iter()
+            .map(|(controller, pods)| (controller.clone(), pod_names(pods)))
+            .collect::<Vec<_>>();
+
+        assert_that(&orders).is_equal_to(vec![
+            (
+                String::from("1"),
+                vec![String::from("server-1"), String::from("server-0")],
+            ),
+            (
+                String::from("2"),
+                vec![String::from("gateway-new"), String::from("gateway")],
+            ),
+        ]);
+    }
+
+    #[test]
+    fn pods_with_the_same_rank_should_be_interchangeable_on_removal() {
+        let order = removal_order(&[
+            created_pod("old", "2021-10-01T12:00:00Z"),
+            created_pod("new-a", "2021-10-01T12:00:10Z"),
+            created_pod("new-b", "2021-10-01T12:00:10Z"),
+            created_pod("new-c", "2021-10-01T12:00:10Z"),
+        ])
+        .remove("1")
+        .unwrap();
+        let removed = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_that(&is_expected_removal(&order, &removed(&["new-a"]))).is_true();
+        assert_that(&is_expected_removal(&order, &removed(&["new-c"]))).is_true();
+        assert_that(&is_expected_removal(&order, &removed(&["new-b", "new-c"]))).is_true();
+        assert_that(&is_expected_removal(
+            &order,
+            &removed(&["new-a", "new-b", "new-c"]),
+        ))
+        .is_true();
+        assert_that(&is_expected_removal(&order, &removed(&[]))).is_true();
+        assert_that(&is_expected_removal(&order, &removed(&["old"]))).is_false();
+        assert_that(&is_expected_removal(&order, &removed(&["old", "new-a"]))).is_false();
+    }
+
+    #[test]
+    fn stateful_set_pods_should_be_removed_in_the_exact_order() {
+        let order = removal_order(&[
+            pod("zk-0", "StatefulSet", 0, true),
+            pod("zk-1", "StatefulSet", 0, true),
+            pod("zk-2", "StatefulSet", 0, true),
+        ])
+        .remove("1")
+        .unwrap();
+
+        assert_that(&is_expected_removal(&order, &[String::from("zk-2")])).is_true();
+        assert_that(&is_expected_removal(&order, &[String::from("zk-1")])).is_false();
+    }
+}
@@ -0,0 +1,105 @@
+//! Fixture which is shared by several test cases
+
+use std::sync::{Arc, Mutex, Weak};
+
+/// A fixture which is shared by the test cases of a process
+///
+/// The fixture is constructed on first use and shared via [`Arc`] by
+/// all test cases which use it concurrently. It is torn down, i.e.
+/// dropped, when the last user releases it. A later user constructs it
+/// again.
+///
+/// Expensive fixtures like a ZooKeeper cluster can be shared this way
+/// across test cases which run in parallel threads.
+///
+/// The fixture is not shared across processes. If the tests are run by
+/// cargo-nextest, which runs every test case in its own process, then
+/// every test case constructs its own fixture. Cluster-wide setup should
+/// use [`bootstrap_once_in_cluster`] instead.
+///
+/// [`bootstrap_once_in_cluster`]: super::bootstrap::bootstrap_once_in_cluster
+///
+/// ```rust,no_run
+/// use integration_test_commons::test::prelude::*;
+///
+/// static CLIENT: SharedFixture<TestKubeClient> = SharedFixture::new(TestKubeClient::new);
+///
+/// #[test]
+/// fn test() {
+///     let client = CLIENT.get();
+///     // use the client
+/// }
+/// ```
+pub struct SharedFixture<T> {
+    construct: fn() -> T,
+    instance: Mutex<Weak<T>>,
+}
+
+impl<T> SharedFixture<T> {
+    /// Creates a shared fixture which is constructed with the given
+    /// function on first use.
+    pub const fn new(construct: fn() -> T) -> Self {
+        SharedFixture {
+            construct,
+            instance: Mutex::new(Weak::new()),
+        }
+    }
+
+    /// Returns the fixture and constructs it if it is not in use.
+    ///
+    /// Concurrent callers block until the fixture is constructed.
+    pub fn get(&self) -> Arc<T> {
+        let mut instance = self
+            .instance
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        instance.upgrade().unwrap_or_else(|| {
+            let fixture = Arc::new((self.construct)());
+            *instance = Arc::downgrade(&fixture);
+            fixture
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use spectral::prelude::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static CONSTRUCTED: AtomicUsize = AtomicUsize::new(0);
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Fixture;
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    static FIXTURE: SharedFixture<Fixture> = SharedFixture::new(|| {
+        CONSTRUCTED.fetch_add(1, Ordering::SeqCst);
+        Fixture
+    });
+
+    #[test]
+    fn fixture_should_be_shared_and_torn_down_after_the_last_use() {
+        let first = FIXTURE.get();
+        let second = FIXTURE.get();
+
+        assert_that(&Arc::ptr_eq(&first, &second)).is_true();
+        assert_that(&CONSTRUCTED.load(Ordering::SeqCst)).is_equal_to(1);
+
+        drop(first);
+        assert_that(&DROPPED.load(Ordering::SeqCst)).is_equal_to(0);
+
+        drop(second);
+        assert_that(&DROPPED.load(Ordering::SeqCst)).is_equal_to(1);
+
+        let _third = FIXTURE.get();
+        assert_that(&CONSTRUCTED.load(Ordering::SeqCst)).is_equal_to(2);
+    }
+}
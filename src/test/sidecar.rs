@@ -0,0 +1,30 @@
+//! Tolerance for sidecars injected by service meshes
+//!
+//! Service meshes like Istio or Linkerd inject sidecar containers into
+//! pods. Test cases which verify the containers of generated pods must
+//! ignore these sidecars so that they can run on meshed clusters.
+//! Fixture pods which must terminate, e.g. pods which run a single
+//! command, must disable the injection because the sidecar would keep
+//! them running.
+
+/// Names of the containers which are injected by common service meshes
+pub const INJECTED_SIDECARS: &[&str] = &[
+    "istio-init",
+    "istio-proxy",
+    "istio-validation",
+    "linkerd-init",
+    "linkerd-proxy",
+];
+
+/// Returns true if the container with the given name is injected by a
+/// service mesh.
+pub fn is_injected_sidecar(container_name: &str) -> bool {
+    INJECTED_SIDECARS.contains(&container_name)
+}
+
+/// Annotations which disable the sidecar injection of common service
+/// meshes
+pub const SIDECAR_INJECTION_DISABLED: &[(&str, &str)] = &[
+    ("sidecar.istio.io/inject", "false"),
+    ("linkerd.io/inject", "disabled"),
+];
@@ -0,0 +1,130 @@
+//! Skipping of test cases which cannot run in the current cluster
+//!
+//! The test harness of Rust does not support skipping test cases at
+//! runtime. A skipped test case therefore passes, but the skip is
+//! printed and recorded in `skipped.jsonl` in the directory given by
+//! `INTEGRATION_TEST_ARTIFACTS_DIR`, so that reports can distinguish
+//! test cases which could not run from broken ones.
+//!
+//! [`skip!`] returns early from the test function:
+//!
+//! ```rust,no_run
+//! use integration_test_commons::skip;
+//! use integration_test_commons::test::prelude::*;
+//!
+//! let client = TestKubeClient::new();
+//! if !client.is_apiservice_available("v1beta1.metrics.k8s.io") {
+//!     skip!("The metrics-server is not installed");
+//! }
+//! ```
+//!
+//! Helper functions which decide deeper in the call stack use
+//! [`skip()`]. It panics with a recognizable payload which is turned
+//! into a skip by [`skippable`].
+
+use super::environment::ARTIFACTS_DIR_ENV_VAR;
+use serde_json::json;
+use std::any::Any;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::{env, panic, thread};
+
+/// Prefix of the panic message of a skipped test case
+pub const SKIPPED_PREFIX: &str = "SKIPPED: ";
+
+/// File name of the skipped test cases in the artifacts directory
+pub const SKIPPED_FILE_NAME: &str = "skipped.jsonl";
+
+/// Reports the test case as skipped with the given reason and returns
+/// from the enclosing function.
+///
+/// The arguments are formatted like the ones of [`format!`].
+#[macro_export]
+macro_rules! skip {
+    ($($arg:tt)+) => {{
+        $crate::test::skip::report_skipped(&format!($($arg)+));
+        return;
+    }};
+}
+
+/// Reports the test case as skipped with the given reason and panics
+/// with a payload which is recognized by [`skippable`].
+pub fn skip(reason: &str) -> ! {
+    report_skipped(reason);
+    panic::panic_any(format!("{}{}", SKIPPED_PREFIX, reason))
+}
+
+/// Executes the given test body and passes if it was skipped with
+/// [`skip()`].
+///
+/// Other panics are propagated.
+pub fn skippable<F>(test: F)
+where
+    F: FnOnce() + panic::UnwindSafe,
+{
+    if let Err(payload) = panic::catch_unwind(test) {
+        if skip_reason(payload.as_ref()).is_none() {
+            panic::resume_unwind(payload);
+        }
+    }
+}
+
+/// Returns the reason if the given panic payload was raised by
+/// [`skip()`].
+pub fn skip_reason(payload: &(dyn Any + Send)) -> Option<&str> {
+    let message = payload
+        .downcast_ref::<String>()
+        .map(String::as_str)
+        .or_else(|| payload.downcast_ref::<&str>().copied())?;
+    message.strip_prefix(SKIPPED_PREFIX)
+}
+
+/// Prints the skip of the current test case and records it in the
+/// artifacts directory if `INTEGRATION_TEST_ARTIFACTS_DIR` is set.
+///
+/// The name of the test case is taken from the name of the current
+/// thread which is set by the test harness.
+pub fn report_skipped(reason: &str) {
+    let thread = thread::current();
+    let test_name = thread.name().unwrap_or("<unnamed>");
+    println!("Test [{}] is skipped: {}", test_name, reason);
+
+    if let Ok(directory) = env::var(ARTIFACTS_DIR_ENV_VAR) {
+        let path = PathBuf::from(directory).join(SKIPPED_FILE_NAME);
+        let record = json!({ "test": test_name, "reason": reason });
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| writeln!(file, "{}", record));
+        if let Err(error) = written {
+            eprintln!(
+                "Skip could not be recorded in [{}]: {}",
+                path.display(),
+                error
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn skip_reason_should_recognize_the_payload_of_skip() {
+        let payload = panic::catch_unwind(|| skip("no GPU nodes")).unwrap_err();
+
+        assert_that(&skip_reason(payload.as_ref())).contains_value("no GPU nodes");
+        assert_that(&skip_reason(&String::from("assertion failed"))).is_none();
+    }
+
+    #[test]
+    #[should_panic(expected = "assertion failed")]
+    fn skippable_should_propagate_other_panics() {
+        skippable(|| panic!("assertion failed"));
+    }
+}
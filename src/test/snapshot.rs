@@ -0,0 +1,235 @@
+//! Snapshots of the cluster state
+
+use super::prelude::TestKubeClient;
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, StatefulSet};
+use k8s_openapi::api::core::v1::{ConfigMap, PersistentVolumeClaim, Pod, Secret, Service};
+use kube::{Resource, ResourceExt};
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug};
+
+/// Identifier of a resource in a snapshot
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ResourceId {
+    pub kind: String,
+    pub namespace: Option<String>,
+    pub name: String,
+}
+
+impl fmt::Display for ResourceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.namespace {
+            Some(namespace) => write!(f, "{}/{}/{}", self.kind, namespace, self.name),
+            None => write!(f, "{}/{}", self.kind, self.name),
+        }
+    }
+}
+
+/// Version of a resource in a snapshot
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct ResourceVersion {
+    uid: Option<String>,
+    resource_version: Option<String>,
+}
+
+/// The state of the resources matching a label selector at a point in
+/// time
+///
+/// Two snapshots can be compared to assert precisely which resources
+/// an operator changed, e.g. in response to an edit of a custom
+/// resource.
+///
+/// ```rust,no_run
+/// use integration_test_commons::test::prelude::*;
+///
+/// let client = TestKubeClient::new();
+/// let before = ClusterSnapshot::take(&client, "app.kubernetes.io/instance=simple");
+/// // edit the custom resource
+/// let after = ClusterSnapshot::take(&client, "app.kubernetes.io/instance=simple");
+///
+/// let diff = before.diff(&after);
+/// assert_that(&diff.modified).has_length(1);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ClusterSnapshot {
+    resources: BTreeMap<ResourceId, ResourceVersion>,
+}
+
+impl ClusterSnapshot {
+    /// Takes a snapshot of the common resources generated by operators,
+    /// i.e. StatefulSets, Deployments, DaemonSets, Services,
+    /// ConfigMaps, Secrets, PersistentVolumeClaims, and Pods, which
+    /// match the given label selector.
+    ///
+    /// Further kinds can be added with [`ClusterSnapshot::add`].
+    pub fn take(client: &TestKubeClient, label_selector: &str) -> Self {
+        let mut snapshot = ClusterSnapshot::default();
+        snapshot.add::<StatefulSet>(client, label_selector);
+        snapshot.add::<Deployment>(client, label_selector);
+        snapshot.add::<DaemonSet>(client, label_selector);
+        snapshot.add::<Service>(client, label_selector);
+        snapshot.add::<ConfigMap>(client, label_selector);
+        snapshot.add::<Secret>(client, label_selector);
+        snapshot.add::<PersistentVolumeClaim>(client, label_selector);
+        snapshot.add::<Pod>(client, label_selector);
+        snapshot
+    }
+
+    /// Adds the resources of the given kind which match the label
+    /// selector to the snapshot.
+    pub fn add<K>(&mut self, client: &TestKubeClient, label_selector: &str)
+    where
+        K: Clone + Debug + DeserializeOwned + Resource<DynamicType = ()>,
+    {
+        for resource in client.list_labeled::<K>(label_selector).items {
+            self.insert(&resource);
+        }
+    }
+
+    fn insert<K>(&mut self, resource: &K)
+    where
+        K: Resource<DynamicType = ()>,
+    {
+        let id = ResourceId {
+            kind: K::kind(&()).into_owned(),
+            namespace: resource.namespace(),
+            name: resource.name(),
+        };
+        let version = ResourceVersion {
+            uid: resource.meta().uid.clone(),
+            resource_version: resource.resource_version(),
+        };
+        self.resources.insert(id, version);
+    }
+
+    /// Returns the resources of this snapshot.
+    pub fn resources(&self) -> Vec<&ResourceId> {
+        self.resources.keys().collect()
+    }
+
+    /// Compares this snapshot with a later one.
+    ///
+    /// A resource which was deleted and recreated with the same name is
+    /// reported as deleted and created.
+    pub fn diff(&self, later: &ClusterSnapshot) -> SnapshotDiff {
+        let mut diff = SnapshotDiff::default();
+
+        for (id, version) in &self.resources {
+            match later.resources.get(id) {
+                None => diff.deleted.push(id.clone()),
+                Some(later_version) if later_version.uid != version.uid => {
+                    diff.deleted.push(id.clone());
+                    diff.created.push(id.clone());
+                }
+                Some(later_version) if later_version != version => diff.modified.push(id.clone()),
+                Some(_) => {}
+            }
+        }
+
+        for id in later.resources.keys() {
+            if !self.resources.contains_key(id) {
+                diff.created.push(id.clone());
+            }
+        }
+
+        diff.created.sort();
+        diff
+    }
+}
+
+/// Differences between two snapshots
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SnapshotDiff {
+    pub created: Vec<ResourceId>,
+    pub deleted: Vec<ResourceId>,
+    pub modified: Vec<ResourceId>,
+}
+
+impl SnapshotDiff {
+    /// Returns true if no resources were created, deleted, or modified.
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty() && self.deleted.is_empty() && self.modified.is_empty()
+    }
+}
+
+impl fmt::Display for SnapshotDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let changes = [
+            ("created", &self.created),
+            ("deleted", &self.deleted),
+            ("modified", &self.modified),
+        ];
+        for (change, ids) in changes {
+            for id in ids {
+                writeln!(f, "{} {}", change, id)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use indoc::indoc;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+    use spectral::prelude::*;
+
+    fn config_map(name: &str, uid: &str, resource_version: &str) -> ConfigMap {
+        ConfigMap {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                namespace: Some(String::from("default")),
+                uid: Some(uid.to_string()),
+                resource_version: Some(resource_version.to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn id(name: &str) -> ResourceId {
+        ResourceId {
+            kind: String::from("ConfigMap"),
+            namespace: Some(String::from("default")),
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn diff_should_report_created_deleted_and_modified_resources() {
+        let mut before = ClusterSnapshot::default();
+        before.insert(&config_map("unchanged", "1", "1"));
+        before.insert(&config_map("modified", "2", "1"));
+        before.insert(&config_map("deleted", "3", "1"));
+        before.insert(&config_map("recreated", "4", "1"));
+
+        let mut after = ClusterSnapshot::default();
+        after.insert(&config_map("unchanged", "1", "1"));
+        after.insert(&config_map("modified", "2", "2"));
+        after.insert(&config_map("recreated", "5", "2"));
+        after.insert(&config_map("created", "6", "1"));
+
+        let diff = before.diff(&after);
+
+        assert_that(&diff).is_equal_to(SnapshotDiff {
+            created: vec![id("created"), id("recreated")],
+            deleted: vec![id("deleted"), id("recreated")],
+            modified: vec![id("modified")],
+        });
+        assert_that(&before.diff(&before).is_empty()).is_true();
+        assert_that(&diff.to_string()).is_equal_to(String::from(indoc! {"
+            created ConfigMap/default/created
+            created ConfigMap/default/recreated
+            deleted ConfigMap/default/deleted
+            deleted ConfigMap/default/recreated
+            modified ConfigMap/default/modified
+        "}));
+    }
+
+    #[test]
+    fn resource_id_should_be_displayed_with_kind_namespace_and_name() {
+        assert_that(&id("test").to_string()).is_equal_to(String::from("ConfigMap/default/test"));
+    }
+}
@@ -0,0 +1,124 @@
+//! Server-side table representation of resources
+//!
+//! The API server transforms lists of resources into tables as printed
+//! by `kubectl get`, including the additional printer columns of custom
+//! resources and columns like `AGE` or `READY`.
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+/// Media type which requests the table representation from the API
+/// server
+pub const TABLE_MEDIA_TYPE: &str = "application/json;as=Table;v=v1;g=meta.k8s.io";
+
+/// A table of resources as printed by `kubectl get`
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Table {
+    /// Names of the columns, e.g. `Name` or `Age`
+    pub columns: Vec<String>,
+    /// Rendered cells of the rows
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Parses the table returned by the API server.
+    pub fn from_value(value: &Value) -> Result<Self> {
+        let columns = value["columnDefinitions"]
+            .as_array()
+            .ok_or_else(|| anyhow!("Response is not a table: {}", value))?
+            .iter()
+            .map(|column| column["name"].as_str().unwrap_or_default().to_owned())
+            .collect();
+
+        let rows = value["rows"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|row| {
+                row["cells"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(render_cell)
+                    .collect()
+            })
+            .collect();
+
+        Ok(Table { columns, rows })
+    }
+
+    /// Returns the cells of the column with the given name, compared
+    /// case-insensitively.
+    pub fn column(&self, name: &str) -> Option<Vec<&str>> {
+        let index = self.column_index(name)?;
+        Some(
+            self.rows
+                .iter()
+                .map(|row| row.get(index).map(String::as_str).unwrap_or_default())
+                .collect(),
+        )
+    }
+
+    /// Returns the cell in the given column of the row of the resource
+    /// with the given name.
+    pub fn cell(&self, resource_name: &str, column: &str) -> Option<&str> {
+        let name_index = self.column_index("Name")?;
+        let index = self.column_index(column)?;
+        self.rows
+            .iter()
+            .find(|row| row.get(name_index).map(String::as_str) == Some(resource_name))
+            .and_then(|row| row.get(index))
+            .map(String::as_str)
+    }
+
+    fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns
+            .iter()
+            .position(|column| column.eq_ignore_ascii_case(name))
+    }
+}
+
+fn render_cell(cell: &Value) -> String {
+    match cell {
+        Value::String(string) => string.clone(),
+        Value::Null => String::from("<none>"),
+        cell => cell.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json::json;
+    use spectral::prelude::*;
+
+    #[test]
+    fn table_should_be_parsed_from_the_response() {
+        let table = Table::from_value(&json!({
+            "kind": "Table",
+            "apiVersion": "meta.k8s.io/v1",
+            "columnDefinitions": [
+                { "name": "Name", "type": "string" },
+                { "name": "Ready", "type": "string" },
+                { "name": "Restarts", "type": "integer" },
+                { "name": "Age", "type": "string" }
+            ],
+            "rows": [
+                { "cells": ["zookeeper-0", "1/1", 0, "5m"] },
+                { "cells": ["zookeeper-1", "0/1", 2, "5m"] }
+            ]
+        }))
+        .unwrap();
+
+        assert_that(&table.column("READY")).contains_value(vec!["1/1", "0/1"]);
+        assert_that(&table.cell("zookeeper-1", "Restarts")).contains_value("2");
+        assert_that(&table.cell("zookeeper-2", "Restarts")).is_none();
+        assert_that(&table.column("Status")).is_none();
+    }
+
+    #[test]
+    fn table_should_not_be_parsed_from_other_responses() {
+        assert_that(&Table::from_value(&json!({ "kind": "PodList" }))).is_err();
+    }
+}
@@ -1,10 +1,15 @@
 //! Resource which is deleted when it goes out of scope
 
 use super::prelude::TestKubeClient;
+use super::rng::TestRng;
+use k8s_openapi::api::core::v1::{ConfigMap, Secret};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
 use kube::Resource;
 use serde::{de::DeserializeOwned, Serialize};
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::{mem, ops::Deref};
+use uuid::Uuid;
 
 /// Trait combo which must be satisfied for a resource to be deletable
 pub trait DeletableResource:
@@ -52,3 +57,71 @@ impl<'a, T: DeletableResource> Deref for TemporaryResource<'a, T> {
         &self.resource
     }
 }
+
+/// A temporary secret which is deleted when it goes out of scope
+pub type TemporarySecret<'a> = TemporaryResource<'a, Secret>;
+
+/// A temporary config map which is deleted when it goes out of scope
+pub type TemporaryConfigMap<'a> = TemporaryResource<'a, ConfigMap>;
+
+impl<'a> TemporaryResource<'a, Secret> {
+    /// Creates a new temporary secret with a random value for each of
+    /// the given keys.
+    ///
+    /// A UUID is appended to `name_prefix` to obtain a unique name. The
+    /// generated values are returned so that they can be used in
+    /// assertions later on.
+    pub fn random(
+        client: &'a TestKubeClient,
+        name_prefix: &str,
+        keys: &[&str],
+    ) -> (Self, BTreeMap<String, String>) {
+        let values = keys
+            .iter()
+            .map(|key| (key.to_string(), TestRng::uuid().to_simple().to_string()))
+            .collect::<BTreeMap<_, _>>();
+
+        let secret = Secret {
+            metadata: unique_metadata(name_prefix),
+            string_data: Some(values.clone()),
+            ..Default::default()
+        };
+
+        (Self::from_resource(client, &secret), values)
+    }
+}
+
+impl<'a> TemporaryResource<'a, ConfigMap> {
+    /// Creates a new temporary config map containing the given data.
+    ///
+    /// A UUID is appended to `name_prefix` to obtain a unique name.
+    pub fn from_map(
+        client: &'a TestKubeClient,
+        name_prefix: &str,
+        data: BTreeMap<String, String>,
+    ) -> Self {
+        let config_map = ConfigMap {
+            metadata: unique_metadata(name_prefix),
+            data: Some(data),
+            ..Default::default()
+        };
+
+        Self::from_resource(client, &config_map)
+    }
+}
+
+impl<'a, T: DeletableResource + Serialize> TemporaryResource<'a, T> {
+    /// Creates a new temporary resource from the given resource object.
+    fn from_resource(client: &'a TestKubeClient, resource: &T) -> Self {
+        let spec = serde_yaml::to_string(resource).expect("Resource could not be serialized");
+        Self::new(client, &spec)
+    }
+}
+
+/// Returns metadata with a unique name starting with the given prefix.
+fn unique_metadata(name_prefix: &str) -> ObjectMeta {
+    ObjectMeta {
+        name: Some(format!("{}-{}", name_prefix, Uuid::new_v4())),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,307 @@
+//! Timeline of the events in the cluster
+//!
+//! Flaky tests often fail because of race conditions which are hard to
+//! reconstruct from the logs of the test runner. The timeline merges the
+//! Events of all namespaces with the state transitions of the pods, e.g.
+//! conditions which changed or containers which were started or
+//! terminated, into one chronologically ordered file. Credentials in the
+//! messages of the Events are redacted:
+//!
+//! ```rust,no_run
+//! use chrono::Utc;
+//! use integration_test_commons::test::prelude::*;
+//! use integration_test_commons::test::timeline::export_event_timeline_artifact;
+//!
+//! let client = TestKubeClient::new();
+//! let start = Utc::now();
+//! // run the test
+//! export_event_timeline_artifact(&client, start).unwrap();
+//! ```
+
+use super::environment::ARTIFACTS_DIR_ENV_VAR;
+use super::kube::get_pod_conditions;
+use super::prelude::TestKubeClient;
+use super::redaction::redact_text;
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use k8s_openapi::api::core::v1::{ContainerStateTerminated, Event, Pod};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::{env, thread};
+
+/// An Event or a state transition of a pod at a point in time
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TimelineEntry {
+    pub time: DateTime<Utc>,
+    /// Kind, namespace, and name of the involved object, e.g.
+    /// `Pod/default/simple-0`
+    pub object: String,
+    pub description: String,
+}
+
+impl fmt::Display for TimelineEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.time.to_rfc3339_opts(SecondsFormat::Micros, true),
+            self.object,
+            self.description
+        )
+    }
+}
+
+/// Gathers the Events and the state transitions of the pods in all
+/// namespaces which occurred since the given time and writes them in
+/// chronological order into the given file.
+pub fn export_event_timeline(
+    client: &TestKubeClient,
+    since: DateTime<Utc>,
+    path: &Path,
+) -> Result<()> {
+    let entries = timeline(
+        &client.list_all_namespaces::<Event>().items,
+        &client.list_all_namespaces::<Pod>().items,
+        since,
+    );
+
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    let content = entries
+        .iter()
+        .map(|entry| format!("{}\n", entry))
+        .collect::<String>();
+    fs::write(path, content)?;
+    Ok(())
+}
+
+/// Exports the timeline since the given time into the artifacts
+/// directory if `INTEGRATION_TEST_ARTIFACTS_DIR` is set and returns the
+/// path of the written file.
+///
+/// The file is named after the current test case, e.g.
+/// `timeline-zookeeper.scale_up.log`.
+pub fn export_event_timeline_artifact(
+    client: &TestKubeClient,
+    since: DateTime<Utc>,
+) -> Result<Option<PathBuf>> {
+    let directory = match env::var(ARTIFACTS_DIR_ENV_VAR) {
+        Ok(directory) => PathBuf::from(directory),
+        Err(_) => return Ok(None),
+    };
+
+    let thread = thread::current();
+    let test_name = thread.name().unwrap_or("unnamed").replace("::", ".");
+    let path = directory.join(format!("timeline-{}.log", test_name));
+    export_event_timeline(client, since, &path)?;
+    Ok(Some(path))
+}
+
+/// Merges the given Events and the state transitions of the given pods
+/// which occurred since the given time into a chronologically ordered
+/// timeline.
+pub fn timeline(events: &[Event], pods: &[Pod], since: DateTime<Utc>) -> Vec<TimelineEntry> {
+    let mut entries = events
+        .iter()
+        .filter_map(event_entry)
+        .chain(pods.iter().flat_map(pod_entries))
+        .filter(|entry| entry.time >= since)
+        .collect::<Vec<_>>();
+    entries.sort_by_key(|entry| entry.time);
+    entries
+}
+
+fn event_entry(event: &Event) -> Option<TimelineEntry> {
+    let time = event
+        .last_timestamp
+        .as_ref()
+        .map(|time| time.0)
+        .or_else(|| event.event_time.as_ref().map(|time| time.0))
+        .or_else(|| event.first_timestamp.as_ref().map(|time| time.0))?;
+    let object = &event.involved_object;
+
+    let mut description = format!(
+        "{} {}: {}",
+        event.type_.as_deref().unwrap_or("Normal"),
+        event.reason.as_deref().unwrap_or_default(),
+        redact_text(event.message.as_deref().unwrap_or_default())
+    );
+    if let Some(count) = event.count.filter(|count| *count > 1) {
+        description.push_str(&format!(" (x{})", count));
+    }
+
+    Some(TimelineEntry {
+        time,
+        object: object_name(
+            object.kind.as_deref().unwrap_or_default(),
+            object.namespace.as_deref(),
+            object.name.as_deref().unwrap_or_default(),
+        ),
+        description,
+    })
+}
+
+fn pod_entries(pod: &Pod) -> Vec<TimelineEntry> {
+    let mut transitions = Vec::new();
+
+    if let Some(time) = &pod.metadata.creation_timestamp {
+        transitions.push((time.0, String::from("created")));
+    }
+    for condition in get_pod_conditions(pod) {
+        if let Some(time) = &condition.last_transition_time {
+            let mut description = format!("condition {} is {}", condition.type_, condition.status);
+            if let Some(reason) = &condition.reason {
+                description.push_str(&format!(" ({})", reason));
+            }
+            transitions.push((time.0, description));
+        }
+    }
+
+    let status = pod.status.clone().unwrap_or_default();
+    let container_statuses = status
+        .init_container_statuses
+        .iter()
+        .flatten()
+        .chain(status.container_statuses.iter().flatten());
+    for container_status in container_statuses {
+        let name = &container_status.name;
+        if let Some(state) = &container_status.state {
+            if let Some(time) = state
+                .running
+                .as_ref()
+                .and_then(|running| running.started_at.as_ref())
+            {
+                transitions.push((time.0, format!("container [{}] is running", name)));
+            }
+            if let Some(transition) = state.terminated.as_ref().and_then(|t| termination(name, t)) {
+                transitions.push(transition);
+            }
+        }
+        let last_termination = container_status
+            .last_state
+            .as_ref()
+            .and_then(|last_state| last_state.terminated.as_ref());
+        if let Some(transition) = last_termination.and_then(|t| termination(name, t)) {
+            transitions.push(transition);
+        }
+    }
+
+    if let Some(time) = &pod.metadata.deletion_timestamp {
+        transitions.push((time.0, String::from("grace period of the deletion ends")));
+    }
+
+    let object = object_name(
+        "Pod",
+        pod.metadata.namespace.as_deref(),
+        pod.metadata.name.as_deref().unwrap_or_default(),
+    );
+    transitions
+        .into_iter()
+        .map(|(time, description)| TimelineEntry {
+            time,
+            object: object.clone(),
+            description,
+        })
+        .collect()
+}
+
+fn termination(
+    container_name: &str,
+    terminated: &ContainerStateTerminated,
+) -> Option<(DateTime<Utc>, String)> {
+    let time = terminated.finished_at.as_ref()?;
+    let mut description = format!(
+        "container [{}] terminated with exit code {}",
+        container_name, terminated.exit_code
+    );
+    if let Some(reason) = &terminated.reason {
+        description.push_str(&format!(" ({})", reason));
+    }
+    Some((time.0, description))
+}
+
+fn object_name(kind: &str, namespace: Option<&str>, name: &str) -> String {
+    match namespace {
+        Some(namespace) => format!("{}/{}/{}", kind, namespace, name),
+        None => format!("{}/{}", kind, name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test::kube::from_yaml;
+    use indoc::indoc;
+    use spectral::prelude::*;
+
+    #[test]
+    fn timeline_should_merge_events_and_pod_transitions_in_chronological_order() {
+        let event: Event = from_yaml(indoc! {"
+            metadata:
+              name: simple-0.event
+            involvedObject:
+              kind: Pod
+              namespace: default
+              name: simple-0
+            type: Warning
+            reason: BackOff
+            message: Back-off restarting failed container
+            count: 3
+            lastTimestamp: 2021-10-01T10:00:30Z
+        "});
+        let pod: Pod = from_yaml(indoc! {"
+            metadata:
+              name: simple-0
+              namespace: default
+              creationTimestamp: 2021-10-01T09:59:00Z
+            status:
+              conditions:
+                - type: Ready
+                  status: 'False'
+                  reason: ContainersNotReady
+                  lastTransitionTime: 2021-10-01T10:00:10Z
+              containerStatuses:
+                - name: zookeeper
+                  image: zookeeper
+                  imageID: ''
+                  ready: false
+                  restartCount: 1
+                  state:
+                    running:
+                      startedAt: 2021-10-01T10:00:40Z
+                  lastState:
+                    terminated:
+                      exitCode: 1
+                      reason: Error
+                      finishedAt: 2021-10-01T10:00:20Z
+        "});
+        let since = "2021-10-01T10:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        let timeline = timeline(&[event], &[pod], since)
+            .iter()
+            .map(TimelineEntry::to_string)
+            .collect::<Vec<_>>();
+
+        assert_that(&timeline).is_equal_to(vec![
+            String::from(
+                "2021-10-01T10:00:10.000000Z Pod/default/simple-0 \
+                condition Ready is False (ContainersNotReady)",
+            ),
+            String::from(
+                "2021-10-01T10:00:20.000000Z Pod/default/simple-0 \
+                container [zookeeper] terminated with exit code 1 (Error)",
+            ),
+            String::from(
+                "2021-10-01T10:00:30.000000Z Pod/default/simple-0 \
+                Warning BackOff: Back-off restarting failed container (x3)",
+            ),
+            String::from(
+                "2021-10-01T10:00:40.000000Z Pod/default/simple-0 \
+                container [zookeeper] is running",
+            ),
+        ]);
+    }
+}
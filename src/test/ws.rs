@@ -0,0 +1,144 @@
+//! Checks of WebSocket endpoints
+//!
+//! Some products expose WebSocket endpoints, e.g. the NiFi UI, which
+//! cannot be validated with plain HTTP requests. A [`WebSocket`] is
+//! opened through a port forwarding to the pod, so the handshake and
+//! the first messages can be asserted.
+//!
+//! ```rust,no_run
+//! use integration_test_commons::test::prelude::*;
+//! use integration_test_commons::test::ws::WebSocket;
+//!
+//! let client = TestKubeClient::new();
+//! let pod = client.find_namespaced::<Pod>("nifi-0").unwrap();
+//!
+//! let mut web_socket = WebSocket::open(&client, &pod, 8080, "/nifi-api/client-dashboard");
+//! let message = web_socket.receive_text(Duration::from_secs(10));
+//! assert_that(&message).contains("connected");
+//! ```
+
+use super::port_forward::PortForward;
+use super::prelude::TestKubeClient;
+use anyhow::{anyhow, Result};
+use futures::{SinkExt, TryStreamExt};
+use k8s_openapi::api::core::v1::Pod;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+/// A WebSocket connection to a port of a pod
+///
+/// The connection and the port forwarding are closed when this value
+/// is dropped.
+pub struct WebSocket<'a> {
+    client: &'a TestKubeClient,
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    _port_forward: PortForward,
+}
+
+impl<'a> WebSocket<'a> {
+    /// Forwards the given port of the pod and performs the WebSocket
+    /// handshake with the given path.
+    ///
+    /// Panics if the handshake fails.
+    pub fn open(client: &'a TestKubeClient, pod: &Pod, port: u16, path: &str) -> Self {
+        let port_forward = client.port_forward(pod, port);
+        let url = web_socket_url(port_forward.local_address(), path);
+
+        let stream = client
+            .block_on(async { tokio_tungstenite::connect_async(&url).await })
+            .map(|(stream, _)| stream)
+            .unwrap_or_else(|error| panic!("WebSocket handshake with [{}] failed: {}", url, error));
+
+        WebSocket {
+            client,
+            stream,
+            _port_forward: port_forward,
+        }
+    }
+
+    /// Sends the given text message.
+    pub fn send_text(&mut self, text: &str) {
+        let stream = &mut self.stream;
+        self.client
+            .block_on(async { stream.send(Message::Text(text.to_string())).await })
+            .expect("WebSocket message could not be sent");
+    }
+
+    /// Waits for the next text or binary message within the given
+    /// timeout and returns it.
+    ///
+    /// Binary messages are decoded as UTF-8. Control messages are
+    /// skipped.
+    pub fn receive_text(&mut self, timeout: Duration) -> String {
+        let stream = &mut self.stream;
+        self.client
+            .block_on(async { receive_text(stream, timeout).await })
+            .expect("WebSocket message was not received")
+    }
+
+    /// Verifies that the next message fulfills the given predicate.
+    pub fn verify_next_message<P>(&mut self, timeout: Duration, predicate: P)
+    where
+        P: Fn(&str) -> bool,
+    {
+        let message = self.receive_text(timeout);
+        assert!(
+            predicate(&message),
+            "WebSocket message [{}] does not match",
+            message
+        );
+    }
+}
+
+/// Returns the URL of the WebSocket endpoint with the given path at the
+/// given local address.
+fn web_socket_url(local_address: SocketAddr, path: &str) -> String {
+    format!("ws://{}/{}", local_address, path.trim_start_matches('/'))
+}
+
+/// Receives the next text or binary message within the given timeout.
+async fn receive_text(
+    stream: &mut WebSocketStream<MaybeTlsStream<TcpStream>>,
+    timeout: Duration,
+) -> Result<String> {
+    let receive = async {
+        while let Some(message) = stream.try_next().await? {
+            match message {
+                Message::Text(text) => return Ok(text),
+                Message::Binary(data) => return Ok(String::from_utf8_lossy(&data).into_owned()),
+                Message::Close(frame) => {
+                    return Err(anyhow!("WebSocket was closed: {:?}", frame));
+                }
+                Message::Ping(_) | Message::Pong(_) => {}
+            }
+        }
+        Err(anyhow!("WebSocket was closed"))
+    };
+
+    tokio::time::timeout(timeout, receive).await.map_err(|_| {
+        anyhow!(
+            "No WebSocket message received within {} seconds.",
+            timeout.as_secs()
+        )
+    })?
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn web_socket_url_should_combine_the_local_address_and_the_path() {
+        let local_address = "127.0.0.1:40000".parse().unwrap();
+
+        assert_that(&web_socket_url(local_address, "/ws"))
+            .is_equal_to(String::from("ws://127.0.0.1:40000/ws"));
+        assert_that(&web_socket_url(local_address, "ws"))
+            .is_equal_to(String::from("ws://127.0.0.1:40000/ws"));
+    }
+}